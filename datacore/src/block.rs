@@ -1,50 +1,107 @@
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Cursor, Read};
 use std::mem::size_of;
 
+use crate::keys::SCHEME_SIGNATURE_LENGTH;
+
 /// Byte length of the [Signature].
-pub const SIGNATURE_LENGTH: usize = 2 * ed25519_compact::Signature::BYTES;
+pub const SIGNATURE_LENGTH: usize = 2 * SCHEME_SIGNATURE_LENGTH;
 
-/// [Signature] holds 2 [Block] [ed25519_compact::Signature]s:
+/// [Signature] holds 2 [Block] signatures:
 /// - `data` - signature for the block data
 /// - `tree` - signature for the block position in the merkle tree
+///
+/// The `tree` signature is optional for `Core`s with checkpoints,
+/// where only checkpoint blocks sign the tree.
+///
+/// Signatures are stored as raw bytes,
+/// interpreted according to the [SignatureScheme] of the `Core`.
+///
+/// The `data` signature of a `KeyRotation` block signs
+/// [Hash::from_key_rotation] of its data instead of the leaf [Hash],
+/// its [Signature] is marked with [Signature::into_key_rotation].
+/// The mark is kept in the header of the `Core`, not in the [Block].
+///
+/// [SignatureScheme]: crate::SignatureScheme
+/// [Hash]: crate::Hash
+/// [Hash::from_key_rotation]: crate::Hash::from_key_rotation
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Signature {
-    data: ed25519_compact::Signature,
-    tree: ed25519_compact::Signature,
+    data: [u8; SCHEME_SIGNATURE_LENGTH],
+    tree: Option<[u8; SCHEME_SIGNATURE_LENGTH]>,
+    key_rotation: bool,
 }
 impl Signature {
     /// Create a new [Signature].
     #[must_use]
     #[inline]
-    pub fn new(data: ed25519_compact::Signature, tree: ed25519_compact::Signature) -> Self {
-        Self { data, tree }
+    pub fn new(
+        data: [u8; SCHEME_SIGNATURE_LENGTH],
+        tree: [u8; SCHEME_SIGNATURE_LENGTH],
+        ) -> Self
+    {
+        Self {
+            data,
+            tree: Some(tree),
+            key_rotation: false,
+        }
     }
-    /// Create a new [Signature].
+    /// Create a new [Signature] without a tree signature.
     #[must_use]
     #[inline]
-    pub fn from_bytes(
-        data: [u8; ed25519_compact::Signature::BYTES],
-        tree: [u8; ed25519_compact::Signature::BYTES],
-        ) -> Self
-    {
+    pub fn without_tree(data: [u8; SCHEME_SIGNATURE_LENGTH]) -> Self {
+        Self {
+            data,
+            tree: None,
+            key_rotation: false,
+        }
+    }
+    /// Create a new [Signature] from byte slices.
+    /// An empty `tree` slice means there is no tree signature.
+    #[inline]
+    pub fn from_bytes(data: &[u8], tree: &[u8]) -> Result<Self> {
+        ensure!(data.len() == SCHEME_SIGNATURE_LENGTH, "Invalid data signature length.");
+        ensure!(
+            tree.is_empty() || tree.len() == SCHEME_SIGNATURE_LENGTH,
+            "Invalid tree signature length."
+        );
+        Ok(Self {
+            data: data.try_into()?,
+            tree: match tree.is_empty() {
+                true => None,
+                false => Some(tree.try_into()?),
+            },
+            key_rotation: false,
+        })
+    }
+    /// Mark the [Signature] of a `KeyRotation` block.
+    #[must_use]
+    #[inline]
+    pub fn into_key_rotation(self) -> Self {
         Self {
-            data: ed25519_compact::Signature::from_slice(&data).unwrap(),
-            tree: ed25519_compact::Signature::from_slice(&tree).unwrap(),
+            key_rotation: true,
+            ..self
         }
     }
 
     /// Get data [Signature].
     #[must_use]
-    pub fn data(&self) -> &ed25519_compact::Signature {
+    pub fn data(&self) -> &[u8; SCHEME_SIGNATURE_LENGTH] {
         &self.data
     }
 
-    /// Get tree [Signature].
+    /// Get tree [Signature], if present.
     #[must_use]
-    pub fn tree(&self) -> &ed25519_compact::Signature {
-        &self.tree
+    pub fn tree(&self) -> Option<&[u8; SCHEME_SIGNATURE_LENGTH]> {
+        self.tree.as_ref()
+    }
+
+    /// Check if the [Signature] is of a `KeyRotation` block.
+    #[must_use]
+    #[inline]
+    pub fn is_key_rotation(&self) -> bool {
+        self.key_rotation
     }
 }
 
@@ -60,6 +117,8 @@ pub struct Block {
 }
 
 pub const BLOCK_LENGTH: usize = size_of::<u64>() + size_of::<u32>() + SIGNATURE_LENGTH;
+/// Byte length of a [Block] without a tree signature.
+pub const BLOCK_WITHOUT_TREE_LENGTH: usize = BLOCK_LENGTH - SCHEME_SIGNATURE_LENGTH;
 
 impl Block {
     /// Create a new [Block].
@@ -80,27 +139,34 @@ impl Block {
 
         data.write_u64::<LittleEndian>(self.offset)?;
         data.write_u32::<LittleEndian>(self.length)?;
-        data.extend_from_slice(self.signature.data.as_slice());
-        data.extend_from_slice(self.signature.tree.as_slice());
+        data.extend_from_slice(&self.signature.data);
+        if let Some(tree) = &self.signature.tree {
+            data.extend_from_slice(tree);
+        }
 
         Ok(data)
     }
     /// Deserialize [Block].
     #[inline]
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        ensure!(
+            data.len() == BLOCK_LENGTH || data.len() == BLOCK_WITHOUT_TREE_LENGTH,
+            "Invalid block length."
+        );
         let mut rdr = Cursor::new(data);
         let offset = rdr.read_u64::<LittleEndian>()?;
         let length = rdr.read_u32::<LittleEndian>()?;
 
-        let mut data_signature = [0u8; ed25519_compact::Signature::BYTES];
+        let mut data_signature = [0u8; SCHEME_SIGNATURE_LENGTH];
         rdr.read_exact(&mut data_signature)?;
-        let mut tree_signature = [0u8; ed25519_compact::Signature::BYTES];
-        rdr.read_exact(&mut tree_signature)?;
-
-        let signature = Signature::new(
-            ed25519_compact::Signature::from_slice(&data_signature)?,
-            ed25519_compact::Signature::from_slice(&tree_signature)?,
-        );
+        let signature = match data.len() {
+            BLOCK_LENGTH => {
+                let mut tree_signature = [0u8; SCHEME_SIGNATURE_LENGTH];
+                rdr.read_exact(&mut tree_signature)?;
+                Signature::new(data_signature, tree_signature)
+            }
+            _ => Signature::without_tree(data_signature),
+        };
 
         Ok(Self {
             offset,
@@ -136,8 +202,8 @@ mod tests {
     #[test]
     pub fn to_bytes_from_bytes() -> Result<()> {
         let signature = Signature::new(
-            ed25519_compact::Signature::from_slice(&[2u8; ed25519_compact::Signature::BYTES])?,
-            ed25519_compact::Signature::from_slice(&[7u8; ed25519_compact::Signature::BYTES])?,
+            [2u8; SCHEME_SIGNATURE_LENGTH],
+            [7u8; SCHEME_SIGNATURE_LENGTH],
         );
         let block = Block::new(1, 8, signature);
         let block2 = Block::from_bytes(&block.to_bytes()?)?;
@@ -147,8 +213,8 @@ mod tests {
     #[test]
     pub fn from_bytes_fails_on_incomplete_input() -> Result<()> {
         let signature = Signature::new(
-            ed25519_compact::Signature::from_slice(&[2u8; ed25519_compact::Signature::BYTES])?,
-            ed25519_compact::Signature::from_slice(&[7u8; ed25519_compact::Signature::BYTES])?,
+            [2u8; SCHEME_SIGNATURE_LENGTH],
+            [7u8; SCHEME_SIGNATURE_LENGTH],
         );
         let block = Block::new(1, 8, signature);
         let result = Block::from_bytes(&block.to_bytes()?[1..]);
@@ -156,12 +222,37 @@ mod tests {
         Ok(())
     }
     #[test]
+    pub fn to_bytes_from_bytes_without_tree() -> Result<()> {
+        let signature = Signature::without_tree([2u8; SCHEME_SIGNATURE_LENGTH]);
+        let block = Block::new(1, 8, signature);
+        let bytes = block.to_bytes()?;
+        assert_eq!(bytes.len(), BLOCK_WITHOUT_TREE_LENGTH);
+        assert_eq!(Block::from_bytes(&bytes)?, block);
+        Ok(())
+    }
+    #[test]
     pub fn get_signatures() -> Result<()> {
-        let data = ed25519_compact::Signature::from_slice(&[2u8; ed25519_compact::Signature::BYTES])?;
-        let tree = ed25519_compact::Signature::from_slice(&[7u8; ed25519_compact::Signature::BYTES])?;
+        let data = [2u8; SCHEME_SIGNATURE_LENGTH];
+        let tree = [7u8; SCHEME_SIGNATURE_LENGTH];
         let signature = Signature::new(data, tree);
         assert_eq!(*signature.data(), data);
-        assert_eq!(*signature.tree(), tree);
+        assert_eq!(signature.tree(), Some(&tree));
+        assert_eq!(Signature::without_tree(data).tree(), None);
+        assert!(!signature.is_key_rotation());
+        let rotation = signature.clone().into_key_rotation();
+        assert!(rotation.is_key_rotation());
+        assert_ne!(rotation, signature);
+        Ok(())
+    }
+    #[test]
+    pub fn signature_from_bytes() -> Result<()> {
+        let data = [2u8; SCHEME_SIGNATURE_LENGTH];
+        let tree = [7u8; SCHEME_SIGNATURE_LENGTH];
+        let signature = Signature::from_bytes(&data, &tree)?;
+        assert_eq!(signature, Signature::new(data, tree));
+        assert!(Signature::from_bytes(&data[1..], &tree).is_err());
+        assert!(Signature::from_bytes(&data, &tree[1..]).is_err());
+        assert_eq!(Signature::from_bytes(&data, &[])?, Signature::without_tree(data));
         Ok(())
     }
 }
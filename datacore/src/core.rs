@@ -1,17 +1,32 @@
 //! Main `Core` abstraction.
 //! Exposes an append-only, single-writer, secure log structure.
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::ops::Range;
 
-use crate::merkle::Merkle;
-use crate::merkle_tree_stream::Node;
-use crate::store::Store;
-use crate::{sign, verify, Block, Hash, IndexAccess, PublicKey, SecretKey, Signature};
+use crate::fork::{ForkError, ForkProof};
+use crate::head::{verify_signed_head, SignedHead};
+use crate::header::Header;
+use crate::merkle::{hash_roots, parent_nodes, root_indexes, Merkle};
+use crate::merkle_tree_stream::flat_tree;
+use crate::proof::{consistency_indexes, ConsistencyProof, Proof};
+use crate::rotation::{verify_key_rotation, KeyRotation};
+use crate::schema::{sign_schema, verify_schema};
+use crate::snapshot::Snapshot;
+use crate::stats::Stats;
+use crate::store::{ClearedError, Content, InvalidStateError, Store};
+use crate::{
+    Block, Hash, IndexAccess, Node, NodeTrait, PublicKey, Signature, SignatureScheme, Signer,
+    Verifier, VerifyError, ZeroizingKey, SCHEME_SIGNATURE_LENGTH,
+};
 
 /// Maximum number of blocks of data in a `Core`.
 pub const MAX_CORE_LENGTH: usize = (u32::MAX - 1) as usize;
 /// Maximum size of a single block of data in a `Core`.
 pub const MAX_BLOCK_SIZE: usize = u32::MAX as usize;
+/// Maximum number of blocks from one checkpoint to the next.
+/// Replicas refuse longer runs of blocks without a tree signature.
+pub const MAX_CHECKPOINT_INTERVAL: u32 = 1024;
 
 /// Core is an append-only, single-writer, secure log structure.
 ///
@@ -19,12 +34,29 @@ pub const MAX_BLOCK_SIZE: usize = u32::MAX as usize;
 /// to write to a `Core` you must also have its [SecretKey].
 /// The [SecretKey] should not be shared unless you know what you're doing
 /// as only one client should be able to write to a single `Core`.
+/// The `Core` keeps it in a [ZeroizingKey], wiped from memory on drop.
 /// If 2 separate clients write conflicting information to the same `Core`
 /// it will become corrupted.
 ///
 /// The feed needs an implementation of [RandomAccess] as a storage backing
 /// for the entries added to it.
 ///
+/// Blocks are signed with `Ed25519` by default,
+/// use [Core::with_scheme] to plug in a different [Signer] and [Verifier].
+///
+/// By default every block signs the whole tree.
+/// With [Core::enable_checkpoints] only checkpoint blocks do,
+/// the blocks in between are authenticated by a [Proof]
+/// against the next checkpoint.
+///
+/// A compromised [SecretKey] can be replaced with [Core::rotate],
+/// blocks after the [KeyRotation] are signed by the new key.
+///
+/// Reads take `&self` and can run concurrently,
+/// only writes need exclusive access to the `Core`.
+/// [IndexAccess::read] takes `&mut self` though,
+/// so the reads of the storage itself still run one at a time.
+///
 /// [SecretKey]: ed25519_dalek::SecretKey
 /// [PublicKey]: ed25519_dalek::PublicKey
 /// [RandomAccess]: random_access_storage::RandomAccess
@@ -33,10 +65,14 @@ pub struct Core<T> {
 
     merkle: Merkle,
     public_key: PublicKey,
-    secret_key: Option<SecretKey>,
+    secret_key: Option<ZeroizingKey>,
+    verifier: Box<dyn Verifier>,
+    signer: Option<Box<dyn Signer>>,
+    header: Header,
 
     length: u32,
     byte_length: u64,
+    checkpoint: u32,
 }
 impl<T> Core<T> {
     /// Get the number of entries in the `Core`.
@@ -49,13 +85,127 @@ impl<T> Core<T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+    /// Get the byte length of all entries in the `Core`.
+    #[inline]
+    pub fn byte_len(&self) -> u64 {
+        self.byte_length
+    }
     /// Access the [PublicKey].
     pub fn public_key(&self) -> &PublicKey {
         &self.public_key
     }
-    /// Access the optional [SecretKey].
-    pub fn secret_key(&self) -> &Option<SecretKey> {
-        &self.secret_key
+    /// Access the optional [SecretKey](crate::SecretKey),
+    /// use [ZeroizingKey::expose] to sign with it.
+    pub fn secret_key(&self) -> Option<&ZeroizingKey> {
+        self.secret_key.as_ref()
+    }
+    /// Get the [PublicKey] signing new blocks,
+    /// the key of the latest [KeyRotation] or the [Core::public_key].
+    #[inline]
+    pub fn writer_key(&self) -> &PublicKey {
+        match self.header.rotations().last() {
+            Some(rotation) => rotation.public_key(),
+            None => &self.public_key,
+        }
+    }
+    /// Get the [KeyRotation]s of the `Core`, ordered by index.
+    #[inline]
+    pub fn rotations(&self) -> &[KeyRotation] {
+        self.header.rotations()
+    }
+    /// Check if the block at `index` is a [KeyRotation] record.
+    #[inline]
+    pub fn is_key_rotation(&self, index: u32) -> bool {
+        self.header
+            .rotations()
+            .binary_search_by_key(&index, KeyRotation::index)
+            .is_ok()
+    }
+    /// Get the [SignatureScheme] used to sign this `Core`.
+    #[inline]
+    pub fn scheme(&self) -> SignatureScheme {
+        self.verifier.scheme()
+    }
+    /// Get the checkpoint interval, `None` if checkpoints are disabled.
+    #[inline]
+    pub fn checkpoints(&self) -> Option<u32> {
+        self.header.checkpoints()
+    }
+    /// Get the number of entries covered by the latest checkpoint.
+    #[inline]
+    pub fn checkpoint_len(&self) -> u32 {
+        self.checkpoint
+    }
+    /// Take a [Snapshot] of the current length and roots.
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(
+            self.length,
+            self.byte_length,
+            self.checkpoint,
+            self.merkle.roots().clone(),
+        )
+    }
+    /// Get the [Stats] of the `Core`, kept up to date on every write.
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        Stats::new(
+            u64::from(self.length),
+            self.byte_length,
+            self.store.metadata_len(),
+            self.store.cleared_len(),
+        )
+    }
+    /// Get the schema of the block data, if set.
+    #[inline]
+    pub fn schema(&self) -> Option<&str> {
+        self.header.schema()
+    }
+    /// Get the signature of the schema by the [PublicKey] of the `Core`,
+    /// replicated with the blocks.
+    #[must_use]
+    #[inline]
+    pub fn schema_signature(&self) -> Option<&[u8; SCHEME_SIGNATURE_LENGTH]> {
+        self.header.schema_signature()
+    }
+    /// Get the [ForkProof] if the `Core` forked.
+    /// A forked `Core` refuses to append.
+    #[inline]
+    pub fn fork_proof(&self) -> Option<&ForkProof> {
+        self.header.fork()
+    }
+
+    /// Get the [Verifier] of the block at `index`,
+    /// the key of the latest [KeyRotation] before it.
+    fn verifier_at(&self, index: u32) -> &dyn Verifier {
+        writer_verifier(&self.header, self.verifier.as_ref(), index)
+    }
+}
+
+/// Get the [Hash] signed by the data signature of a block,
+/// the data of a [KeyRotation] block is hashed apart from other data.
+fn signed_hash(data: &[u8], rotation: bool) -> Result<Hash> {
+    match rotation {
+        true => Hash::from_key_rotation(data),
+        false => Hash::from_leaf(data),
+    }
+}
+
+/// Get the [Verifier] of the block at `index` of a `Core` with `header`,
+/// `verifier` checks the blocks before the first [KeyRotation].
+fn writer_verifier<'a>(
+    header: &'a Header,
+    verifier: &'a dyn Verifier,
+    index: u32,
+) -> &'a dyn Verifier {
+    let rotation = header
+        .rotations()
+        .iter()
+        .rev()
+        .find(|rotation| rotation.index() < index);
+    match rotation {
+        Some(rotation) => rotation.public_key(),
+        None => verifier,
     }
 }
 impl<T> Core<T>
@@ -67,12 +217,126 @@ where
     pub async fn new(
         store: T,
         public_key: PublicKey,
-        secret_key: Option<SecretKey>,
+        secret_key: Option<ZeroizingKey>,
     ) -> Result<Self> {
+        let signer = secret_key
+            .clone()
+            .map(|secret| Box::new(secret) as Box<dyn Signer>);
+        let mut core = Self::with_scheme(store, public_key, Box::new(public_key), signer).await?;
+        core.secret_key = secret_key;
+        Ok(core)
+    }
+
+    /// Create a new instance with a custom storage backend
+    /// and a custom signature scheme.
+    ///
+    /// `public_key` identifies the `Core`,
+    /// block signatures are checked by `verifier` and created by `signer`.
+    pub async fn with_scheme(
+        store: T,
+        public_key: PublicKey,
+        verifier: Box<dyn Verifier>,
+        signer: Option<Box<dyn Signer>>,
+    ) -> Result<Self> {
+        let scheme = verifier.scheme();
+        if let Some(signer) = &signer {
+            ensure!(
+                signer.scheme() == scheme,
+                "Signer scheme {:?} does not match Verifier scheme {:?}.",
+                signer.scheme(),
+                scheme
+            );
+        }
+
         let mut store = Store::new(store);
 
-        let merkle = store.read_merkle().await?;
+        // a torn state record is recovered from the block records
+        let state = match store.read_merkle().await {
+            Ok(state) => Some(state),
+            Err(err) if err.is::<InvalidStateError>() => None,
+            Err(err) => return Err(err),
+        };
+        let mut header = match store.read_header().await? {
+            Some(header) => {
+                ensure!(
+                    header.scheme() == scheme,
+                    "Core is signed with {:?}, not {:?}.",
+                    header.scheme(),
+                    scheme
+                );
+                header
+            }
+            None => {
+                let length = match &state {
+                    Some((merkle, _)) => merkle.blocks(),
+                    None => bail!("Invalid state record of a Core without a header."),
+                };
+                // cores written before headers existed are always `Ed25519`
+                ensure!(
+                    length == 0 || scheme == SignatureScheme::Ed25519,
+                    "Core is signed with {:?}, not {:?}.",
+                    SignatureScheme::Ed25519,
+                    scheme
+                );
+                let mut header = Header::new(scheme);
+                header.set_tree_nodes(length == 0);
+                store.write_header(&header).await?;
+                header
+            }
+        };
+        let checkpoints = header.checkpoints();
+        store.set_checkpoints(checkpoints.is_some());
+        store.set_tree_nodes(header.tree_nodes());
+
+        let recovered = state.is_none();
+        let (merkle, usage) = match state {
+            Some(state) => state,
+            None => {
+                let merkle = Self::recover(&store, verifier.as_ref(), &header).await?;
+                (merkle, false)
+            }
+        };
         let length: u32 = merkle.blocks();
+        // cores written before tree nodes were stored keep their block layout
+        if length == 0 && !header.tree_nodes() {
+            header.set_tree_nodes(true);
+            store.write_header(&header).await?;
+            store.set_tree_nodes(true);
+        }
+
+        // rotations of blocks lost before the tree was written are dropped
+        let mut rotations = header.rotations().to_vec();
+        let mut kept = 0;
+        for rotation in &rotations {
+            if rotation.index() >= length {
+                break;
+            }
+            let leaf = Hash::from_leaf(&rotation.to_bytes()?)?;
+            match store.read(rotation.index()).await? {
+                Some((content, _)) if content.leaf()? == leaf => kept += 1,
+                _ => break,
+            }
+        }
+        if kept < rotations.len() {
+            rotations.truncate(kept);
+            header.set_rotations(rotations);
+            store.write_header(&header).await?;
+        }
+
+        if !usage {
+            // cores written before the usage was saved are counted once
+            store.scan(length).await?;
+        }
+        if recovered {
+            let mut refetch = header.refetch().to_vec();
+            refetch.retain(|&index| index < length);
+            if refetch != header.refetch() {
+                header.set_refetch(refetch);
+                store.write_header(&header).await?;
+            }
+            store.write_merkle(&merkle).await?;
+        }
+
         let byte_length = match length {
             0 => 0,
             n => {
@@ -84,89 +348,855 @@ where
             }
         };
 
+        // find the latest block signing the tree
+        let mut checkpoint = length;
+        if checkpoints.is_some() {
+            while checkpoint > 0 {
+                match store.read(checkpoint - 1).await? {
+                    Some((_, block)) if block.signature().tree().is_some() => break,
+                    Some(_) => checkpoint -= 1,
+                    None => bail!("Missing expected block."),
+                }
+            }
+        }
+
         Ok(Self {
             store,
             merkle,
             public_key,
-            secret_key,
+            secret_key: None,
+            verifier,
+            signer,
+            header,
             length,
             byte_length,
+            checkpoint,
         })
     }
 
+    /// Rebuild the tree of a `Core` with a torn state record
+    /// from its intact block records, up to the last block
+    /// with a valid tree signature.
+    async fn recover(store: &Store<T>, verifier: &dyn Verifier, header: &Header) -> Result<Merkle> {
+        let mut merkle = Merkle::default();
+        let mut recovered = Merkle::default();
+        for index in 0..MAX_CORE_LENGTH as u32 {
+            let (content, block) = match store.read_intact(index).await? {
+                Some(record) => record,
+                None => break,
+            };
+            merkle.next(content.leaf()?, block.length());
+            if let Some(tree) = block.signature().tree() {
+                let verifier = writer_verifier(header, verifier, index);
+                if verifier.verify(&hash_roots(merkle.roots()), tree).is_err() {
+                    break;
+                }
+                recovered = merkle.clone();
+            }
+        }
+        Ok(recovered)
+    }
+
+    /// Enable checkpoints, only possible while the `Core` is empty.
+    ///
+    /// Every `interval`-th block and every block passed to [Core::checkpoint]
+    /// signs the tree, other blocks only sign their data.
+    /// An `interval` of `0` only creates checkpoints on demand.
+    ///
+    /// Replicas of a `Core` with checkpoints need checkpoints enabled too.
+    /// At least every [MAX_CHECKPOINT_INTERVAL]-th block is a checkpoint.
+    pub async fn enable_checkpoints(&mut self, interval: u32) -> Result<()> {
+        ensure!(
+            self.is_empty() || self.checkpoints().is_some(),
+            "Checkpoints can only be enabled on an empty Core."
+        );
+        ensure!(
+            interval <= MAX_CHECKPOINT_INTERVAL,
+            "Checkpoint interval {} is larger than {}.",
+            interval,
+            MAX_CHECKPOINT_INTERVAL
+        );
+        let mut header = self.header.clone();
+        header.set_checkpoints(Some(interval));
+        self.store.write_header(&header).await?;
+        self.store.set_checkpoints(true);
+        self.header = header;
+        Ok(())
+    }
+
+    /// Record the `schema` of the block data in the header.
+    ///
+    /// The schema can not be changed once set.
+    /// A writer signs it with its [SecretKey](crate::SecretKey) to replicate it,
+    /// on a replica it is the expected schema until a signed one arrives.
+    /// A `Core` with blocks needs [Core::set_schema_with] to check them.
+    pub async fn set_schema(&mut self, schema: &str) -> Result<()> {
+        ensure!(
+            self.length == 0 || self.schema().is_some(),
+            "Core has blocks, they need to be checked against schema {}.",
+            schema
+        );
+        self.set_schema_with(schema, |_| Ok(())).await
+    }
+
+    /// Record the `schema` of the block data in the header,
+    /// after checking the data of every available block with `check`.
+    ///
+    /// Fails on cleared blocks, they can not be checked.
+    pub async fn set_schema_with<F>(&mut self, schema: &str, check: F) -> Result<()>
+    where
+        F: Fn(&[u8]) -> Result<()>,
+    {
+        ensure!(!schema.is_empty(), "Schema can not be empty.");
+        match self.schema() {
+            Some(current) if current == schema => return Ok(()),
+            Some(current) => bail!("Core schema is {}, not {}.", current, schema),
+            None => (),
+        }
+        for index in 0..self.length {
+            if self.is_key_rotation(index) {
+                continue;
+            }
+            let Some((data, _)) = self.get(index).await? else {
+                break;
+            };
+            check(&data)
+                .with_context(|| format!("Block {} does not match schema {}.", index, schema))?;
+        }
+        let signature = match &self.signer {
+            Some(signer) => {
+                ensure!(
+                    self.header.rotations().is_empty(),
+                    "Schema can only be signed with the key of the Core."
+                );
+                Some(sign_schema(signer.as_ref(), &self.public_key, schema).await?)
+            }
+            None => None,
+        };
+        let mut header = self.header.clone();
+        header.set_schema(Some(schema.to_owned()));
+        header.set_schema_signature(signature);
+        self.store.write_header(&header).await?;
+        self.header = header;
+        Ok(())
+    }
+
+    /// Record a `schema` signed by the [PublicKey] of the `Core`,
+    /// e.g. replicated from a peer.
+    ///
+    /// Fails if the `Core` expects a different schema.
+    pub async fn set_signed_schema(
+        &mut self,
+        schema: &str,
+        signature: &[u8; SCHEME_SIGNATURE_LENGTH],
+    ) -> Result<()> {
+        verify_schema(&self.public_key, self.verifier.as_ref(), schema, signature)?;
+        match self.schema() {
+            Some(current) if current != schema => {
+                bail!("Core schema is {}, not the signed {}.", current, schema)
+            }
+            Some(_) if self.schema_signature().is_some() => return Ok(()),
+            _ => (),
+        }
+        let mut header = self.header.clone();
+        header.set_schema(Some(schema.to_owned()));
+        header.set_schema_signature(Some(*signature));
+        self.store.write_header(&header).await?;
+        self.header = header;
+        Ok(())
+    }
+
+    /// Sign the tree at the current length,
+    /// turning the head block into a checkpoint.
+    pub async fn checkpoint(&mut self) -> Result<()> {
+        if self.checkpoint == self.length {
+            return Ok(());
+        }
+        let signer = match &self.signer {
+            Some(signer) => signer,
+            None => bail!("No Signer for Core, cannot checkpoint."),
+        };
+        let index = self.length - 1;
+        let tree_hash = hash_roots(self.merkle.roots());
+        let tree_sign = signer.sign(&tree_hash).await?;
+        self.verifier_at(index).verify(&tree_hash, &tree_sign)?;
+
+        let (content, block, nodes) = self
+            .store
+            .read_record(index)
+            .await?
+            .ok_or_else(|| anyhow!("Missing expected block."))?;
+        self.store.forget(index, &content, &block);
+        let signature = Signature::new(*block.signature().data(), tree_sign);
+        let block = Block::new(block.offset(), block.length(), signature);
+        self.store
+            .write_content(index, &content, &nodes, &block)
+            .await?;
+        self.store.write_merkle(&self.merkle).await?;
+        self.checkpoint = self.length;
+        Ok(())
+    }
+
     /// Append data into the `Core`.
     ///
     /// If `signature` is supplied, the caller is responsible for verifying its
     /// integrity and consistency with the `data`.
+    /// Otherwise the data is signed by the [Signer] of the `Core`,
+    /// and the signatures are checked locally before the block is written.
+    ///
+    /// Signatures are checked with the [Core::writer_key] at the block,
+    /// a block with a [KeyRotation] record rotates it,
+    /// its `signature` is marked with [Signature::into_key_rotation].
+    /// Failed checks of the signatures return a [VerifyError].
     #[inline]
     pub async fn append(&mut self, data: &[u8], signature: Option<Signature>) -> Result<()> {
+        let rotation = signature.as_ref().is_some_and(Signature::is_key_rotation);
+        self.append_block(data, signature, rotation).await?;
+        self.store.write_merkle(&self.merkle).await
+    }
+
+    /// Append a batch of blocks signed by the [Signer] of the `Core`.
+    ///
+    /// The tree is written once after the blocks, so the batch is atomic:
+    /// when appending a block fails, none of the blocks are appended
+    /// and the blocks already written are overwritten by later appends.
+    pub async fn append_batch(&mut self, batch: &[&[u8]]) -> Result<()> {
+        let merkle = self.merkle.clone();
+        let length = self.length;
+        let byte_length = self.byte_length;
+        let checkpoint = self.checkpoint;
+        let header = self.header.clone();
+        let mut written = vec![];
+        let mut result = Ok(());
+        for data in batch {
+            match self.append_block(data, None, false).await {
+                Ok(block) => written.push(block),
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        if result.is_ok() {
+            result = self.store.write_merkle(&self.merkle).await;
+        }
+        if result.is_err() {
+            for ((index, data), block) in (length..).zip(batch).zip(&written) {
+                self.store
+                    .forget(index, &Content::Data(data.to_vec()), block);
+            }
+            self.merkle = merkle;
+            self.length = length;
+            self.byte_length = byte_length;
+            self.checkpoint = checkpoint;
+            // a rotation in the batch is dropped on open, without its block
+            if self.header != header {
+                self.header = header;
+                self.store.write_header(&self.header).await?;
+            }
+        }
+        result
+    }
+
+    /// Rotate the key writing the `Core` to `secret_key`,
+    /// e.g. when the current [SecretKey](crate::SecretKey) is compromised.
+    ///
+    /// Appends a [KeyRotation] record signed by the current [Signer]
+    /// as a checkpoint, the blocks after it are signed by `secret_key`.
+    /// Replicas follow the rotation when they replicate the record.
+    /// Only `Ed25519` `Core`s can rotate keys.
+    pub async fn rotate(&mut self, secret_key: ZeroizingKey) -> Result<()> {
+        let signer = match &self.signer {
+            Some(signer) => signer,
+            None => bail!("No Signer for Core, cannot rotate keys."),
+        };
+        let rotation = KeyRotation::sign(
+            signer.as_ref(),
+            &self.public_key,
+            self.length,
+            secret_key.public_key(),
+        )
+        .await?;
+        self.append_block(&rotation.to_bytes()?, None, true).await?;
+        self.store.write_merkle(&self.merkle).await?;
+        self.signer = Some(Box::new(secret_key.clone()));
+        self.secret_key = Some(secret_key);
+        Ok(())
+    }
+
+    /// Append a block without writing the tree, return the written [Block].
+    ///
+    /// `rotation` marks a [KeyRotation] record, a supplied `signature` has to be marked too.
+    async fn append_block(
+        &mut self,
+        data: &[u8],
+        signature: Option<Signature>,
+        rotation: bool,
+    ) -> Result<Block> {
+        if let Some(proof) = self.fork_proof() {
+            return Err(ForkError::new(proof.clone()).into());
+        }
         let index = self.len();
         let data_length = data.len();
         ensure!(data_length <= MAX_BLOCK_SIZE);
         let data_length = u32::try_from(data_length)?;
+        let rotation = match rotation {
+            true => Some(self.check_rotation(index, data)?),
+            false => None,
+        };
+
+        let data_hash = Hash::from_leaf(data)?;
+        let signed_hash = signed_hash(data, rotation.is_some())?;
+        let leaf = Node::new(2 * u64::from(index), data_hash.clone(), data_length);
+        let nodes = parent_nodes(self.merkle.roots(), &leaf);
 
         // get or try to create the `signature`
-        let signature = if let Some(signature) = signature {
-            let data_hash = Hash::from_leaf(data)?;
-            verify(&self.public_key, &data_hash, signature.data())?;
+        let (signature, merkle) = if let Some(signature) = signature {
+            ensure!(
+                signature.is_key_rotation() == rotation.is_some(),
+                "Signature is not marked as the key rotation it signs."
+            );
+            self.verifier_at(index)
+                .verify(&signed_hash, signature.data())
+                .context(VerifyError::new(index))?;
             let mut merkle = self.merkle.clone();
             merkle.next(data_hash, data_length);
-            verify(&self.public_key, &hash_merkle(&merkle), signature.tree())?;
-            self.merkle = merkle;
-            signature
+            match signature.tree() {
+                Some(tree) => {
+                    let verified = self
+                        .verifier_at(index)
+                        .verify(&hash_roots(merkle.roots()), tree);
+                    if let Err(err) = verified {
+                        // blocks since the last checkpoint can not be trusted
+                        if self.checkpoint < self.length {
+                            self.rollback().await?;
+                        }
+                        return Err(err.context(VerifyError::new(index)));
+                    }
+                }
+                None => {
+                    ensure!(
+                        self.checkpoints().is_some(),
+                        "Missing tree signature, checkpoints are not enabled."
+                    );
+                    ensure!(
+                        index + 1 - self.checkpoint < MAX_CHECKPOINT_INTERVAL,
+                        "Missing tree signature, {} blocks since the last checkpoint.",
+                        MAX_CHECKPOINT_INTERVAL
+                    );
+                    ensure!(rotation.is_none(), "Key rotation does not sign the tree.");
+                }
+            }
+            (signature, merkle)
         } else {
-            let secret = match &self.secret_key {
-                Some(secret) => secret,
-                None => bail!("No SecretKey for Core, cannot append."),
+            let signer = match &self.signer {
+                Some(signer) => signer,
+                None => bail!("No Signer for Core, cannot append."),
             };
-            let data_hash = Hash::from_leaf(data)?;
-            let data_sign = sign(secret, &data_hash);
-            self.merkle.next(data_hash, data_length);
-            let tree_sign = sign(secret, &hash_merkle(&self.merkle));
-            Signature::new(data_sign, tree_sign)
+            let data_sign = signer.sign(&signed_hash).await?;
+            self.verifier_at(index).verify(&signed_hash, &data_sign)?;
+            let mut merkle = self.merkle.clone();
+            merkle.next(data_hash, data_length);
+            let is_checkpoint = match self.checkpoints() {
+                _ if rotation.is_some() => true,
+                _ if index + 1 - self.checkpoint >= MAX_CHECKPOINT_INTERVAL => true,
+                None => true,
+                Some(0) => false,
+                Some(interval) => (index + 1).is_multiple_of(interval),
+            };
+            let signature = if is_checkpoint {
+                let tree_hash = hash_roots(merkle.roots());
+                let tree_sign = signer.sign(&tree_hash).await?;
+                self.verifier_at(index).verify(&tree_hash, &tree_sign)?;
+                Signature::new(data_sign, tree_sign)
+            } else {
+                Signature::without_tree(data_sign)
+            };
+            let signature = match rotation {
+                Some(_) => signature.into_key_rotation(),
+                None => signature,
+            };
+            (signature, merkle)
         };
+        let signs_tree = signature.tree().is_some();
 
         let block = Block::new(self.byte_length, data_length as u32, signature);
 
-        self.store.write(index, data, &block).await?;
-        self.store.write_merkle(&self.merkle).await?;
+        // the rotation is written first, on open it is dropped without its block
+        let mut header = None;
+        if let Some(rotation) = rotation {
+            let mut rotated = self.header.clone();
+            let mut rotations = rotated.rotations().to_vec();
+            rotations.push(rotation);
+            rotated.set_rotations(rotations);
+            self.store.write_header(&rotated).await?;
+            header = Some(rotated);
+        }
+        self.store.write(index, data, &nodes, &block).await?;
+        if let Some(header) = header {
+            self.header = header;
+        }
+        self.merkle = merkle;
         self.byte_length += u64::from(data_length);
         self.length += 1;
+        if signs_tree {
+            self.checkpoint = self.length;
+        }
 
-        Ok(())
+        Ok(block)
+    }
+
+    /// Parse and verify the [KeyRotation] record of the block at `index`.
+    fn check_rotation(&self, index: u32, data: &[u8]) -> Result<KeyRotation> {
+        let rotation = KeyRotation::from_bytes(data)?;
+        ensure!(
+            rotation.index() == index,
+            "Key rotation is for block {}, not {}.",
+            rotation.index(),
+            index
+        );
+        ensure!(
+            rotation.public_key() != self.writer_key(),
+            "Key rotation to the current key."
+        );
+        verify_key_rotation(&self.public_key, self.verifier_at(index), &rotation)
+            .context(VerifyError::new(index))?;
+        Ok(rotation)
     }
 
     /// Get the block of data at the tip of the feed.
     /// This will be the most recently appended block.
     #[inline]
-    pub async fn head(&mut self) -> Result<Option<(Vec<u8>, Signature)>> {
+    pub async fn head(&self) -> Result<Option<(Vec<u8>, Signature)>> {
         match self.len() {
             0 => Ok(None),
             len => self.get(len - 1).await,
         }
     }
     /// Retrieve data for a block at index.
+    ///
+    /// Fails with a [ClearedError] if the block was cleared.
+    /// Replicas only serve blocks covered by a checkpoint,
+    /// the position of later blocks is not authenticated yet.
+    /// The [Signature] of a [KeyRotation] record is marked,
+    /// see [Signature::is_key_rotation].
     #[inline]
-    pub async fn get(&mut self, index: u32) -> Result<Option<(Vec<u8>, Signature)>> {
+    pub async fn get(&self, index: u32) -> Result<Option<(Vec<u8>, Signature)>> {
         ensure!((index as usize) < MAX_CORE_LENGTH);
-        let length = self.len();
+        let length = match self.signer {
+            Some(_) => self.len(),
+            None => self.checkpoint,
+        };
         if index >= length {
             return Ok(None);
         }
-        Ok(self
+        match self.store.read(index).await? {
+            None => Ok(None),
+            Some((Content::Data(data), block)) => {
+                let signature = match self.is_key_rotation(index) {
+                    true => block.signature().clone().into_key_rotation(),
+                    false => block.signature().clone(),
+                };
+                Ok(Some((data, signature)))
+            }
+            Some((Content::Cleared(_), _)) => Err(ClearedError::new(index).into()),
+        }
+    }
+
+    /// Find the block in `blocks` containing the byte at `offset`.
+    ///
+    /// Returns the index of the block and the offset within the block.
+    /// The binary search only parses the `Block`s of the probed records,
+    /// bound `blocks` to where `offset` is expected, e.g. a file.
+    pub async fn seek(&self, offset: u64, blocks: Range<u32>) -> Result<(u32, u32)> {
+        ensure!(
+            offset < self.byte_length,
+            "Offset {} is out of bounds of Core with byte length {}.",
+            offset,
+            self.byte_length
+        );
+        ensure!(
+            !blocks.is_empty() && blocks.end <= self.length,
+            "Invalid range of blocks {:?}.",
+            blocks
+        );
+        // find the last block starting at or before `offset`
+        let (mut low, mut high) = (blocks.start, blocks.end);
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            let block = self
+                .store
+                .read_block(middle)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            if block.offset() <= offset {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        let block = self
             .store
-            .read(index)
+            .read_block(low)
             .await?
-            .map(|(data, block)| (data, block.signature().clone())))
+            .ok_or_else(|| anyhow!("Missing expected block."))?;
+        ensure!(
+            block.offset() <= offset && offset - block.offset() < u64::from(block.length()),
+            "Offset {} is not in blocks {:?}.",
+            offset,
+            blocks
+        );
+        Ok((low, u32::try_from(offset - block.offset())?))
+    }
+
+    /// Clear the data of blocks in `range` to reclaim storage.
+    ///
+    /// The tree and signatures are kept,
+    /// so the `Core` can still be verified and appended to.
+    /// Cleared blocks can be restored with [Core::restore].
+    pub async fn clear(&mut self, range: Range<u32>) -> Result<()> {
+        ensure!(
+            range.end <= self.length,
+            "Core is shorter than {}.",
+            range.end
+        );
+        for index in range {
+            let (content, block, nodes) = self
+                .store
+                .read_record(index)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            if let Content::Data(data) = &content {
+                let leaf = Hash::from_leaf(data)?;
+                self.store.forget(index, &content, &block);
+                self.store
+                    .write_cleared(index, &leaf, &nodes, &block)
+                    .await?;
+            }
+        }
+        self.store.write_merkle(&self.merkle).await
+    }
+
+    /// Compact the storage into the empty `backend` and swap it in,
+    /// reclaiming the space of cleared and rolled back blocks.
+    ///
+    /// The tree is verified before the swap, on error the `Core` is unchanged.
+    /// Returns the previous storage backend.
+    ///
+    /// The swap only happens in memory, the previous storage is not touched.
+    /// To keep the compacted `Core`, callers open it from `backend` later,
+    /// e.g. by renaming its directory over the previous one after the swap.
+    pub async fn compact(&mut self, backend: T) -> Result<T> {
+        self.store
+            .compact(backend, self.length, &self.merkle, &self.header)
+            .await
+    }
+
+    /// Check if the block at index was cleared.
+    pub async fn is_cleared(&self, index: u32) -> Result<bool> {
+        ensure!(index < self.length, "Block {} is not in the Core.", index);
+        match self.store.read(index).await? {
+            Some((content, _)) => Ok(matches!(content, Content::Cleared(_))),
+            None => bail!("Missing expected block."),
+        }
     }
-}
 
-#[inline]
-fn hash_merkle(merkle: &Merkle) -> Hash {
-    let roots = merkle.roots();
-    let hashes = roots.iter().map(Node::hash).collect::<Vec<&Hash>>();
-    let lengths = roots.iter().map(Node::length).collect::<Vec<u32>>();
-    Hash::from_roots(&hashes, &lengths)
+    /// Restore the data of a cleared block,
+    /// `data` is checked against the kept leaf [Hash].
+    pub async fn restore(&mut self, index: u32, data: &[u8]) -> Result<()> {
+        ensure!(index < self.length, "Block {} is not in the Core.", index);
+        let (content, block, nodes) = self
+            .store
+            .read_record(index)
+            .await?
+            .ok_or_else(|| anyhow!("Missing expected block."))?;
+        if let Content::Cleared(leaf) = &content {
+            ensure!(Hash::from_leaf(data)? == *leaf, "Data does not match block {}.", index);
+            self.store.forget(index, &content, &block);
+            self.store.write(index, data, &nodes, &block).await?;
+            self.store.write_merkle(&self.merkle).await?;
+        }
+        if self.header.refetch().contains(&index) {
+            let mut refetch = self.header.refetch().to_vec();
+            refetch.retain(|&refetch| refetch != index);
+            self.set_refetch(refetch).await?;
+        }
+        Ok(())
+    }
+
+    /// Mark cleared blocks in `range` to be fetched again from peers,
+    /// see `libdata::replication::CoreReplica`.
+    ///
+    /// The marks are kept in the header until the blocks are restored.
+    pub async fn refetch(&mut self, range: Range<u32>) -> Result<()> {
+        ensure!(
+            range.end <= self.length,
+            "Core is shorter than {}.",
+            range.end
+        );
+        let mut refetch = self.header.refetch().to_vec();
+        for index in range {
+            if self.is_cleared(index).await? {
+                refetch.push(index);
+            }
+        }
+        refetch.sort_unstable();
+        refetch.dedup();
+        self.set_refetch(refetch).await
+    }
+
+    /// Get the next cleared block marked to be fetched again.
+    #[inline]
+    pub fn next_refetch(&self) -> Option<u32> {
+        self.header.refetch().first().copied()
+    }
+
+    /// Get the next cleared block after `index` marked to be fetched again.
+    #[inline]
+    pub fn next_refetch_after(&self, index: u32) -> Option<u32> {
+        let refetch = self.header.refetch();
+        let next = refetch.partition_point(|&refetch| refetch <= index);
+        refetch.get(next).copied()
+    }
+
+    /// Write the cleared blocks to fetch again into the header.
+    async fn set_refetch(&mut self, refetch: Vec<u32>) -> Result<()> {
+        if refetch == self.header.refetch() {
+            return Ok(());
+        }
+        let mut header = self.header.clone();
+        header.set_refetch(refetch);
+        self.store.write_header(&header).await?;
+        self.header = header;
+        Ok(())
+    }
+
+    /// Create a [Proof] for the block at index,
+    /// against the first checkpoint covering it.
+    ///
+    /// Computing the [Proof] reads the blocks up to the checkpoint
+    /// and a block record per level of the tree.
+    pub async fn proof(&self, index: u32) -> Result<Proof> {
+        ensure!(
+            index < self.checkpoint,
+            "Block {} is not covered by a checkpoint.",
+            index
+        );
+
+        let mut length = index + 1;
+        let signature = loop {
+            let (_, block) = self
+                .store
+                .read(length - 1)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            if let Some(tree) = block.signature().tree() {
+                break *tree;
+            }
+            length += 1;
+        };
+        let roots = self.roots(length).await?;
+
+        let leaf = 2 * u64::from(index);
+        let root = roots
+            .iter()
+            .map(NodeTrait::index)
+            .find(|&root| {
+                let (left, right) = flat_tree::spans(root);
+                left <= leaf && leaf <= right
+            })
+            .ok_or_else(|| anyhow!("Block is not covered by the roots."))?;
+        let mut nodes = vec![];
+        let mut node = leaf;
+        while node != root {
+            nodes.push(self.tree_node(flat_tree::sibling(node)).await?);
+            node = flat_tree::parent(node);
+        }
+
+        Ok(Proof::new(index, length, nodes, roots, signature))
+    }
+
+    /// Get the [SignedHead] of the latest checkpoint,
+    /// `None` if there is no checkpoint yet.
+    pub async fn signed_head(&self) -> Result<Option<SignedHead>> {
+        self.signed_head_at(self.checkpoint).await
+    }
+
+    /// Get the [SignedHead] of the `Core` at `length`,
+    /// `None` if the block at `length - 1` does not sign the tree.
+    pub async fn signed_head_at(&self, length: u32) -> Result<Option<SignedHead>> {
+        ensure!(length <= self.length, "Core is shorter than {}.", length);
+        if length == 0 {
+            return Ok(None);
+        }
+        let (_, block) = self
+            .store
+            .read(length - 1)
+            .await?
+            .ok_or_else(|| anyhow!("Missing expected block."))?;
+        let signature = match block.signature().tree() {
+            Some(signature) => *signature,
+            None => return Ok(None),
+        };
+        let byte_length = block.offset() + u64::from(block.length());
+        let roots = self.roots(length).await?;
+        Ok(Some(SignedHead::new(
+            self.scheme(),
+            length,
+            byte_length,
+            roots,
+            signature,
+        )))
+    }
+
+    /// Compare a remote [SignedHead] with the `Core` at the same length,
+    /// returns whether both have the same roots.
+    ///
+    /// The [SignedHead] is verified first.
+    /// If the roots differ and the local block at its length signs the tree,
+    /// the writer signed 2 different trees of the same length,
+    /// a [ForkProof] is recorded and returned in a [ForkError].
+    pub async fn check_fork(&mut self, head: &SignedHead) -> Result<bool> {
+        verify_signed_head(
+            &self.public_key,
+            self.verifier.as_ref(),
+            self.header.rotations(),
+            head,
+        )?;
+        let length = head.length();
+        ensure!(
+            length <= self.length,
+            "Signed head of length {} is past the Core.",
+            length
+        );
+        if self.roots(length).await? == head.roots() {
+            return Ok(true);
+        }
+        let local = match self.signed_head_at(length).await? {
+            Some(local) => local,
+            None => return Ok(false),
+        };
+
+        let proof = ForkProof::new(local, head.clone());
+        let mut header = self.header.clone();
+        header.set_fork(Some(proof.clone()));
+        self.store.write_header(&header).await?;
+        self.header = header;
+        Err(ForkError::new(proof).into())
+    }
+
+    /// Get the root [Node]s of the tree with `length` blocks.
+    ///
+    /// Roots of older lengths are read from the stored block records.
+    pub async fn roots(&self, length: u32) -> Result<Vec<Node>> {
+        ensure!(length <= self.length, "Core is shorter than {}.", length);
+        match length == self.length {
+            true => Ok(self.merkle.roots().clone()),
+            false => self.roots_at(length).await,
+        }
+    }
+
+    /// Create a [ConsistencyProof] that the `Core` at `new_length`
+    /// is an extension of the `Core` at `old_length`.
+    ///
+    /// Computing the [ConsistencyProof] reads a block record per [Node].
+    pub async fn consistency_proof(
+        &self,
+        old_length: u32,
+        new_length: u32,
+    ) -> Result<ConsistencyProof> {
+        ensure!(
+            old_length <= new_length && new_length <= self.length,
+            "Invalid lengths {}..{} for Core of length {}.",
+            old_length,
+            new_length,
+            self.length
+        );
+        let mut nodes = vec![];
+        for index in consistency_indexes(old_length, new_length) {
+            nodes.push(self.tree_node(index).await?);
+        }
+        Ok(ConsistencyProof::new(old_length, new_length, nodes))
+    }
+
+    /// Roll back to the latest checkpoint.
+    async fn rollback(&mut self) -> Result<()> {
+        let length = self.checkpoint;
+        let merkle = Merkle::from_roots(self.roots_at(length).await?);
+        let byte_length = match length {
+            0 => 0,
+            n => {
+                let (_, block) = self
+                    .store
+                    .read(n - 1)
+                    .await?
+                    .ok_or_else(|| anyhow!("Missing expected block."))?;
+                block.offset() + u64::from(block.length())
+            }
+        };
+        for index in length..self.length {
+            let (content, block) = self
+                .store
+                .read(index)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            self.store.forget(index, &content, &block);
+        }
+        self.store.write_merkle(&merkle).await?;
+        self.merkle = merkle;
+        self.length = length;
+        self.byte_length = byte_length;
+        let mut refetch = self.header.refetch().to_vec();
+        refetch.retain(|&index| index < length);
+        self.set_refetch(refetch).await
+    }
+
+    /// Compute the root [Node]s of the tree with `length` blocks.
+    async fn roots_at(&self, length: u32) -> Result<Vec<Node>> {
+        let mut roots = vec![];
+        for index in root_indexes(length) {
+            roots.push(self.tree_node(index).await?);
+        }
+        Ok(roots)
+    }
+
+    /// Read a tree [Node] from the record of the last block under it.
+    ///
+    /// `Core`s written before tree nodes were stored
+    /// compute it from the blocks under it.
+    async fn tree_node(&self, index: u64) -> Result<Node> {
+        let (left, right) = flat_tree::spans(index);
+        if self.store.tree_nodes() {
+            let (content, block, nodes) = self
+                .store
+                .read_record(u32::try_from(right / 2)?)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            return match index == right {
+                true => Ok(Node::new(index, content.leaf()?, block.length())),
+                false => nodes
+                    .into_iter()
+                    .find(|node| node.index() == index)
+                    .ok_or_else(|| anyhow!("Missing expected tree node.")),
+            };
+        }
+        let mut nodes = vec![];
+        for leaf in (left / 2)..=(right / 2) {
+            let (content, block) = self
+                .store
+                .read(u32::try_from(leaf)?)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            nodes.push(Node::new(leaf * 2, content.leaf()?, block.length()));
+        }
+        while nodes.len() > 1 {
+            nodes = nodes
+                .chunks(2)
+                .map(|pair| Node::parent(&pair[0], &pair[1]))
+                .collect();
+        }
+        nodes.pop().ok_or_else(|| anyhow!("Empty tree node."))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,400 @@
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::IndexAccess;
+
+/// Operation on a storage interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// [IndexAccess::read].
+    Read,
+    /// [IndexAccess::write].
+    Write,
+}
+
+/// [Fault] injected into an [Operation].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Fail with an [InjectedError].
+    Error,
+    /// Keep only the first bytes of the data.
+    ///
+    /// A torn write stores the bytes and then fails with an [InjectedError],
+    /// as if the process crashed in the middle of the write.
+    Torn(usize),
+    /// Delay the operation.
+    Latency(Duration),
+    /// Flip a bit of the data, the bit index wraps around the data length.
+    BitFlip(usize),
+}
+
+/// [Trigger] selects the operations a [Fault] is injected into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Every operation.
+    Always,
+    /// Operations on an index.
+    Index(u32),
+    /// Operations after the first `n` operations of the same kind.
+    After(u64),
+}
+
+/// [Injection] of a [Fault] into the operations selected by a [Trigger].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    operation: Operation,
+    trigger: Trigger,
+    fault: Fault,
+    remaining: Option<u64>,
+}
+impl Injection {
+    /// Create a new [Injection], active until [Faults::clear].
+    #[must_use]
+    #[inline]
+    pub fn new(operation: Operation, trigger: Trigger, fault: Fault) -> Self {
+        Self {
+            operation,
+            trigger,
+            fault,
+            remaining: None,
+        }
+    }
+    /// Inject the [Fault] at most `times` times.
+    #[must_use]
+    #[inline]
+    pub fn times(mut self, times: u64) -> Self {
+        self.remaining = Some(times);
+        self
+    }
+
+    /// Get the [Operation].
+    #[must_use]
+    #[inline]
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+    /// Get the [Trigger].
+    #[must_use]
+    #[inline]
+    pub fn trigger(&self) -> Trigger {
+        self.trigger
+    }
+    /// Get the [Fault].
+    #[must_use]
+    #[inline]
+    pub fn fault(&self) -> Fault {
+        self.fault
+    }
+
+    fn matches(&self, operation: Operation, index: u32, count: u64) -> bool {
+        self.operation == operation
+            && self.remaining != Some(0)
+            && match self.trigger {
+                Trigger::Always => true,
+                Trigger::Index(i) => i == index,
+                Trigger::After(n) => count >= n,
+            }
+    }
+}
+
+/// [InjectedError] is returned by operations failed by a [Fault].
+///
+/// Use [anyhow::Error::downcast_ref] to tell it apart from other errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedError {
+    operation: Operation,
+    index: u32,
+}
+impl InjectedError {
+    /// Create a new [InjectedError].
+    #[must_use]
+    #[inline]
+    pub fn new(operation: Operation, index: u32) -> Self {
+        Self { operation, index }
+    }
+    /// Get the failed [Operation].
+    #[must_use]
+    #[inline]
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+    /// Get the index of the failed operation.
+    #[must_use]
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+impl fmt::Display for InjectedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Injected {:?} fault at index {}.",
+            self.operation, self.index
+        )
+    }
+}
+impl std::error::Error for InjectedError {}
+
+#[derive(Debug, Default)]
+struct State {
+    injections: Vec<Injection>,
+    reads: u64,
+    writes: u64,
+    injected: u64,
+}
+
+/// [Faults] is a shared handle of the injections of an [IndexAccessFault].
+///
+/// Faults can be injected and cleared while the storage is owned by a `Core`.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    state: Arc<Mutex<State>>,
+}
+impl Faults {
+    /// Inject a [Fault] into the following operations.
+    pub fn inject(&self, injection: Injection) {
+        self.state().injections.push(injection);
+    }
+    /// Remove all injections.
+    pub fn clear(&self) {
+        self.state().injections.clear();
+    }
+
+    /// Get the number of reads.
+    #[must_use]
+    pub fn reads(&self) -> u64 {
+        self.state().reads
+    }
+    /// Get the number of writes.
+    #[must_use]
+    pub fn writes(&self) -> u64 {
+        self.state().writes
+    }
+    /// Get the number of injected faults.
+    #[must_use]
+    pub fn injected(&self) -> u64 {
+        self.state().injected
+    }
+
+    /// Count an operation and take the faults injected into it.
+    fn take(&self, operation: Operation, index: u32) -> Vec<Fault> {
+        let mut state = self.state();
+        let count = match operation {
+            Operation::Read => &mut state.reads,
+            Operation::Write => &mut state.writes,
+        };
+        let previous = *count;
+        *count += 1;
+
+        let mut faults = vec![];
+        for injection in &mut state.injections {
+            if injection.matches(operation, index, previous) {
+                if let Some(remaining) = &mut injection.remaining {
+                    *remaining -= 1;
+                }
+                faults.push(injection.fault);
+            }
+        }
+        state.injected += faults.len() as u64;
+        faults
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // counters and injections stay consistent, a panic can not tear them
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// [IndexAccessFault] wraps a storage interface and injects [Fault]s
+/// into its operations, to test how failing storage is handled.
+///
+/// Faults are applied in the order they were injected.
+/// Read faults change the returned data, the wrapped storage is unchanged.
+#[derive(Debug)]
+pub struct IndexAccessFault<T> {
+    store: T,
+    faults: Faults,
+}
+impl<T> IndexAccessFault<T> {
+    /// Create a new [IndexAccessFault] without injected faults.
+    #[must_use]
+    #[inline]
+    pub fn new(store: T) -> Self {
+        Self {
+            store,
+            faults: Faults::default(),
+        }
+    }
+    /// Get a handle to the [Faults].
+    #[must_use]
+    #[inline]
+    pub fn faults(&self) -> Faults {
+        self.faults.clone()
+    }
+    /// Unwrap the storage interface.
+    #[must_use]
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.store
+    }
+}
+#[async_trait]
+impl<T> IndexAccess for IndexAccessFault<T>
+where
+    T: IndexAccess + Send,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    type Error = anyhow::Error;
+
+    async fn write(&mut self, index: u32, data: &[u8]) -> anyhow::Result<()> {
+        let mut data = data.to_vec();
+        for fault in self.faults.take(Operation::Write, index) {
+            match fault {
+                Fault::Error => return Err(InjectedError::new(Operation::Write, index).into()),
+                Fault::Torn(length) => {
+                    data.truncate(length);
+                    self.store.write(index, &data).await.map_err(Into::into)?;
+                    return Err(InjectedError::new(Operation::Write, index).into());
+                }
+                Fault::Latency(duration) => tokio::time::sleep(duration).await,
+                Fault::BitFlip(bit) => flip(&mut data, bit),
+            }
+        }
+        self.store.write(index, &data).await.map_err(Into::into)
+    }
+
+    async fn read(&mut self, index: u32) -> anyhow::Result<Option<Vec<u8>>> {
+        let faults = self.faults.take(Operation::Read, index);
+        let mut data = self.store.read(index).await.map_err(Into::into)?;
+        for fault in faults {
+            match fault {
+                Fault::Error => return Err(InjectedError::new(Operation::Read, index).into()),
+                Fault::Torn(length) => {
+                    if let Some(data) = &mut data {
+                        data.truncate(length);
+                    }
+                }
+                Fault::Latency(duration) => tokio::time::sleep(duration).await,
+                Fault::BitFlip(bit) => {
+                    if let Some(data) = &mut data {
+                        flip(data, bit);
+                    }
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+fn flip(data: &mut [u8], bit: usize) {
+    if !data.is_empty() {
+        let bit = bit % (data.len() * 8);
+        data[bit / 8] ^= 1 << (bit % 8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use index_access_memory::IndexAccessMemory;
+    use tokio::time::Instant;
+
+    #[tokio::test]
+    async fn error() -> anyhow::Result<()> {
+        let mut storage = IndexAccessFault::new(IndexAccessMemory::default());
+        let faults = storage.faults();
+        faults.inject(Injection::new(
+            Operation::Write,
+            Trigger::Index(1),
+            Fault::Error,
+        ));
+        faults.inject(Injection::new(Operation::Read, Trigger::After(2), Fault::Error).times(1));
+
+        storage.write(0, b"a").await?;
+        let err = storage.write(1, b"b").await.unwrap_err();
+        let err = err.downcast_ref::<InjectedError>().unwrap();
+        assert_eq!(err, &InjectedError::new(Operation::Write, 1));
+
+        assert_eq!(storage.read(0).await?.unwrap(), b"a");
+        assert_eq!(storage.read(1).await?, None);
+        assert!(storage.read(0).await.is_err());
+        assert_eq!(storage.read(0).await?.unwrap(), b"a");
+
+        faults.clear();
+        storage.write(1, b"b").await?;
+        assert_eq!(storage.read(1).await?.unwrap(), b"b");
+        assert_eq!(
+            (faults.reads(), faults.writes(), faults.injected()),
+            (5, 3, 2)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn torn() -> anyhow::Result<()> {
+        let mut storage = IndexAccessFault::new(IndexAccessMemory::default());
+        let faults = storage.faults();
+        faults.inject(Injection::new(Operation::Write, Trigger::Always, Fault::Torn(2)).times(1));
+        faults.inject(Injection::new(
+            Operation::Read,
+            Trigger::Index(1),
+            Fault::Torn(1),
+        ));
+
+        assert!(storage.write(0, b"abcd").await.is_err());
+        storage.write(1, b"abcd").await?;
+        assert_eq!(storage.read(0).await?.unwrap(), b"ab");
+        assert_eq!(storage.read(1).await?.unwrap(), b"a");
+
+        let mut store = storage.into_inner();
+        assert_eq!(store.read(1).await?.unwrap(), b"abcd");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bit_flip() -> anyhow::Result<()> {
+        let mut storage = IndexAccessFault::new(IndexAccessMemory::default());
+        let faults = storage.faults();
+        faults.inject(Injection::new(
+            Operation::Write,
+            Trigger::Index(0),
+            Fault::BitFlip(9),
+        ));
+        faults.inject(Injection::new(
+            Operation::Read,
+            Trigger::Index(1),
+            Fault::BitFlip(16),
+        ));
+
+        storage.write(0, &[0, 0]).await?;
+        storage.write(1, &[0, 0]).await?;
+        storage.write(2, &[]).await?;
+        assert_eq!(storage.read(0).await?.unwrap(), [0, 2]);
+        assert_eq!(storage.read(1).await?.unwrap(), [1, 0]);
+        assert_eq!(storage.read(1).await?.unwrap(), [1, 0]);
+        assert!(storage.read(2).await?.unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn latency() -> anyhow::Result<()> {
+        let mut storage = IndexAccessFault::new(IndexAccessMemory::default());
+        let latency = Duration::from_millis(20);
+        storage.faults().inject(Injection::new(
+            Operation::Write,
+            Trigger::Always,
+            Fault::Latency(latency),
+        ));
+
+        let start = Instant::now();
+        storage.write(0, b"a").await?;
+        assert!(start.elapsed() >= latency);
+        assert_eq!(storage.read(0).await?.unwrap(), b"a");
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{ensure, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fmt;
+use std::io::{Cursor, Read};
+
+use crate::head::{verify_signed_head, SignedHead};
+use crate::{KeyRotation, PublicKey, Verifier};
+
+/// [ForkProof] shows that the writer of a `Core` signed
+/// 2 different trees of the same length.
+///
+/// It is self-contained, anyone with the `PublicKey`
+/// and the `KeyRotation`s of the `Core` can verify it with [verify_fork_proof].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ForkProof {
+    local: SignedHead,
+    remote: SignedHead,
+}
+impl ForkProof {
+    /// Create a new [ForkProof].
+    #[must_use]
+    #[inline]
+    pub fn new(local: SignedHead, remote: SignedHead) -> Self {
+        Self { local, remote }
+    }
+
+    /// Serialize [ForkProof].
+    #[inline]
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let local = self.local.to_bytes()?;
+        let remote = self.remote.to_bytes()?;
+        let mut data = Vec::with_capacity(4 + local.len() + remote.len());
+        data.write_u32::<LittleEndian>(u32::try_from(local.len())?)?;
+        data.extend_from_slice(&local);
+        data.extend_from_slice(&remote);
+        Ok(data)
+    }
+    /// Deserialize [ForkProof].
+    #[inline]
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut rdr = Cursor::new(data);
+        let length = rdr.read_u32::<LittleEndian>()? as usize;
+        let mut local = vec![0u8; length];
+        rdr.read_exact(&mut local)?;
+        let mut remote = vec![];
+        rdr.read_to_end(&mut remote)?;
+        Ok(Self {
+            local: SignedHead::from_bytes(&local)?,
+            remote: SignedHead::from_bytes(&remote)?,
+        })
+    }
+
+    /// Get the length at which the `Core` forked.
+    #[must_use]
+    #[inline]
+    pub fn length(&self) -> u32 {
+        self.local.length()
+    }
+    /// Get the locally stored [SignedHead].
+    #[must_use]
+    #[inline]
+    pub fn local(&self) -> &SignedHead {
+        &self.local
+    }
+    /// Get the conflicting remote [SignedHead].
+    #[must_use]
+    #[inline]
+    pub fn remote(&self) -> &SignedHead {
+        &self.remote
+    }
+}
+
+/// Verify a [ForkProof] of the `Core` `core` with the [Verifier]
+/// of its `PublicKey` and its [KeyRotation]s, see [verify_signed_head].
+pub fn verify_fork_proof(
+    core: &PublicKey,
+    verifier: &dyn Verifier,
+    rotations: &[KeyRotation],
+    proof: &ForkProof,
+) -> Result<()> {
+    ensure!(
+        proof.local.length() == proof.remote.length(),
+        "Fork proof heads have different lengths."
+    );
+    ensure!(
+        proof.local.hash() != proof.remote.hash(),
+        "Fork proof heads are equal."
+    );
+    verify_signed_head(core, verifier, rotations, &proof.local)?;
+    verify_signed_head(core, verifier, rotations, &proof.remote)
+}
+
+/// [ForkError] is returned by a `Core` with a [ForkProof],
+/// such a `Core` refuses to append or replicate.
+///
+/// Use [anyhow::Error::downcast_ref] to tell it apart from other errors.
+#[derive(Debug, Clone)]
+pub struct ForkError {
+    proof: ForkProof,
+}
+impl ForkError {
+    /// Create a new [ForkError].
+    #[must_use]
+    #[inline]
+    pub fn new(proof: ForkProof) -> Self {
+        Self { proof }
+    }
+    /// Get the [ForkProof].
+    #[must_use]
+    #[inline]
+    pub fn proof(&self) -> &ForkProof {
+        &self.proof
+    }
+}
+impl fmt::Display for ForkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Core forked at length {}.", self.proof.length())
+    }
+}
+impl std::error::Error for ForkError {}
@@ -10,6 +10,7 @@ const HASH_LENGTH: usize = 32;
 const LEAF_TYPE: [u8; 1] = [0x00];
 const PARENT_TYPE: [u8; 1] = [0x01];
 const ROOT_TYPE: [u8; 1] = [0x02];
+const KEY_ROTATION_TYPE: [u8; 1] = [0x03];
 
 pub const HASH_SIZE: usize = HASH_LENGTH;
 
@@ -34,6 +35,21 @@ impl Hash {
         Ok(Self { hash })
     }
 
+    /// Hash the data of a `KeyRotation` block, signed instead of its leaf `Hash`,
+    /// so a signature of block data can not mark it as a `KeyRotation`.
+    #[inline]
+    pub fn from_key_rotation(data: &[u8]) -> Result<Self> {
+        let length = u32::try_from(data.len())?;
+
+        let mut hasher = Hasher::new();
+        hasher.update(&KEY_ROTATION_TYPE);
+        hasher.update(&u32_to_bytes(length));
+        hasher.update(data);
+        let hash = hasher.finalize().into();
+
+        Ok(Self { hash })
+    }
+
     /// Hash two `Hash` together to form a parent `Hash`.
     #[must_use]
     #[inline]
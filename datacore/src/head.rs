@@ -0,0 +1,217 @@
+use anyhow::{ensure, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read};
+use std::mem::size_of;
+
+use crate::merkle::{hash_roots, root_indexes, NODE_SIZE};
+use crate::rotation::rotated_verifier;
+use crate::{
+    Hash, KeyRotation, Node, NodeTrait, PublicKey, SignatureScheme, Verifier,
+    SCHEME_SIGNATURE_LENGTH,
+};
+
+/// Current version of the [SignedHead] format.
+pub const SIGNED_HEAD_VERSION: u8 = 1;
+
+/// [SignedHead] is the signed state of a `Core` at some length,
+/// meant to be published and verified outside of datacore.
+///
+/// Carries the root [Node]s, so `length` and `byte_length`
+/// are covered by the tree signature.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignedHead {
+    scheme: SignatureScheme,
+    length: u32,
+    byte_length: u64,
+    roots: Vec<Node>,
+    signature: [u8; SCHEME_SIGNATURE_LENGTH],
+}
+
+impl SignedHead {
+    /// Create a new [SignedHead].
+    #[must_use]
+    #[inline]
+    pub fn new(
+        scheme: SignatureScheme,
+        length: u32,
+        byte_length: u64,
+        roots: Vec<Node>,
+        signature: [u8; SCHEME_SIGNATURE_LENGTH],
+    ) -> Self {
+        Self {
+            scheme,
+            length,
+            byte_length,
+            roots,
+            signature,
+        }
+    }
+
+    /// Serialize [SignedHead].
+    #[inline]
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(
+            3 + size_of::<u32>()
+                + size_of::<u64>()
+                + SCHEME_SIGNATURE_LENGTH
+                + self.roots.len() * NODE_SIZE,
+        );
+        data.write_u8(SIGNED_HEAD_VERSION)?;
+        data.write_u8(self.scheme.to_u8())?;
+        data.write_u32::<LittleEndian>(self.length)?;
+        data.write_u64::<LittleEndian>(self.byte_length)?;
+        data.extend_from_slice(&self.signature);
+        data.write_u8(u8::try_from(self.roots.len())?)?;
+        for root in &self.roots {
+            data.extend_from_slice(&root.to_bytes()?);
+        }
+        Ok(data)
+    }
+    /// Deserialize [SignedHead].
+    #[inline]
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut rdr = Cursor::new(data);
+        let version = rdr.read_u8()?;
+        ensure!(
+            version == SIGNED_HEAD_VERSION,
+            "Unsupported signed head version {}.",
+            version
+        );
+        let scheme = SignatureScheme::from_u8(rdr.read_u8()?);
+        let length = rdr.read_u32::<LittleEndian>()?;
+        let byte_length = rdr.read_u64::<LittleEndian>()?;
+        let mut signature = [0u8; SCHEME_SIGNATURE_LENGTH];
+        rdr.read_exact(&mut signature)?;
+        let count = rdr.read_u8()?;
+        let mut roots = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut node = [0u8; NODE_SIZE];
+            rdr.read_exact(&mut node)?;
+            roots.push(Node::from_bytes(&node)?);
+        }
+        ensure!(
+            rdr.position() as usize == data.len(),
+            "Trailing bytes after signed head."
+        );
+        Ok(Self {
+            scheme,
+            length,
+            byte_length,
+            roots,
+            signature,
+        })
+    }
+
+    /// Get the [SignatureScheme] of the `Core`.
+    #[must_use]
+    #[inline]
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+    /// Get the number of entries.
+    #[must_use]
+    #[inline]
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+    /// Get the byte length of all entries.
+    #[must_use]
+    #[inline]
+    pub fn byte_length(&self) -> u64 {
+        self.byte_length
+    }
+    /// Get the root [Node]s.
+    #[must_use]
+    #[inline]
+    pub fn roots(&self) -> &[Node] {
+        &self.roots
+    }
+    /// Get the root [Hash], the signed message.
+    #[must_use]
+    #[inline]
+    pub fn hash(&self) -> Hash {
+        hash_roots(&self.roots)
+    }
+    /// Get the tree signature.
+    #[must_use]
+    #[inline]
+    pub fn signature(&self) -> &[u8; SCHEME_SIGNATURE_LENGTH] {
+        &self.signature
+    }
+}
+
+/// Verify a [SignedHead] of the `Core` `core` with the [Verifier]
+/// of its `PublicKey`, usually the `PublicKey` itself.
+///
+/// The head is signed by the key writing the `Core` at its length,
+/// resolved through the [KeyRotation]s of the `Core`, see [rotated_verifier].
+pub fn verify_signed_head(
+    core: &PublicKey,
+    verifier: &dyn Verifier,
+    rotations: &[KeyRotation],
+    head: &SignedHead,
+) -> Result<()> {
+    ensure!(
+        head.scheme == verifier.scheme(),
+        "Head is signed with {:?}, not {:?}.",
+        head.scheme,
+        verifier.scheme()
+    );
+    let indexes = head.roots.iter().map(NodeTrait::index).collect::<Vec<u64>>();
+    ensure!(
+        indexes == root_indexes(head.length),
+        "Head roots do not match its length."
+    );
+    let byte_length = head
+        .roots
+        .iter()
+        .map(|root| u64::from(root.length()))
+        .sum::<u64>();
+    ensure!(
+        byte_length == head.byte_length,
+        "Head roots do not match its byte length."
+    );
+    let verifier = rotated_verifier(core, verifier, rotations, head.length.saturating_sub(1))?;
+    verifier.verify(&head.hash(), &head.signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyPair, Merkle, Signer};
+
+    async fn signed_head(keypair: &KeyPair) -> Result<SignedHead> {
+        let mut merkle = Merkle::default();
+        merkle.next(Hash::from_leaf(b"a")?, 1);
+        merkle.next(Hash::from_leaf(b"bc")?, 2);
+        merkle.next(Hash::from_leaf(b"def")?, 3);
+        let roots = merkle.roots().clone();
+        let signature = Signer::sign(&keypair.sk, &hash_roots(&roots)).await?;
+        Ok(SignedHead::new(SignatureScheme::Ed25519, 3, 6, roots, signature))
+    }
+
+    #[tokio::test]
+    async fn to_bytes_from_bytes() -> Result<()> {
+        let head = signed_head(&KeyPair::generate()).await?;
+        let bytes = head.to_bytes()?;
+        assert_eq!(SignedHead::from_bytes(&bytes)?, head);
+        assert!(SignedHead::from_bytes(&bytes[1..]).is_err());
+        assert!(SignedHead::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify() -> Result<()> {
+        let keypair = KeyPair::generate();
+        let head = signed_head(&keypair).await?;
+        verify_signed_head(&keypair.pk, &keypair.pk, &[], &head)?;
+        assert!(verify_signed_head(&keypair.pk, &KeyPair::generate().pk, &[], &head).is_err());
+
+        let roots = head.roots().to_vec();
+        let forged = SignedHead::new(head.scheme(), 3, 7, roots.clone(), *head.signature());
+        assert!(verify_signed_head(&keypair.pk, &keypair.pk, &[], &forged).is_err());
+        let forged = SignedHead::new(head.scheme(), 4, 6, roots, *head.signature());
+        assert!(verify_signed_head(&keypair.pk, &keypair.pk, &[], &forged).is_err());
+        Ok(())
+    }
+}
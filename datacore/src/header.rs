@@ -0,0 +1,299 @@
+use anyhow::{ensure, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read};
+
+use crate::fork::ForkProof;
+use crate::rotation::{KeyRotation, KEY_ROTATION_LENGTH};
+use crate::{SignatureScheme, SCHEME_SIGNATURE_LENGTH};
+
+/// Current version of the [Header] format.
+pub const HEADER_VERSION: u8 = 1;
+
+/// [Header] describes a `Core` as a whole.
+///
+/// It is written after the version as:
+/// - [SignatureScheme]
+/// - checkpoint interval
+/// - schema of the block data
+/// - [KeyRotation]s
+/// - if block records store tree nodes
+/// - cleared blocks to fetch again
+/// - signature of the schema
+/// - [ForkProof], to the end
+///
+/// `Core`s written before the [Header] have none.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Header {
+    scheme: SignatureScheme,
+    checkpoints: Option<u32>,
+    schema: Option<String>,
+    rotations: Vec<KeyRotation>,
+    tree_nodes: bool,
+    refetch: Vec<u32>,
+    schema_signature: Option<[u8; SCHEME_SIGNATURE_LENGTH]>,
+    fork: Option<ForkProof>,
+}
+
+impl Header {
+    /// Create a new [Header].
+    #[must_use]
+    #[inline]
+    pub fn new(scheme: SignatureScheme) -> Self {
+        Self {
+            scheme,
+            checkpoints: None,
+            schema: None,
+            rotations: vec![],
+            tree_nodes: false,
+            refetch: vec![],
+            schema_signature: None,
+            fork: None,
+        }
+    }
+
+    /// Serialize [Header].
+    #[inline]
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(7);
+        data.write_u8(HEADER_VERSION)?;
+        data.write_u8(self.scheme.to_u8())?;
+        data.write_u8(u8::from(self.checkpoints.is_some()))?;
+        data.write_u32::<LittleEndian>(self.checkpoints.unwrap_or(0))?;
+        let schema = self.schema.as_deref().unwrap_or_default();
+        data.write_u16::<LittleEndian>(u16::try_from(schema.len())?)?;
+        data.extend_from_slice(schema.as_bytes());
+        data.write_u32::<LittleEndian>(u32::try_from(self.rotations.len())?)?;
+        for rotation in &self.rotations {
+            data.extend_from_slice(&rotation.to_bytes()?);
+        }
+        data.write_u8(u8::from(self.tree_nodes))?;
+        data.write_u32::<LittleEndian>(u32::try_from(self.refetch.len())?)?;
+        for index in &self.refetch {
+            data.write_u32::<LittleEndian>(*index)?;
+        }
+        data.write_u8(u8::from(self.schema_signature.is_some()))?;
+        if let Some(signature) = &self.schema_signature {
+            data.extend_from_slice(signature);
+        }
+        if let Some(fork) = &self.fork {
+            data.extend_from_slice(&fork.to_bytes()?);
+        }
+        Ok(data)
+    }
+    /// Deserialize [Header].
+    #[inline]
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut rdr = Cursor::new(data);
+        let version = rdr.read_u8()?;
+        ensure!(
+            version == HEADER_VERSION,
+            "Unsupported header version {}.",
+            version
+        );
+        let scheme = SignatureScheme::from_u8(rdr.read_u8()?);
+        let enabled = rdr.read_u8()? != 0;
+        let interval = rdr.read_u32::<LittleEndian>()?;
+        let checkpoints = enabled.then_some(interval);
+
+        let length = rdr.read_u16::<LittleEndian>()?;
+        let mut schema = vec![0u8; length as usize];
+        rdr.read_exact(&mut schema)?;
+        let schema = match schema.is_empty() {
+            true => None,
+            false => Some(String::from_utf8(schema)?),
+        };
+
+        let count = rdr.read_u32::<LittleEndian>()?;
+        let mut rotations = vec![];
+        for _ in 0..count {
+            let mut rotation = [0u8; KEY_ROTATION_LENGTH];
+            rdr.read_exact(&mut rotation)?;
+            rotations.push(KeyRotation::from_bytes(&rotation)?);
+        }
+
+        let tree_nodes = rdr.read_u8()? != 0;
+
+        let count = rdr.read_u32::<LittleEndian>()?;
+        let mut refetch = vec![];
+        for _ in 0..count {
+            refetch.push(rdr.read_u32::<LittleEndian>()?);
+        }
+
+        let schema_signature = match rdr.read_u8()? {
+            0 => None,
+            _ => {
+                let mut signature = [0u8; SCHEME_SIGNATURE_LENGTH];
+                rdr.read_exact(&mut signature)?;
+                Some(signature)
+            }
+        };
+
+        let mut fork = vec![];
+        rdr.read_to_end(&mut fork)?;
+        let fork = match fork.is_empty() {
+            true => None,
+            false => Some(ForkProof::from_bytes(&fork)?),
+        };
+        Ok(Self {
+            scheme,
+            checkpoints,
+            schema,
+            rotations,
+            tree_nodes,
+            refetch,
+            schema_signature,
+            fork,
+        })
+    }
+
+    /// Get the [SignatureScheme] of the `Core`.
+    #[must_use]
+    #[inline]
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// Get the checkpoint interval, `None` if checkpoints are disabled.
+    #[must_use]
+    #[inline]
+    pub fn checkpoints(&self) -> Option<u32> {
+        self.checkpoints
+    }
+    /// Set the checkpoint interval.
+    #[inline]
+    pub fn set_checkpoints(&mut self, checkpoints: Option<u32>) {
+        self.checkpoints = checkpoints;
+    }
+
+    /// Get the schema of the block data, if set.
+    #[must_use]
+    #[inline]
+    pub fn schema(&self) -> Option<&str> {
+        self.schema.as_deref()
+    }
+    /// Set the schema of the block data.
+    #[inline]
+    pub fn set_schema(&mut self, schema: Option<String>) {
+        self.schema = schema;
+    }
+
+    /// Get the signature of the schema by the key of the `Core`, if signed.
+    #[must_use]
+    #[inline]
+    pub fn schema_signature(&self) -> Option<&[u8; SCHEME_SIGNATURE_LENGTH]> {
+        self.schema_signature.as_ref()
+    }
+    /// Set the signature of the schema.
+    #[inline]
+    pub fn set_schema_signature(&mut self, signature: Option<[u8; SCHEME_SIGNATURE_LENGTH]>) {
+        self.schema_signature = signature;
+    }
+
+    /// Get the [KeyRotation]s, ordered by index.
+    #[must_use]
+    #[inline]
+    pub fn rotations(&self) -> &[KeyRotation] {
+        &self.rotations
+    }
+    /// Set the [KeyRotation]s.
+    #[inline]
+    pub fn set_rotations(&mut self, rotations: Vec<KeyRotation>) {
+        self.rotations = rotations;
+    }
+
+    /// Check if block records store the tree nodes they complete.
+    #[must_use]
+    #[inline]
+    pub fn tree_nodes(&self) -> bool {
+        self.tree_nodes
+    }
+    /// Set if block records store the tree nodes they complete.
+    #[inline]
+    pub fn set_tree_nodes(&mut self, tree_nodes: bool) {
+        self.tree_nodes = tree_nodes;
+    }
+
+    /// Get the indexes of cleared blocks to fetch again, in order.
+    #[must_use]
+    #[inline]
+    pub fn refetch(&self) -> &[u32] {
+        &self.refetch
+    }
+    /// Set the indexes of cleared blocks to fetch again, in order.
+    #[inline]
+    pub fn set_refetch(&mut self, refetch: Vec<u32>) {
+        self.refetch = refetch;
+    }
+
+    /// Get the [ForkProof], if the `Core` forked.
+    #[must_use]
+    #[inline]
+    pub fn fork(&self) -> Option<&ForkProof> {
+        self.fork.as_ref()
+    }
+    /// Set the [ForkProof].
+    #[inline]
+    pub fn set_fork(&mut self, fork: Option<ForkProof>) {
+        self.fork = fork;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+    use std::num::NonZeroU8;
+
+    #[test]
+    pub fn to_bytes_from_bytes() -> Result<()> {
+        let header = Header::new(SignatureScheme::Custom(NonZeroU8::new(3).unwrap()));
+        let header2 = Header::from_bytes(&header.to_bytes()?)?;
+        assert_eq!(header2, header);
+        Ok(())
+    }
+    #[test]
+    pub fn to_bytes_from_bytes_checkpoints() -> Result<()> {
+        let mut header = Header::default();
+        header.set_checkpoints(Some(16));
+        let header2 = Header::from_bytes(&header.to_bytes()?)?;
+        assert_eq!(header2.checkpoints(), Some(16));
+        header.set_checkpoints(Some(0));
+        let header2 = Header::from_bytes(&header.to_bytes()?)?;
+        assert_eq!(header2.checkpoints(), Some(0));
+        Ok(())
+    }
+    #[test]
+    pub fn to_bytes_from_bytes_schema() -> Result<()> {
+        let mut header = Header::default();
+        header.set_schema(Some("json:message".to_owned()));
+        let header2 = Header::from_bytes(&header.to_bytes()?)?;
+        assert_eq!(header2.schema(), Some("json:message"));
+        Ok(())
+    }
+    #[tokio::test]
+    pub async fn to_bytes_from_bytes_rotations() -> Result<()> {
+        let core = KeyPair::generate();
+        let mut header = Header::default();
+        let mut rotations = vec![];
+        for index in [2, 5] {
+            let next = KeyPair::generate();
+            rotations.push(KeyRotation::sign(&core.sk, &core.pk, index, next.pk).await?);
+        }
+        header.set_rotations(rotations.clone());
+        header.set_schema(Some("json:message".to_owned()));
+        header.set_tree_nodes(true);
+        header.set_refetch(vec![3, 7]);
+        header.set_schema_signature(Some([4; SCHEME_SIGNATURE_LENGTH]));
+        let header2 = Header::from_bytes(&header.to_bytes()?)?;
+        assert_eq!(header2.rotations(), rotations.as_slice());
+        assert_eq!(header2, header);
+        Ok(())
+    }
+    #[test]
+    pub fn from_bytes_fails_on_unknown_version() -> Result<()> {
+        let mut bytes = Header::default().to_bytes()?;
+        bytes[0] = HEADER_VERSION + 1;
+        assert!(Header::from_bytes(&bytes).is_err());
+        Ok(())
+    }
+}
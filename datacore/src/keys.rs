@@ -1,9 +1,245 @@
 //! Generate a `Keypair`, sign and verify messages with `Keypair`.
-//! Uses `Ed25519` cryptography.
+//! Uses `Ed25519` cryptography by default,
+//! other schemes can be plugged in through [Signer] and [Verifier].
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use std::fmt;
+use std::hint::black_box;
+use std::num::NonZeroU8;
+use zeroize::Zeroize;
 
-pub use ed25519_compact::{KeyPair, Seed, PublicKey, SecretKey, Signature};
+pub use ed25519_compact::{PublicKey, SecretKey, Seed, Signature};
+
+/// Byte length of a single signature, the same for every [SignatureScheme].
+pub const SCHEME_SIGNATURE_LENGTH: usize = Signature::BYTES;
+
+/// Signature scheme used to sign a `Core`.
+///
+/// The scheme is recorded in the `Core` header and sent along with
+/// every block over the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignatureScheme {
+    /// `Ed25519`, the default scheme.
+    #[default]
+    Ed25519,
+    /// Application defined scheme, identified by a non-zero tag.
+    Custom(NonZeroU8),
+}
+impl SignatureScheme {
+    /// Serialize [SignatureScheme] into its tag.
+    #[must_use]
+    #[inline]
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::Ed25519 => 0,
+            Self::Custom(tag) => tag.get(),
+        }
+    }
+    /// Deserialize [SignatureScheme] from its tag.
+    #[must_use]
+    #[inline]
+    pub fn from_u8(tag: u8) -> Self {
+        match NonZeroU8::new(tag) {
+            None => Self::Ed25519,
+            Some(tag) => Self::Custom(tag),
+        }
+    }
+}
+
+/// Sign messages for a `Core`.
+///
+/// Every signature has to be exactly [SCHEME_SIGNATURE_LENGTH] bytes long.
+/// Signing is async, so it can be delegated to a separate signing service
+/// without keeping the [SecretKey] in memory.
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// [SignatureScheme] of the produced signatures.
+    fn scheme(&self) -> SignatureScheme;
+    /// Sign a byte slice.
+    async fn sign(&self, msg: &[u8]) -> Result<[u8; SCHEME_SIGNATURE_LENGTH]>;
+}
+
+/// Verify signatures of a `Core`.
+pub trait Verifier: Send + Sync {
+    /// [SignatureScheme] of the accepted signatures.
+    fn scheme(&self) -> SignatureScheme;
+    /// Verify a signature of a byte slice.
+    fn verify(&self, msg: &[u8], signature: &[u8; SCHEME_SIGNATURE_LENGTH]) -> Result<()>;
+}
+
+/// [VerifyError] is returned when a block fails verification
+/// with the [Verifier] of the `Core`.
+///
+/// Use [anyhow::Error::is] to tell it apart from other errors,
+/// the error of the [Verifier] follows it in the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    index: u32,
+}
+impl VerifyError {
+    /// Create a new [VerifyError].
+    #[must_use]
+    #[inline]
+    pub fn new(index: u32) -> Self {
+        Self { index }
+    }
+    /// Get the index of the block failing verification.
+    #[must_use]
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Block {} failed verification.", self.index)
+    }
+}
+impl std::error::Error for VerifyError {}
+
+#[async_trait]
+impl Signer for SecretKey {
+    #[inline]
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+    #[inline]
+    async fn sign(&self, msg: &[u8]) -> Result<[u8; SCHEME_SIGNATURE_LENGTH]> {
+        Ok(*sign(self, msg))
+    }
+}
+
+impl Verifier for PublicKey {
+    #[inline]
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+    #[inline]
+    fn verify(&self, msg: &[u8], signature: &[u8; SCHEME_SIGNATURE_LENGTH]) -> Result<()> {
+        verify(self, msg, &Signature::new(*signature))
+    }
+}
+
+/// [KeyPair] is an `Ed25519` [PublicKey] and its [SecretKey],
+/// held as a [ZeroizingKey].
+///
+/// `Debug` only shows the [PublicKey].
+#[derive(Clone)]
+pub struct KeyPair {
+    /// The [PublicKey].
+    pub pk: PublicKey,
+    /// The [SecretKey], wiped from memory when dropped.
+    pub sk: ZeroizingKey,
+}
+impl KeyPair {
+    /// Generate a new random [KeyPair].
+    #[must_use]
+    #[inline]
+    pub fn generate() -> Self {
+        Self::from_compact(ed25519_compact::KeyPair::generate())
+    }
+    /// Create the [KeyPair] of a [Seed].
+    #[must_use]
+    #[inline]
+    pub fn from_seed(seed: Seed) -> Self {
+        Self::from_compact(ed25519_compact::KeyPair::from_seed(seed))
+    }
+    /// Create a [KeyPair] from the bytes of its [SecretKey] and [PublicKey].
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let keypair = ed25519_compact::KeyPair::from_slice(bytes)
+            .map_err(|err| anyhow!("Invalid key pair: {}.", err))?;
+        Ok(Self::from_compact(keypair))
+    }
+
+    fn from_compact(keypair: ed25519_compact::KeyPair) -> Self {
+        let ed25519_compact::KeyPair { pk, sk } = keypair;
+        Self {
+            pk,
+            sk: ZeroizingKey::new(sk),
+        }
+    }
+}
+impl fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("pk", &self.pk)
+            .finish_non_exhaustive()
+    }
+}
+
+/// [ZeroizingKey] holds a [SecretKey], wiped from memory when dropped.
+///
+/// `Debug` only shows the [PublicKey],
+/// the [SecretKey] is only reachable through [ZeroizingKey::expose].
+/// A plain [SecretKey] only wipes a copy of itself on drop.
+/// Keys are compared in constant time.
+#[derive(Clone)]
+pub struct ZeroizingKey {
+    key: SecretKey,
+}
+impl ZeroizingKey {
+    /// Create a new [ZeroizingKey].
+    #[must_use]
+    #[inline]
+    pub fn new(key: SecretKey) -> Self {
+        Self { key }
+    }
+    /// Expose the [SecretKey].
+    #[must_use]
+    #[inline]
+    pub fn expose(&self) -> &SecretKey {
+        &self.key
+    }
+    /// Get the [PublicKey] of the [SecretKey].
+    #[must_use]
+    #[inline]
+    pub fn public_key(&self) -> PublicKey {
+        self.key.public_key()
+    }
+}
+impl From<SecretKey> for ZeroizingKey {
+    #[inline]
+    fn from(key: SecretKey) -> Self {
+        Self::new(key)
+    }
+}
+impl Drop for ZeroizingKey {
+    fn drop(&mut self) {
+        let key: &mut [u8; SecretKey::BYTES] = &mut self.key;
+        key.zeroize();
+    }
+}
+impl PartialEq for ZeroizingKey {
+    fn eq(&self, other: &Self) -> bool {
+        let difference = self
+            .key
+            .iter()
+            .zip(other.key.iter())
+            .fold(0u8, |difference, (a, b)| difference | black_box(a ^ b));
+        difference == 0
+    }
+}
+impl Eq for ZeroizingKey {}
+impl fmt::Debug for ZeroizingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZeroizingKey")
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Signer for ZeroizingKey {
+    #[inline]
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+    #[inline]
+    async fn sign(&self, msg: &[u8]) -> Result<[u8; SCHEME_SIGNATURE_LENGTH]> {
+        Ok(*sign(&self.key, msg))
+    }
+}
 
 /// Sign a byte slice.
 #[must_use]
@@ -25,8 +261,49 @@ mod tests {
     fn sign_verify() {
         let keypair = KeyPair::generate();
         let msg = b"hello";
-        let signature = sign(&keypair.sk, msg);
+        let signature = sign(keypair.sk.expose(), msg);
         assert!(verify(&keypair.pk, msg, &signature).is_ok());
         assert!(verify(&keypair.pk, b"oops", &signature).is_err());
     }
+
+    #[tokio::test]
+    async fn signer_verifier() {
+        let keypair = KeyPair::generate();
+        let msg = b"hello";
+        let signature = Signer::sign(&keypair.sk, msg).await.unwrap();
+        assert!(Verifier::verify(&keypair.pk, msg, &signature).is_ok());
+        assert!(Verifier::verify(&keypair.pk, b"oops", &signature).is_err());
+    }
+
+    #[tokio::test]
+    async fn zeroizing_key() {
+        let keypair = KeyPair::generate();
+        let key = ZeroizingKey::from(keypair.sk.expose().clone());
+        assert_eq!(key, keypair.sk);
+        assert_ne!(key, KeyPair::generate().sk);
+        assert_eq!(key.public_key(), keypair.pk);
+        let signature = Signer::sign(&key, b"hello").await.unwrap();
+        assert!(Verifier::verify(&keypair.pk, b"hello", &signature).is_ok());
+
+        let debug = format!("{:?}", key);
+        assert_eq!(
+            debug,
+            format!("ZeroizingKey {{ public_key: {:?}, .. }}", keypair.pk)
+        );
+        assert!(!debug.contains(&format!("{:?}", &keypair.sk.expose()[..32])));
+
+        let debug = format!("{:?}", keypair);
+        assert_eq!(debug, format!("KeyPair {{ pk: {:?}, .. }}", keypair.pk));
+        let restored = KeyPair::from_slice(keypair.sk.expose().as_slice()).unwrap();
+        assert_eq!(restored.sk, keypair.sk);
+        assert!(KeyPair::from_slice(&[0u8; 12]).is_err());
+    }
+
+    #[test]
+    fn scheme_tag() {
+        let custom = SignatureScheme::Custom(NonZeroU8::new(7).unwrap());
+        assert_eq!(SignatureScheme::from_u8(0), SignatureScheme::Ed25519);
+        assert_eq!(SignatureScheme::from_u8(custom.to_u8()), custom);
+        assert_eq!(SignatureScheme::default(), SignatureScheme::Ed25519);
+    }
 }
@@ -37,16 +37,53 @@
 
 mod block;
 mod core;
+#[cfg(any(test, feature = "fault"))]
+mod fault;
+mod fork;
 mod hash;
+mod head;
+mod header;
 mod keys;
+#[cfg(feature = "log")]
+mod log;
 mod merkle;
 mod merkle_tree_stream;
+mod proof;
+mod rotation;
+mod schema;
+#[cfg(feature = "sled")]
+mod sled;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stats;
 mod store;
 
 pub use index_access_storage::IndexAccess;
 
-pub use self::core::{Core, MAX_BLOCK_SIZE, MAX_CORE_LENGTH};
+pub use self::core::{Core, MAX_BLOCK_SIZE, MAX_CHECKPOINT_INTERVAL, MAX_CORE_LENGTH};
 pub use block::{Block, Signature, SIGNATURE_LENGTH};
+#[cfg(any(test, feature = "fault"))]
+pub use fault::{Fault, Faults, IndexAccessFault, InjectedError, Injection, Operation, Trigger};
+pub use fork::{verify_fork_proof, ForkError, ForkProof};
 pub use hash::Hash;
-pub use keys::{sign, verify, KeyPair, PublicKey, SecretKey, Seed};
-pub use merkle::{Merkle, Node, NodeTrait};
+pub use head::{verify_signed_head, SignedHead};
+pub use keys::{
+    sign, verify, KeyPair, PublicKey, SecretKey, Seed, SignatureScheme, Signer, Verifier,
+    VerifyError, ZeroizingKey, SCHEME_SIGNATURE_LENGTH,
+};
+#[cfg(feature = "log")]
+pub use log::{Fsync, IndexAccessLog, LogOptions, DEFAULT_SEGMENT_SIZE};
+pub use merkle::{hash_roots, Merkle, Node, NodeTrait};
+#[cfg(feature = "sled")]
+pub use self::sled::{IndexAccessSled, SledStorage};
+pub use proof::{
+    verify_consistency, verify_proof, verify_signed_consistency, ConsistencyProof, Proof,
+};
+pub use rotation::{rotated_verifier, verify_key_rotation, KeyRotation};
+pub use schema::{sign_schema, verify_schema};
+pub use snapshot::Snapshot;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{IndexAccessSqlite, SqliteStorage, SqliteTransaction};
+pub use stats::Stats;
+pub use store::ClearedError;
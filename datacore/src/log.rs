@@ -0,0 +1,489 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+
+use crate::IndexAccess;
+
+/// Length of a record header, index, data length and checksum.
+const RECORD_HEADER_LENGTH: usize = 12;
+/// Default maximum size of a segment file.
+pub const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// When [IndexAccessLog] syncs written records to disk.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Fsync {
+    /// Sync after every write.
+    Always,
+    /// Sync after every `n` writes.
+    Every(u32),
+    /// Leave syncing to the operating system,
+    /// a segment is still synced when it is sealed.
+    #[default]
+    Never,
+}
+
+/// Options of an [IndexAccessLog].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LogOptions {
+    segment_size: u64,
+    fsync: Fsync,
+}
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEGMENT_SIZE, Fsync::default())
+    }
+}
+impl LogOptions {
+    /// Create new [LogOptions].
+    ///
+    /// A new segment is started when a record would grow
+    /// the current one past `segment_size` bytes.
+    #[must_use]
+    #[inline]
+    pub fn new(segment_size: u64, fsync: Fsync) -> Self {
+        Self {
+            segment_size,
+            fsync,
+        }
+    }
+
+    /// Get the maximum size of a segment file.
+    #[must_use]
+    #[inline]
+    pub fn segment_size(&self) -> u64 {
+        self.segment_size
+    }
+    /// Get the [Fsync] policy.
+    #[must_use]
+    #[inline]
+    pub fn fsync(&self) -> Fsync {
+        self.fsync
+    }
+}
+
+/// Position of the data of a record.
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    segment: u32,
+    offset: u64,
+    length: u32,
+}
+
+/// [IndexAccessLog] stores records in append-only segment files in one directory.
+///
+/// Every write appends a record `[index][length][checksum][data]`,
+/// the latest record of an index wins.
+/// Offsets of the records are kept in memory and rebuilt on open.
+///
+/// The checksums are checked on open and on every read.
+/// On open a partial or corrupted record at the end of the last segment,
+/// e.g. torn by a crash, is cut off.
+/// A corrupted record in a sealed segment fails the open with
+/// [ErrorKind::InvalidData], no segment is truncated or removed.
+/// A segment is synced before the next one is started
+/// and the directory is synced when a segment file is created.
+///
+/// Replaced records take space until the `Core` is compacted
+/// into a new [IndexAccessLog].
+#[derive(Debug)]
+pub struct IndexAccessLog {
+    dir: PathBuf,
+    options: LogOptions,
+    index: HashMap<u32, Location>,
+    segment: u32,
+    segment_length: u64,
+    active: File,
+    reader: Option<(u32, File)>,
+    unsynced: u32,
+}
+impl IndexAccessLog {
+    /// Open an [IndexAccessLog] in `dir` with default [LogOptions].
+    pub async fn open(dir: &Path) -> io::Result<Self> {
+        Self::with_options(dir, LogOptions::default()).await
+    }
+
+    /// Open an [IndexAccessLog] in `dir`.
+    pub async fn with_options(dir: &Path, options: LogOptions) -> io::Result<Self> {
+        fs::create_dir_all(dir).await?;
+        let segments = list_segments(dir).await?;
+
+        let mut index = HashMap::new();
+        let mut segment_length = 0;
+        let segment = segments.last().copied().unwrap_or(0);
+        for &scanned in &segments {
+            segment_length = scan_segment(dir, scanned, scanned == segment, &mut index).await?;
+        }
+        let active = open_segment(dir, segment).await?;
+        sync_dir(dir).await?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            options,
+            index,
+            segment,
+            segment_length,
+            active,
+            reader: None,
+            unsynced: 0,
+        })
+    }
+
+    /// Get the [LogOptions].
+    #[must_use]
+    #[inline]
+    pub fn options(&self) -> &LogOptions {
+        &self.options
+    }
+    /// Get the number of segment files.
+    #[must_use]
+    #[inline]
+    pub fn segments(&self) -> u32 {
+        self.segment + 1
+    }
+
+    /// Sync the records written to the current segment to disk.
+    pub async fn sync(&mut self) -> io::Result<()> {
+        self.active.sync_data().await?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Seal the current segment and start a new one.
+    async fn roll(&mut self) -> io::Result<()> {
+        self.sync().await?;
+        self.segment += 1;
+        self.segment_length = 0;
+        self.active = open_segment(&self.dir, self.segment).await?;
+        sync_dir(&self.dir).await
+    }
+}
+#[async_trait]
+impl IndexAccess for IndexAccessLog {
+    type Error = io::Error;
+
+    async fn write(&mut self, index: u32, data: &[u8]) -> io::Result<()> {
+        let length = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "Record is too large."))?;
+        let record_length = (RECORD_HEADER_LENGTH + data.len()) as u64;
+        if self.segment_length > 0
+            && self.segment_length + record_length > self.options.segment_size
+        {
+            self.roll().await?;
+        }
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LENGTH + data.len());
+        record.extend_from_slice(&index.to_le_bytes());
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&checksum(index, data));
+        record.extend_from_slice(data);
+        self.active.write_all(&record).await?;
+        self.active.flush().await?;
+
+        self.unsynced += 1;
+        match self.options.fsync {
+            Fsync::Always => self.sync().await?,
+            Fsync::Every(n) if self.unsynced >= n => self.sync().await?,
+            _ => (),
+        }
+
+        let location = Location {
+            segment: self.segment,
+            offset: self.segment_length + RECORD_HEADER_LENGTH as u64,
+            length,
+        };
+        self.index.insert(index, location);
+        self.segment_length += record_length;
+        Ok(())
+    }
+
+    async fn read(&mut self, index: u32) -> io::Result<Option<Vec<u8>>> {
+        let location = match self.index.get(&index) {
+            Some(location) => *location,
+            None => return Ok(None),
+        };
+        let reader = match &mut self.reader {
+            Some((segment, reader)) if *segment == location.segment => reader,
+            reader => {
+                let file = File::open(segment_path(&self.dir, location.segment)).await?;
+                &mut reader.insert((location.segment, file)).1
+            }
+        };
+        let start = location.offset - RECORD_HEADER_LENGTH as u64;
+        reader.seek(SeekFrom::Start(start)).await?;
+        let mut record = vec![0u8; RECORD_HEADER_LENGTH + location.length as usize];
+        reader.read_exact(&mut record).await?;
+        let data = record.split_off(RECORD_HEADER_LENGTH);
+        if record[..4] != index.to_le_bytes() || record[8..] != checksum(index, &data) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Corrupted record {} in log segment {}.",
+                    index, location.segment
+                ),
+            ));
+        }
+        Ok(Some(data))
+    }
+}
+
+/// Checksum of a record, the first bytes of the BLAKE3 hash of its index and data.
+fn checksum(index: u32, data: &[u8]) -> [u8; 4] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&index.to_le_bytes());
+    hasher.update(data);
+    let mut checksum = [0u8; 4];
+    checksum.copy_from_slice(&hasher.finalize().as_bytes()[..4]);
+    checksum
+}
+
+fn segment_path(dir: &Path, segment: u32) -> PathBuf {
+    dir.join(format!("{:010}.log", segment))
+}
+
+/// Sync `dir`, so created segment files are kept after a crash.
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir).await?.sync_all().await
+}
+#[cfg(not(unix))]
+async fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+async fn open_segment(dir: &Path, segment: u32) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(segment_path(dir, segment))
+        .await
+}
+
+/// List the segments in `dir`, they must be numbered from `0` without gaps.
+async fn list_segments(dir: &Path) -> io::Result<Vec<u32>> {
+    let mut segments = vec![];
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let segment = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".log"))
+            .and_then(|segment| segment.parse::<u32>().ok());
+        if let Some(segment) = segment {
+            segments.push(segment);
+        }
+    }
+    segments.sort_unstable();
+    for (expected, &segment) in segments.iter().enumerate() {
+        if segment as usize != expected {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Missing log segment {}.", expected),
+            ));
+        }
+    }
+    Ok(segments)
+}
+
+/// Add the records of `segment` to `index` and return its valid length.
+///
+/// The `last` segment is truncated at its first partial or corrupted record,
+/// e.g. torn by a crash. A sealed segment was synced before the next one
+/// was started, so a bad record in it is an error.
+async fn scan_segment(
+    dir: &Path,
+    segment: u32,
+    last: bool,
+    index: &mut HashMap<u32, Location>,
+) -> io::Result<u64> {
+    let path = segment_path(dir, segment);
+    let file = File::open(&path).await?;
+    let file_length = file.metadata().await?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut position = 0;
+    let mut header = [0u8; RECORD_HEADER_LENGTH];
+    while position < file_length {
+        let mut valid = position + RECORD_HEADER_LENGTH as u64 <= file_length;
+        if valid {
+            reader.read_exact(&mut header).await?;
+        }
+        let record_index = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let length = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let data_offset = position + RECORD_HEADER_LENGTH as u64;
+        valid = valid && data_offset + u64::from(length) <= file_length;
+        if valid {
+            let mut data = vec![0u8; length as usize];
+            reader.read_exact(&mut data).await?;
+            valid = checksum(record_index, &data) == header[8..];
+        }
+
+        if !valid {
+            if !last {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Corrupted record at {} in sealed log segment {}.",
+                        position, segment
+                    ),
+                ));
+            }
+            let file = OpenOptions::new().write(true).open(&path).await?;
+            file.set_len(position).await?;
+            file.sync_all().await?;
+            return Ok(position);
+        }
+        let location = Location {
+            segment,
+            offset: data_offset,
+            length,
+        };
+        index.insert(record_index, location);
+        position = data_offset + u64::from(length);
+    }
+    Ok(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn write_read() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut log = IndexAccessLog::open(dir.path()).await?;
+        assert_eq!(log.read(0).await?, None);
+        log.write(0, b"hello").await?;
+        log.write(u32::MAX, b"header").await?;
+        log.write(1, b"").await?;
+        log.write(0, b"world").await?;
+        assert_eq!(log.read(0).await?.unwrap(), b"world");
+        assert_eq!(log.read(1).await?.unwrap(), b"");
+        drop(log);
+
+        let mut log = IndexAccessLog::open(dir.path()).await?;
+        assert_eq!(log.read(0).await?.unwrap(), b"world");
+        assert_eq!(log.read(u32::MAX).await?.unwrap(), b"header");
+        assert_eq!(log.read(2).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn segments() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = LogOptions::new(64, Fsync::Every(2));
+        let mut log = IndexAccessLog::with_options(dir.path(), options).await?;
+        for index in 0..10u32 {
+            log.write(index, &[index as u8; 20]).await?;
+        }
+        assert_eq!(log.segments(), 5);
+        log.write(3, &[0u8; 100]).await?;
+        assert_eq!(log.segments(), 6);
+        drop(log);
+
+        let mut log = IndexAccessLog::with_options(dir.path(), options).await?;
+        assert_eq!(log.segments(), 6);
+        assert_eq!(log.read(9).await?.unwrap(), [9u8; 20]);
+        assert_eq!(log.read(3).await?.unwrap(), [0u8; 100]);
+        assert_eq!(log.read(0).await?.unwrap(), [0u8; 20]);
+
+        fs::remove_file(segment_path(dir.path(), 2)).await?;
+        assert!(IndexAccessLog::open(dir.path()).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recover_partial_record() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = LogOptions::new(DEFAULT_SEGMENT_SIZE, Fsync::Always);
+        let mut log = IndexAccessLog::with_options(dir.path(), options).await?;
+        log.write(0, b"kept").await?;
+        log.write(1, b"torn").await?;
+        drop(log);
+
+        // lose the end of the last record
+        let path = segment_path(dir.path(), 0);
+        let length = fs::metadata(&path).await?.len();
+        let file = OpenOptions::new().write(true).open(&path).await?;
+        file.set_len(length - 2).await?;
+        drop(file);
+
+        let mut log = IndexAccessLog::open(dir.path()).await?;
+        assert_eq!(log.read(0).await?.unwrap(), b"kept");
+        assert_eq!(log.read(1).await?, None);
+        log.write(1, b"again").await?;
+        drop(log);
+
+        // a record with a wrong checksum is cut off too
+        let mut file = OpenOptions::new().append(true).open(&path).await?;
+        let mut record = 2u32.to_le_bytes().to_vec();
+        record.extend_from_slice(&3u32.to_le_bytes());
+        record.extend_from_slice(&[0u8; 4]);
+        record.extend_from_slice(b"bad");
+        file.write_all(&record).await?;
+        drop(file);
+
+        let mut log = IndexAccessLog::open(dir.path()).await?;
+        assert_eq!(log.read(1).await?.unwrap(), b"again");
+        assert_eq!(log.read(2).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recover_corrupted_segment() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = LogOptions::new(64, Fsync::Never);
+        let mut log = IndexAccessLog::with_options(dir.path(), options).await?;
+        for index in 0..6u32 {
+            log.write(index, &[index as u8; 20]).await?;
+        }
+        log.write(0, &[9u8; 20]).await?;
+        assert_eq!(log.segments(), 4);
+        drop(log);
+
+        // flip a byte in the data of the second record of sealed segment 1
+        let path = segment_path(dir.path(), 1);
+        let mut segment = fs::read(&path).await?;
+        let last = segment.len() - 1;
+        segment[last] ^= 1;
+        fs::write(&path, &segment).await?;
+
+        // the open fails and no segment is touched
+        let err = IndexAccessLog::with_options(dir.path(), options)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(fs::read(&path).await?, segment);
+        for later in 2..4 {
+            fs::metadata(segment_path(dir.path(), later)).await?;
+        }
+        segment[last] ^= 1;
+        fs::write(&path, &segment).await?;
+
+        // a bad record at the end of the last segment is cut off
+        let path = segment_path(dir.path(), 3);
+        let mut segment = fs::read(&path).await?;
+        let last = segment.len() - 1;
+        segment[last] ^= 1;
+        fs::write(&path, &segment).await?;
+        let mut log = IndexAccessLog::with_options(dir.path(), options).await?;
+        assert_eq!(log.segments(), 4);
+        assert_eq!(log.read(0).await?.unwrap(), [0u8; 20]);
+        assert_eq!(log.read(3).await?.unwrap(), [3u8; 20]);
+        assert_eq!(log.read(5).await?.unwrap(), [5u8; 20]);
+        assert_eq!(fs::metadata(&path).await?.len(), 0);
+
+        // corruption after open fails the read
+        log.write(3, &[3u8; 20]).await?;
+        let path = segment_path(dir.path(), 0);
+        let mut segment = fs::read(&path).await?;
+        segment[RECORD_HEADER_LENGTH] ^= 1;
+        fs::write(&path, &segment).await?;
+        let err = log.read(0).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(log.read(3).await?.unwrap(), [3u8; 20]);
+        Ok(())
+    }
+}
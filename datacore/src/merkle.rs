@@ -4,7 +4,7 @@ use std::io::{Cursor, Read};
 use std::mem::size_of;
 
 use crate::hash::{Hash, HASH_SIZE};
-use crate::merkle_tree_stream::{HashMethods, MerkleTreeStream};
+use crate::merkle_tree_stream::{flat_tree, HashMethods, MerkleTreeStream};
 
 pub use crate::merkle_tree_stream::Node as NodeTrait;
 
@@ -35,6 +35,17 @@ impl Node {
         })
     }
 
+    /// Create the parent [Node] of 2 sibling [Node]s.
+    #[must_use]
+    #[inline]
+    pub fn parent(left: &Node, right: &Node) -> Self {
+        Self {
+            index: flat_tree::parent(left.index),
+            length: left.length + right.length,
+            hash: H.parent(left, right),
+        }
+    }
+
     /// Serialize [Node].
     #[inline]
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
@@ -134,6 +145,53 @@ impl Merkle {
     }
 }
 
+/// Hash root [Node]s of a tree, this is the hash signed by tree signatures.
+#[must_use]
+#[inline]
+pub fn hash_roots(roots: &[Node]) -> Hash {
+    let hashes = roots.iter().map(|node| &node.hash).collect::<Vec<&Hash>>();
+    let lengths = roots.iter().map(|node| node.length).collect::<Vec<u32>>();
+    Hash::from_roots(&hashes, &lengths)
+}
+
+/// Parent [Node]s completed by appending `leaf` to a tree with `roots`,
+/// from the bottom up.
+#[must_use]
+pub(crate) fn parent_nodes(roots: &[Node], leaf: &Node) -> Vec<Node> {
+    let mut nodes = vec![];
+    let mut node = leaf.clone();
+    for root in roots.iter().rev() {
+        if flat_tree::parent(root.index) != flat_tree::parent(node.index) {
+            break;
+        }
+        node = Node::parent(root, &node);
+        nodes.push(node.clone());
+    }
+    nodes
+}
+
+/// Number of parent [Node]s completed by appending the block at `index`.
+#[must_use]
+pub(crate) fn parent_count(index: u32) -> usize {
+    index.trailing_ones() as usize
+}
+
+/// Flat tree indexes of the root [Node]s of a tree with `length` blocks.
+#[must_use]
+pub(crate) fn root_indexes(length: u32) -> Vec<u64> {
+    let mut indexes = vec![];
+    let mut offset = 0u64;
+    let mut remaining = u64::from(length);
+    while remaining > 0 {
+        let depth = u64::from(63 - remaining.leading_zeros());
+        let size = 1 << depth;
+        indexes.push(flat_tree::index(depth, offset / size));
+        offset += size;
+        remaining -= size;
+    }
+    indexes
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,4 +251,36 @@ mod tests {
         assert_eq!(roots.get(0).unwrap().index(), 1);
         assert_eq!(roots.get(1).unwrap().index(), 4);
     }
+    #[test]
+    fn parent() {
+        let mut merkle = Merkle::default();
+        merkle.next(Hash::from_leaf("a".as_bytes()).unwrap(), 1);
+        let a = merkle.roots()[0].clone();
+        merkle.next(Hash::from_leaf("b".as_bytes()).unwrap(), 2);
+        let b = Node::new(2, Hash::from_leaf("b".as_bytes()).unwrap(), 2);
+        assert_eq!(Node::parent(&a, &b), merkle.roots()[0]);
+    }
+    #[test]
+    fn root_indexes_match_roots() {
+        let mut merkle = Merkle::default();
+        assert!(root_indexes(0).is_empty());
+        for length in 1..=20 {
+            merkle.next(Hash::from_leaf(&[length as u8]).unwrap(), 1);
+            let indexes: Vec<u64> = merkle.roots().iter().map(|root| root.index()).collect();
+            assert_eq!(root_indexes(length), indexes);
+        }
+    }
+    #[test]
+    fn parent_nodes_match_roots() {
+        let mut merkle = Merkle::default();
+        for index in 0..20u32 {
+            let hash = Hash::from_leaf(&[index as u8]).unwrap();
+            let leaf = Node::new(2 * u64::from(index), hash, 1);
+            let nodes = parent_nodes(merkle.roots(), &leaf);
+            merkle.next(leaf.hash().clone(), 1);
+            assert_eq!(nodes.len(), parent_count(index));
+            let last = nodes.last().unwrap_or(&leaf);
+            assert_eq!(merkle.roots().last(), Some(last));
+        }
+    }
 }
@@ -1,4 +1,4 @@
-mod flat_tree;
+pub(crate) mod flat_tree;
 
 /// Functions that need to be implemented for `MerkleTreeStream`.
 pub trait HashMethods {
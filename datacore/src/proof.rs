@@ -0,0 +1,264 @@
+//! Merkle proofs for blocks of a `Core` and between its lengths.
+
+use anyhow::{ensure, Result};
+
+use crate::head::{verify_signed_head, SignedHead};
+use crate::merkle::{hash_roots, root_indexes};
+use crate::merkle_tree_stream::flat_tree;
+use crate::rotation::rotated_verifier;
+use crate::{Hash, KeyRotation, Node, NodeTrait, PublicKey, Verifier, SCHEME_SIGNATURE_LENGTH};
+
+/// [Proof] of the position of a block in a `Core`.
+///
+/// Authenticates a block against the tree signature of a checkpoint,
+/// for blocks that do not carry a tree signature of their own.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Proof {
+    index: u32,
+    length: u32,
+    nodes: Vec<Node>,
+    roots: Vec<Node>,
+    signature: [u8; SCHEME_SIGNATURE_LENGTH],
+}
+impl Proof {
+    /// Create a new [Proof].
+    #[must_use]
+    #[inline]
+    pub fn new(
+        index: u32,
+        length: u32,
+        nodes: Vec<Node>,
+        roots: Vec<Node>,
+        signature: [u8; SCHEME_SIGNATURE_LENGTH],
+    ) -> Self {
+        Self {
+            index,
+            length,
+            nodes,
+            roots,
+            signature,
+        }
+    }
+
+    /// Get the index of the proven block.
+    #[must_use]
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    /// Get the length of the `Core` at the checkpoint.
+    #[must_use]
+    #[inline]
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+    /// Get the sibling [Node]s from the block up to its root.
+    #[must_use]
+    #[inline]
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    /// Get the root [Node]s at the checkpoint.
+    #[must_use]
+    #[inline]
+    pub fn roots(&self) -> &[Node] {
+        &self.roots
+    }
+    /// Get the tree signature of the checkpoint.
+    #[must_use]
+    #[inline]
+    pub fn signature(&self) -> &[u8; SCHEME_SIGNATURE_LENGTH] {
+        &self.signature
+    }
+}
+
+/// Verify that `data` is the block proven by [Proof]
+/// in the `Core` `core` with the [Verifier] of its `PublicKey`.
+///
+/// The checkpoint is signed by the key writing the `Core` at it,
+/// resolved through the [KeyRotation]s of the `Core`, see [rotated_verifier].
+pub fn verify_proof(
+    core: &PublicKey,
+    verifier: &dyn Verifier,
+    rotations: &[KeyRotation],
+    data: &[u8],
+    proof: &Proof,
+) -> Result<()> {
+    ensure!(proof.index < proof.length, "Block is not covered by the proof.");
+    let indexes = proof.roots.iter().map(NodeTrait::index).collect::<Vec<u64>>();
+    ensure!(
+        indexes == root_indexes(proof.length),
+        "Proof roots do not match its length."
+    );
+
+    let mut node = Node::new(
+        2 * u64::from(proof.index),
+        Hash::from_leaf(data)?,
+        u32::try_from(data.len())?,
+    );
+    for sibling in &proof.nodes {
+        ensure!(
+            sibling.index() == flat_tree::sibling(node.index()),
+            "Invalid proof node."
+        );
+        node = match sibling.index() < node.index() {
+            true => Node::parent(sibling, &node),
+            false => Node::parent(&node, sibling),
+        };
+    }
+    ensure!(proof.roots.contains(&node), "Proof does not match its roots.");
+
+    let verifier = rotated_verifier(core, verifier, rotations, proof.length - 1)?;
+    verifier.verify(&hash_roots(&proof.roots), &proof.signature)
+}
+
+/// [ConsistencyProof] that a `Core` at one length extends
+/// the same `Core` at an older length.
+///
+/// Holds the tree [Node]s covering the blocks appended in between.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConsistencyProof {
+    old_length: u32,
+    new_length: u32,
+    nodes: Vec<Node>,
+}
+impl ConsistencyProof {
+    /// Create a new [ConsistencyProof].
+    #[must_use]
+    #[inline]
+    pub fn new(old_length: u32, new_length: u32, nodes: Vec<Node>) -> Self {
+        Self {
+            old_length,
+            new_length,
+            nodes,
+        }
+    }
+
+    /// Get the older length.
+    #[must_use]
+    #[inline]
+    pub fn old_length(&self) -> u32 {
+        self.old_length
+    }
+    /// Get the newer length.
+    #[must_use]
+    #[inline]
+    pub fn new_length(&self) -> u32 {
+        self.new_length
+    }
+    /// Get the [Node]s covering the blocks between both lengths.
+    #[must_use]
+    #[inline]
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+}
+
+/// Flat tree indexes of the [Node]s covering blocks `old_length..new_length`.
+#[must_use]
+pub(crate) fn consistency_indexes(old_length: u32, new_length: u32) -> Vec<u64> {
+    let mut indexes = vec![];
+    let mut offset = u64::from(old_length);
+    let end = u64::from(new_length);
+    while offset < end {
+        let mut depth = u64::from(offset.trailing_zeros().min(63));
+        while offset + (1 << depth) > end {
+            depth -= 1;
+        }
+        indexes.push(flat_tree::index(depth, offset >> depth));
+        offset += 1 << depth;
+    }
+    indexes
+}
+
+/// Verify that `new_roots` extend `old_roots` as proven by [ConsistencyProof].
+///
+/// The roots are expected to come from verified tree signatures,
+/// see [hash_roots] and [verify_signed_consistency].
+///
+/// [hash_roots]: crate::hash_roots
+pub fn verify_consistency(
+    old_roots: &[Node],
+    new_roots: &[Node],
+    proof: &ConsistencyProof,
+) -> Result<()> {
+    ensure!(
+        proof.old_length <= proof.new_length,
+        "Old length is past the new length."
+    );
+    let indexes = old_roots.iter().map(NodeTrait::index).collect::<Vec<u64>>();
+    ensure!(
+        indexes == root_indexes(proof.old_length),
+        "Old roots do not match the old length."
+    );
+    let indexes = proof.nodes.iter().map(NodeTrait::index).collect::<Vec<u64>>();
+    ensure!(
+        indexes == consistency_indexes(proof.old_length, proof.new_length),
+        "Invalid proof nodes."
+    );
+
+    let mut roots = old_roots.to_vec();
+    for node in &proof.nodes {
+        let mut node = node.clone();
+        while let Some(root) = roots.last() {
+            if root.index() != flat_tree::sibling(node.index()) {
+                break;
+            }
+            node = Node::parent(root, &node);
+            roots.pop();
+        }
+        roots.push(node);
+    }
+    ensure!(roots == new_roots, "New roots do not extend the old roots.");
+    Ok(())
+}
+
+/// Verify that the [SignedHead] `new` extends the [SignedHead] `old`
+/// of the `Core` `core` as proven by [ConsistencyProof].
+///
+/// Both heads are verified with [verify_signed_head].
+pub fn verify_signed_consistency(
+    core: &PublicKey,
+    verifier: &dyn Verifier,
+    rotations: &[KeyRotation],
+    old: &SignedHead,
+    new: &SignedHead,
+    proof: &ConsistencyProof,
+) -> Result<()> {
+    verify_signed_head(core, verifier, rotations, old)?;
+    verify_signed_head(core, verifier, rotations, new)?;
+    ensure!(
+        old.length() == proof.old_length,
+        "Old head does not match the old length."
+    );
+    ensure!(
+        new.length() == proof.new_length,
+        "New head does not match the new length."
+    );
+    verify_consistency(old.roots(), new.roots(), proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consistency_indexes_cover_range() {
+        assert!(consistency_indexes(3, 3).is_empty());
+        assert_eq!(consistency_indexes(0, 4), root_indexes(4));
+        assert_eq!(consistency_indexes(0, 7), root_indexes(7));
+        assert_eq!(consistency_indexes(3, 8), vec![6, 11]);
+        assert_eq!(consistency_indexes(5, 7), vec![10, 12]);
+        for old in 0..20 {
+            for new in old..20 {
+                let mut offset = u64::from(old);
+                for index in consistency_indexes(old, new) {
+                    let (left, right) = flat_tree::spans(index);
+                    assert_eq!(left / 2, offset);
+                    offset = right / 2 + 1;
+                }
+                assert_eq!(offset, u64::from(new));
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{ensure, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read};
+
+use crate::{PublicKey, SignatureScheme, Signer, Verifier, SCHEME_SIGNATURE_LENGTH};
+
+/// Current version of the [KeyRotation] format.
+pub const KEY_ROTATION_VERSION: u8 = 1;
+/// Byte length of a serialized [KeyRotation].
+pub const KEY_ROTATION_LENGTH: usize = 4 + 1 + 4 + PublicKey::BYTES + SCHEME_SIGNATURE_LENGTH;
+
+/// Marks a block of a `Core` as a [KeyRotation] record.
+const KEY_ROTATION_MAGIC: &[u8; 4] = b"DCKR";
+
+/// [KeyRotation] hands the writing of a `Core` over to a new [PublicKey].
+///
+/// The record is appended as the block at `index`,
+/// signed by the key writing the `Core` up to that block.
+/// The block is marked as a rotation by its `Signature`,
+/// data starting like a record is not a rotation.
+/// Blocks after `index` are signed by the new key.
+///
+/// The signature covers the `PublicKey` of the `Core`,
+/// so a record can not be replayed in another `Core`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct KeyRotation {
+    index: u32,
+    public_key: PublicKey,
+    signature: [u8; SCHEME_SIGNATURE_LENGTH],
+}
+impl KeyRotation {
+    /// Create a new [KeyRotation].
+    #[must_use]
+    #[inline]
+    pub fn new(
+        index: u32,
+        public_key: PublicKey,
+        signature: [u8; SCHEME_SIGNATURE_LENGTH],
+    ) -> Self {
+        Self {
+            index,
+            public_key,
+            signature,
+        }
+    }
+
+    /// Sign a [KeyRotation] to `public_key` at `index` of the `Core` `core`
+    /// with the [Signer] currently writing it.
+    pub async fn sign(
+        signer: &dyn Signer,
+        core: &PublicKey,
+        index: u32,
+        public_key: PublicKey,
+    ) -> Result<Self> {
+        let signature = signer.sign(&message(core, index, &public_key)).await?;
+        Ok(Self::new(index, public_key, signature))
+    }
+
+    /// Serialize [KeyRotation].
+    #[inline]
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(KEY_ROTATION_LENGTH);
+        data.extend_from_slice(KEY_ROTATION_MAGIC);
+        data.write_u8(KEY_ROTATION_VERSION)?;
+        data.write_u32::<LittleEndian>(self.index)?;
+        data.extend_from_slice(self.public_key.as_slice());
+        data.extend_from_slice(&self.signature);
+        Ok(data)
+    }
+    /// Deserialize [KeyRotation].
+    #[inline]
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        ensure!(
+            data.starts_with(KEY_ROTATION_MAGIC),
+            "Not a key rotation record."
+        );
+        ensure!(
+            data.len() == KEY_ROTATION_LENGTH,
+            "Key rotation record has {} bytes, expected {}.",
+            data.len(),
+            KEY_ROTATION_LENGTH
+        );
+        let mut rdr = Cursor::new(&data[KEY_ROTATION_MAGIC.len()..]);
+        let version = rdr.read_u8()?;
+        ensure!(
+            version == KEY_ROTATION_VERSION,
+            "Unsupported key rotation version {}.",
+            version
+        );
+        let index = rdr.read_u32::<LittleEndian>()?;
+        let mut public_key = [0u8; PublicKey::BYTES];
+        rdr.read_exact(&mut public_key)?;
+        let mut signature = [0u8; SCHEME_SIGNATURE_LENGTH];
+        rdr.read_exact(&mut signature)?;
+        Ok(Self {
+            index,
+            public_key: PublicKey::new(public_key),
+            signature,
+        })
+    }
+
+    /// Get the index of the block holding the record.
+    #[must_use]
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    /// Get the new [PublicKey], signing the blocks after the record.
+    #[must_use]
+    #[inline]
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+    /// Get the signature of the previous key.
+    #[must_use]
+    #[inline]
+    pub fn signature(&self) -> &[u8; SCHEME_SIGNATURE_LENGTH] {
+        &self.signature
+    }
+}
+
+/// Verify a [KeyRotation] of the `Core` `core`
+/// with the [Verifier] of the key it rotates from.
+pub fn verify_key_rotation(
+    core: &PublicKey,
+    verifier: &dyn Verifier,
+    rotation: &KeyRotation,
+) -> Result<()> {
+    ensure!(
+        verifier.scheme() == SignatureScheme::Ed25519,
+        "Keys can only be rotated with {:?}, not {:?}.",
+        SignatureScheme::Ed25519,
+        verifier.scheme()
+    );
+    let message = message(core, rotation.index, &rotation.public_key);
+    verifier.verify(&message, &rotation.signature)
+}
+
+/// Get the [Verifier] of the key writing the block at `index`
+/// of the `Core` `core`, written by `verifier` before its first [KeyRotation].
+///
+/// The `rotations` before the block are checked as a chain,
+/// each signed by the key before it, so only the `Core` has to be trusted.
+pub fn rotated_verifier<'a>(
+    core: &PublicKey,
+    verifier: &'a dyn Verifier,
+    rotations: &'a [KeyRotation],
+    index: u32,
+) -> Result<&'a dyn Verifier> {
+    let mut current = verifier;
+    let mut previous: Option<u32> = None;
+    for rotation in rotations
+        .iter()
+        .take_while(|rotation| rotation.index < index)
+    {
+        ensure!(
+            previous.is_none_or(|previous| previous < rotation.index),
+            "Key rotations are not ordered by index."
+        );
+        verify_key_rotation(core, current, rotation)?;
+        previous = Some(rotation.index);
+        current = &rotation.public_key;
+    }
+    Ok(current)
+}
+
+/// Message signed by a [KeyRotation].
+fn message(core: &PublicKey, index: u32, public_key: &PublicKey) -> Vec<u8> {
+    let mut message = Vec::with_capacity(KEY_ROTATION_MAGIC.len() + 4 + 2 * PublicKey::BYTES);
+    message.extend_from_slice(KEY_ROTATION_MAGIC);
+    message.extend_from_slice(core.as_slice());
+    message.extend_from_slice(&index.to_le_bytes());
+    message.extend_from_slice(public_key.as_slice());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+
+    #[tokio::test]
+    async fn to_bytes_from_bytes() -> Result<()> {
+        let core = KeyPair::generate();
+        let next = KeyPair::generate();
+        let rotation = KeyRotation::sign(&core.sk, &core.pk, 3, next.pk).await?;
+        let bytes = rotation.to_bytes()?;
+        assert_eq!(bytes.len(), KEY_ROTATION_LENGTH);
+        assert_eq!(KeyRotation::from_bytes(&bytes)?, rotation);
+        assert!(KeyRotation::from_bytes(&bytes[1..]).is_err());
+        assert!(KeyRotation::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify() -> Result<()> {
+        let core = KeyPair::generate();
+        let next = KeyPair::generate();
+        let rotation = KeyRotation::sign(&core.sk, &core.pk, 3, next.pk).await?;
+        verify_key_rotation(&core.pk, &core.pk, &rotation)?;
+        assert!(verify_key_rotation(&core.pk, &next.pk, &rotation).is_err());
+        assert!(verify_key_rotation(&next.pk, &core.pk, &rotation).is_err());
+
+        let forged = KeyRotation::new(4, next.pk, *rotation.signature());
+        assert!(verify_key_rotation(&core.pk, &core.pk, &forged).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rotated() -> Result<()> {
+        let core = KeyPair::generate();
+        let next = KeyPair::generate();
+        let last = KeyPair::generate();
+        let rotations = vec![
+            KeyRotation::sign(&core.sk, &core.pk, 2, next.pk).await?,
+            KeyRotation::sign(&next.sk, &core.pk, 5, last.pk).await?,
+        ];
+        let msg = b"message";
+        for (index, keypair) in [(2, &core), (3, &next), (5, &next), (6, &last)] {
+            let verifier = rotated_verifier(&core.pk, &core.pk, &rotations, index)?;
+            verifier.verify(msg, &keypair.sk.expose().sign(msg, None))?;
+        }
+
+        // the chain is checked up to the block
+        let broken = vec![
+            rotations[0].clone(),
+            KeyRotation::sign(&core.sk, &core.pk, 5, last.pk).await?,
+        ];
+        assert!(rotated_verifier(&core.pk, &core.pk, &broken, 5).is_ok());
+        assert!(rotated_verifier(&core.pk, &core.pk, &broken, 6).is_err());
+        let reversed = vec![rotations[1].clone(), rotations[0].clone()];
+        assert!(rotated_verifier(&core.pk, &core.pk, &reversed, 6).is_err());
+        Ok(())
+    }
+}
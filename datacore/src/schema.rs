@@ -0,0 +1,57 @@
+use anyhow::Result;
+
+use crate::{PublicKey, Signer, Verifier, SCHEME_SIGNATURE_LENGTH};
+
+/// Marks a message as a signed schema.
+const SCHEMA_MAGIC: &[u8; 4] = b"DCSC";
+
+/// Sign the `schema` of the block data of the `Core` `core`
+/// with the [Signer] of its [PublicKey].
+///
+/// The signature covers the `PublicKey` of the `Core`,
+/// so a schema can not be replayed in another `Core`.
+pub async fn sign_schema(
+    signer: &dyn Signer,
+    core: &PublicKey,
+    schema: &str,
+) -> Result<[u8; SCHEME_SIGNATURE_LENGTH]> {
+    signer.sign(&message(core, schema)).await
+}
+
+/// Verify the signed `schema` of the `Core` `core`
+/// with the [Verifier] of its [PublicKey].
+pub fn verify_schema(
+    core: &PublicKey,
+    verifier: &dyn Verifier,
+    schema: &str,
+    signature: &[u8; SCHEME_SIGNATURE_LENGTH],
+) -> Result<()> {
+    verifier.verify(&message(core, schema), signature)
+}
+
+/// Message signed for a schema.
+fn message(core: &PublicKey, schema: &str) -> Vec<u8> {
+    let mut message = Vec::with_capacity(SCHEMA_MAGIC.len() + PublicKey::BYTES + schema.len());
+    message.extend_from_slice(SCHEMA_MAGIC);
+    message.extend_from_slice(core.as_slice());
+    message.extend_from_slice(schema.as_bytes());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+
+    #[tokio::test]
+    async fn verify() -> Result<()> {
+        let core = KeyPair::generate();
+        let other = KeyPair::generate();
+        let signature = sign_schema(&core.sk, &core.pk, "json:message").await?;
+        verify_schema(&core.pk, &core.pk, "json:message", &signature)?;
+        assert!(verify_schema(&core.pk, &core.pk, "json:other", &signature).is_err());
+        assert!(verify_schema(&core.pk, &other.pk, "json:message", &signature).is_err());
+        assert!(verify_schema(&other.pk, &core.pk, "json:message", &signature).is_err());
+        Ok(())
+    }
+}
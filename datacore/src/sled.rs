@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use std::path::Path;
+
+use crate::{IndexAccess, PublicKey};
+
+/// Prefix of the names of the trees storing `Core`s.
+const TREE_PREFIX: &[u8] = b"core:";
+
+/// [SledStorage] is a shared handle of a `sled` database storing many `Core`s.
+///
+/// Every `Core` is stored in its own tree, named by its [PublicKey].
+/// Cloning the handle is cheap, all clones use the same database.
+///
+/// `sled` does blocking I/O: reads missing its cache and writes
+/// filling its log block the calling thread,
+/// run the `Core`s on a multi-threaded runtime.
+/// [SledStorage::flush] does not block.
+#[derive(Debug, Clone)]
+pub struct SledStorage {
+    db: sled::Db,
+}
+impl SledStorage {
+    /// Open or create the database at `path`.
+    pub fn open(path: &Path) -> sled::Result<Self> {
+        Ok(Self::from_db(sled::open(path)?))
+    }
+    /// Create a [SledStorage] from an open database.
+    #[must_use]
+    #[inline]
+    pub fn from_db(db: sled::Db) -> Self {
+        Self { db }
+    }
+
+    /// Access the database.
+    #[must_use]
+    #[inline]
+    pub fn db(&self) -> &sled::Db {
+        &self.db
+    }
+
+    /// Get the [IndexAccessSled] of the `Core` with `public_key`.
+    pub fn storage(&self, public_key: &PublicKey) -> sled::Result<IndexAccessSled> {
+        let tree = self.db.open_tree(tree_name(public_key))?;
+        Ok(IndexAccessSled { tree })
+    }
+
+    /// Get the [PublicKey]s of all stored `Core`s, ordered by key.
+    pub fn public_keys(&self) -> Vec<PublicKey> {
+        let mut names = self.db.tree_names();
+        names.sort_unstable();
+        names
+            .iter()
+            .filter_map(|name| name.strip_prefix(TREE_PREFIX))
+            .filter_map(|key| PublicKey::from_slice(key).ok())
+            .collect()
+    }
+
+    /// Remove the `Core` with `public_key`, return `false` if it was not stored.
+    pub fn remove(&self, public_key: &PublicKey) -> sled::Result<bool> {
+        self.db.drop_tree(tree_name(public_key))
+    }
+
+    /// Flush all `Core`s to disk.
+    pub async fn flush(&self) -> sled::Result<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+}
+
+fn tree_name(public_key: &PublicKey) -> Vec<u8> {
+    [TREE_PREFIX, public_key.as_slice()].concat()
+}
+
+/// [IndexAccessSled] stores the records of one `Core` in a tree of a [SledStorage].
+///
+/// Records are keyed by their big-endian index, so the tree iterates in order.
+#[derive(Debug, Clone)]
+pub struct IndexAccessSled {
+    tree: sled::Tree,
+}
+#[async_trait]
+impl IndexAccess for IndexAccessSled {
+    type Error = sled::Error;
+
+    async fn write(&mut self, index: u32, data: &[u8]) -> sled::Result<()> {
+        self.tree.insert(index.to_be_bytes(), data)?;
+        Ok(())
+    }
+
+    async fn read(&mut self, index: u32) -> sled::Result<Option<Vec<u8>>> {
+        Ok(self
+            .tree
+            .get(index.to_be_bytes())?
+            .map(|data| data.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyPair;
+
+    #[tokio::test]
+    async fn write_read() -> sled::Result<()> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let storage = SledStorage::from_db(db);
+        let a = KeyPair::generate().pk;
+        let b = KeyPair::generate().pk;
+
+        let mut a_storage = storage.storage(&a)?;
+        a_storage.write(0, b"a").await?;
+        a_storage.write(u32::MAX, b"header").await?;
+        let mut b_storage = storage.storage(&b)?;
+        assert_eq!(b_storage.read(0).await?, None);
+        b_storage.write(0, b"b").await?;
+
+        let mut a_storage = storage.clone().storage(&a)?;
+        assert_eq!(a_storage.read(0).await?.unwrap(), b"a");
+        assert_eq!(a_storage.read(u32::MAX).await?.unwrap(), b"header");
+        let mut keys = vec![a, b];
+        keys.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+        assert_eq!(storage.public_keys(), keys);
+
+        assert!(storage.remove(&a)?);
+        assert!(!storage.remove(&a)?);
+        assert_eq!(storage.public_keys(), vec![b]);
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use crate::Node;
+
+/// [Snapshot] pins the state of a `Core` at a fixed length.
+///
+/// Appends after the [Snapshot] do not change it,
+/// blocks below [Snapshot::len] read the same as when it was taken.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    length: u32,
+    byte_length: u64,
+    checkpoint: u32,
+    roots: Vec<Node>,
+}
+impl Snapshot {
+    /// Create a new [Snapshot].
+    #[must_use]
+    #[inline]
+    pub fn new(length: u32, byte_length: u64, checkpoint: u32, roots: Vec<Node>) -> Self {
+        Self {
+            length,
+            byte_length,
+            checkpoint,
+            roots,
+        }
+    }
+
+    /// Get the number of entries.
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.length
+    }
+    /// Check if the [Snapshot] is empty.
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+    /// Get the byte length of all entries.
+    #[must_use]
+    #[inline]
+    pub fn byte_len(&self) -> u64 {
+        self.byte_length
+    }
+    /// Get the number of entries covered by the latest checkpoint.
+    #[must_use]
+    #[inline]
+    pub fn checkpoint_len(&self) -> u32 {
+        self.checkpoint
+    }
+    /// Get the root [Node]s.
+    #[must_use]
+    #[inline]
+    pub fn roots(&self) -> &[Node] {
+        &self.roots
+    }
+}
@@ -0,0 +1,369 @@
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{IndexAccess, PublicKey};
+
+/// Schema changes, applied in order and recorded in the `migrations` table.
+const MIGRATIONS: &[&str] = &["CREATE TABLE records (
+        core BLOB NOT NULL,
+        idx INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (core, idx)
+    ) WITHOUT ROWID;"];
+
+/// [SqliteStorage] is a shared handle of a SQLite database storing many `Core`s.
+///
+/// Records are rows `(core, idx, data)` of the `records` table,
+/// `core` is the [PublicKey] of the `Core`.
+/// File databases use WAL mode.
+///
+/// All [IndexAccessSqlite]s of a handle share its connection,
+/// so the writes of all its `Core`s while a [SqliteTransaction] is open
+/// form one transaction, e.g. to sync many `Core::append_batch`es once.
+/// A failed `Core::append_batch` writes no tree, so the transaction
+/// can still be committed. If it is dropped without
+/// [SqliteTransaction::commit] it is rolled back,
+/// the `Core`s written in it have to be reopened.
+///
+/// Statements run on the calling thread and block it,
+/// holding the lock of the connection for their duration,
+/// run the `Core`s on a multi-threaded runtime.
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+impl SqliteStorage {
+    /// Open or create the database at `path`, applying pending migrations.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+    /// Create a new in-memory database.
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+    /// Create a [SqliteStorage] from an open connection, applying pending migrations.
+    pub fn from_connection(mut connection: Connection) -> rusqlite::Result<Self> {
+        connection.query_row("PRAGMA journal_mode = WAL", [], |row| {
+            row.get::<_, String>(0)
+        })?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        migrate(&mut connection)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Get the number of applied migrations.
+    pub fn version(&self) -> rusqlite::Result<usize> {
+        version(&self.connection())
+    }
+
+    /// Get the [IndexAccessSqlite] of the `Core` with `public_key`.
+    #[must_use]
+    #[inline]
+    pub fn storage(&self, public_key: &PublicKey) -> IndexAccessSqlite {
+        IndexAccessSqlite {
+            connection: Arc::clone(&self.connection),
+            core: public_key.as_slice().to_vec(),
+        }
+    }
+
+    /// Get the [PublicKey]s of all stored `Core`s, ordered by key.
+    pub fn public_keys(&self) -> rusqlite::Result<Vec<PublicKey>> {
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT DISTINCT core FROM records ORDER BY core")?;
+        let keys = statement.query_map([], |row| row.get::<_, Vec<u8>>(0))?;
+        let mut public_keys = vec![];
+        for key in keys {
+            if let Ok(public_key) = PublicKey::from_slice(&key?) {
+                public_keys.push(public_key);
+            }
+        }
+        Ok(public_keys)
+    }
+
+    /// Remove the `Core` with `public_key`, return `false` if it was not stored.
+    pub fn remove(&self, public_key: &PublicKey) -> rusqlite::Result<bool> {
+        let removed = self.connection().execute(
+            "DELETE FROM records WHERE core = ?1",
+            params![public_key.as_slice()],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Start a [SqliteTransaction], the writes of all `Core`s of the handle
+    /// are not visible to other connections or persisted
+    /// until [SqliteTransaction::commit].
+    pub fn transaction(&self) -> rusqlite::Result<SqliteTransaction> {
+        self.connection().execute_batch("BEGIN IMMEDIATE")?;
+        Ok(SqliteTransaction {
+            connection: Arc::clone(&self.connection),
+            done: false,
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        lock(&self.connection)
+    }
+}
+
+/// [SqliteTransaction] is an open transaction of a [SqliteStorage],
+/// rolled back on drop unless it is committed.
+#[derive(Debug)]
+#[must_use = "the transaction is rolled back when it is dropped"]
+pub struct SqliteTransaction {
+    connection: Arc<Mutex<Connection>>,
+    done: bool,
+}
+impl SqliteTransaction {
+    /// Commit the writes made since [SqliteStorage::transaction].
+    pub fn commit(mut self) -> rusqlite::Result<()> {
+        self.done = true;
+        let connection = lock(&self.connection);
+        if let Err(err) = connection.execute_batch("COMMIT") {
+            if !connection.is_autocommit() {
+                connection.execute_batch("ROLLBACK")?;
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+    /// Roll back the writes made since [SqliteStorage::transaction].
+    pub fn rollback(mut self) -> rusqlite::Result<()> {
+        self.done = true;
+        lock(&self.connection).execute_batch("ROLLBACK")
+    }
+}
+impl Drop for SqliteTransaction {
+    fn drop(&mut self) {
+        if !self.done {
+            let connection = lock(&self.connection);
+            if !connection.is_autocommit() {
+                // nothing to report from a drop, the connection is left outside a transaction
+                let _ = connection.execute_batch("ROLLBACK");
+            }
+        }
+    }
+}
+
+/// [IndexAccessSqlite] stores the records of one `Core` in a [SqliteStorage].
+#[derive(Debug)]
+pub struct IndexAccessSqlite {
+    connection: Arc<Mutex<Connection>>,
+    core: Vec<u8>,
+}
+#[async_trait]
+impl IndexAccess for IndexAccessSqlite {
+    type Error = rusqlite::Error;
+
+    async fn write(&mut self, index: u32, data: &[u8]) -> rusqlite::Result<()> {
+        lock(&self.connection).execute(
+            "INSERT OR REPLACE INTO records (core, idx, data) VALUES (?1, ?2, ?3)",
+            params![self.core, index, data],
+        )?;
+        Ok(())
+    }
+
+    async fn read(&mut self, index: u32) -> rusqlite::Result<Option<Vec<u8>>> {
+        lock(&self.connection)
+            .query_row(
+                "SELECT data FROM records WHERE core = ?1 AND idx = ?2",
+                params![self.core, index],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+}
+
+fn lock(connection: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    // statements are atomic, a panic can not leave the connection inconsistent
+    connection
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn version(connection: &Connection) -> rusqlite::Result<usize> {
+    let version: Option<usize> =
+        connection.query_row("SELECT MAX(version) FROM migrations", [], |row| row.get(0))?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Apply the [MIGRATIONS] newer than the recorded version.
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS migrations (
+            version INTEGER PRIMARY KEY,
+            applied INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        );",
+    )?;
+    let current = version(connection)?;
+    if current > MIGRATIONS.len() {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+            Some(format!(
+                "Database schema version {} is not supported.",
+                current
+            )),
+        ));
+    }
+    let transaction = connection.transaction()?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        transaction.execute_batch(migration)?;
+        transaction.execute(
+            "INSERT INTO migrations (version) VALUES (?1)",
+            params![version + 1],
+        )?;
+    }
+    transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Core, KeyPair};
+
+    #[tokio::test]
+    async fn write_read() -> rusqlite::Result<()> {
+        let storage = SqliteStorage::open_in_memory()?;
+        let a = KeyPair::generate().pk;
+        let b = KeyPair::generate().pk;
+
+        let mut a_storage = storage.storage(&a);
+        a_storage.write(0, b"a").await?;
+        a_storage.write(0, b"aa").await?;
+        a_storage.write(u32::MAX, b"header").await?;
+        let mut b_storage = storage.storage(&b);
+        assert_eq!(b_storage.read(0).await?, None);
+        b_storage.write(0, b"b").await?;
+
+        assert_eq!(a_storage.read(0).await?.unwrap(), b"aa");
+        assert_eq!(a_storage.read(u32::MAX).await?.unwrap(), b"header");
+        let mut keys = vec![a, b];
+        keys.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+        assert_eq!(storage.public_keys()?, keys);
+
+        assert!(storage.remove(&a)?);
+        assert!(!storage.remove(&a)?);
+        assert_eq!(storage.public_keys()?, vec![b]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrations() -> rusqlite::Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cores.db");
+        let storage = SqliteStorage::open(&path)?;
+        assert_eq!(storage.version()?, MIGRATIONS.len());
+        let mode: String = storage
+            .connection()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+        assert_eq!(mode, "wal");
+        storage
+            .storage(&KeyPair::generate().pk)
+            .write(0, b"a")
+            .await?;
+        drop(storage);
+
+        let storage = SqliteStorage::open(&path)?;
+        assert_eq!(storage.version()?, MIGRATIONS.len());
+        assert_eq!(storage.public_keys()?.len(), 1);
+
+        storage
+            .connection()
+            .execute("INSERT INTO migrations (version) VALUES (?1)", params![100])?;
+        drop(storage);
+        assert!(SqliteStorage::open(&path).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batch() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cores.db");
+        let storage = SqliteStorage::open(&path)?;
+        let keypair = KeyPair::generate();
+        let mut core = Core::new(
+            storage.storage(&keypair.pk),
+            keypair.pk,
+            Some(keypair.sk.clone()),
+        )
+        .await?;
+        let other_keypair = KeyPair::generate();
+        let mut other_core = Core::new(
+            storage.storage(&other_keypair.pk),
+            other_keypair.pk,
+            Some(other_keypair.sk.clone()),
+        )
+        .await?;
+
+        let transaction = storage.transaction()?;
+        core.append_batch(&[b"a", b"b", b"c"]).await?;
+        other_core.append(b"x", None).await?;
+        // blocks are not visible to other connections before the commit
+        let mut other = SqliteStorage::open(&path)?.storage(&keypair.pk);
+        assert!(other.read(1).await?.is_none());
+        // a failed batch is dropped, not the transaction
+        let large = vec![0u8; crate::MAX_BLOCK_SIZE + 1];
+        assert!(core.append_batch(&[b"d", &large]).await.is_err());
+        transaction.commit()?;
+        assert!(other.read(1).await?.is_some());
+        drop(core);
+        drop(other_core);
+
+        let other = SqliteStorage::open(&path)?;
+        let core = Core::new(other.storage(&keypair.pk), keypair.pk, None).await?;
+        assert_eq!(core.len(), 3);
+        assert_eq!(core.get(2).await?.unwrap().0, b"c");
+        let other_core =
+            Core::new(other.storage(&other_keypair.pk), other_keypair.pk, None).await?;
+        assert_eq!(other_core.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rollback() -> anyhow::Result<()> {
+        let storage = SqliteStorage::open_in_memory()?;
+        let keypair = KeyPair::generate();
+        let mut core = Core::new(
+            storage.storage(&keypair.pk),
+            keypair.pk,
+            Some(keypair.sk.clone()),
+        )
+        .await?;
+        core.append(b"a", None).await?;
+
+        let transaction = storage.transaction()?;
+        core.append_batch(&[b"b", b"c"]).await?;
+        drop(transaction);
+        let mut core = Core::new(
+            storage.storage(&keypair.pk),
+            keypair.pk,
+            Some(keypair.sk.clone()),
+        )
+        .await?;
+        assert_eq!(core.len(), 1);
+
+        let transaction = storage.transaction()?;
+        core.append(b"d", None).await?;
+        transaction.rollback()?;
+        let mut core = Core::new(
+            storage.storage(&keypair.pk),
+            keypair.pk,
+            Some(keypair.sk.clone()),
+        )
+        .await?;
+        assert_eq!(core.len(), 1);
+
+        // the connection is usable after a rollback
+        let transaction = storage.transaction()?;
+        core.append(b"e", None).await?;
+        transaction.commit()?;
+        let core = Core::new(storage.storage(&keypair.pk), keypair.pk, None).await?;
+        assert_eq!(core.len(), 2);
+        assert_eq!(core.get(1).await?.unwrap().0, b"e");
+        Ok(())
+    }
+}
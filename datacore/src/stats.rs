@@ -0,0 +1,101 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// [Stats] summarize the size of one or more `Core`s.
+///
+/// Metadata includes the offsets and signatures of the blocks,
+/// the leaf hashes kept for cleared blocks, the tree roots and the header.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Stats {
+    blocks: u64,
+    bytes: u64,
+    metadata_bytes: u64,
+    cleared_bytes: u64,
+}
+impl Stats {
+    /// Create new [Stats].
+    #[must_use]
+    #[inline]
+    pub fn new(blocks: u64, bytes: u64, metadata_bytes: u64, cleared_bytes: u64) -> Self {
+        Self {
+            blocks,
+            bytes,
+            metadata_bytes,
+            cleared_bytes,
+        }
+    }
+
+    /// Get the number of blocks.
+    #[must_use]
+    #[inline]
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+    /// Get the total byte length of all blocks, including cleared ones.
+    #[must_use]
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+    /// Get the number of bytes stored besides the block data.
+    #[must_use]
+    #[inline]
+    pub fn metadata_bytes(&self) -> u64 {
+        self.metadata_bytes
+    }
+    /// Get the byte length of cleared blocks.
+    #[must_use]
+    #[inline]
+    pub fn cleared_bytes(&self) -> u64 {
+        self.cleared_bytes
+    }
+    /// Get the average byte length of a block, `0` without blocks.
+    #[must_use]
+    #[inline]
+    pub fn average_block_size(&self) -> u64 {
+        self.bytes.checked_div(self.blocks).unwrap_or(0)
+    }
+    /// Get the number of bytes written to the storage backend,
+    /// the block data still stored and the metadata.
+    #[must_use]
+    #[inline]
+    pub fn storage_bytes(&self) -> u64 {
+        self.bytes - self.cleared_bytes + self.metadata_bytes
+    }
+}
+impl Add for Stats {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Self) {
+        self.blocks += other.blocks;
+        self.bytes += other.bytes;
+        self.metadata_bytes += other.metadata_bytes;
+        self.cleared_bytes += other.cleared_bytes;
+    }
+}
+impl Sum for Stats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum() {
+        let stats = [Stats::new(2, 10, 100, 4), Stats::new(3, 20, 150, 0)];
+        let total: Stats = stats.iter().copied().sum();
+        assert_eq!(total, Stats::new(5, 30, 250, 4));
+        assert_eq!(total.average_block_size(), 6);
+        assert_eq!(total.storage_bytes(), 276);
+        assert_eq!(Stats::default().average_block_size(), 0);
+    }
+}
@@ -1,21 +1,199 @@
-use anyhow::{anyhow, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::fmt;
 use std::mem::size_of;
+use tokio::sync::Mutex;
 
-use crate::block::BLOCK_LENGTH;
-use crate::merkle::NODE_SIZE;
-use crate::{Block, IndexAccess, Merkle, Node};
+use crate::block::{BLOCK_LENGTH, BLOCK_WITHOUT_TREE_LENGTH};
+use crate::hash::HASH_SIZE;
+use crate::header::Header;
+use crate::merkle::{parent_count, NODE_SIZE};
+use crate::{Block, Hash, IndexAccess, Merkle, Node};
 
 const STATE_INDEX: u32 = 0;
+// Blocks occupy indexes `1..=MAX_CORE_LENGTH`, the last index is free.
+const HEADER_INDEX: u32 = u32::MAX;
+// Usage of block records, stored in the state record.
+const USAGE_LENGTH: usize = 2 * size_of::<u64>();
+// The state record starts with a marker no root `Node` index can take,
+// followed by its version and the usage, then the roots.
+const STATE_MARKER: [u8; 8] = u64::MAX.to_le_bytes();
+const STATE_VERSION: u8 = 1;
+const STATE_PREFIX_LENGTH: usize = STATE_MARKER.len() + 1 + USAGE_LENGTH;
+
+/// Content of a stored `Block`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Content {
+    /// Block data.
+    Data(Vec<u8>),
+    /// Cleared block, only the leaf [Hash] of its data is kept.
+    Cleared(Hash),
+}
+impl Content {
+    /// Get the leaf [Hash] of the block data.
+    #[inline]
+    pub fn leaf(&self) -> Result<Hash> {
+        match self {
+            Self::Data(data) => Hash::from_leaf(data),
+            Self::Cleared(hash) => Ok(hash.clone()),
+        }
+    }
+}
+
+/// [ClearedError] is returned when reading a cleared block.
+///
+/// Use [anyhow::Error::downcast_ref] to tell it apart from other errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClearedError {
+    index: u32,
+}
+impl ClearedError {
+    /// Create a new [ClearedError].
+    #[must_use]
+    #[inline]
+    pub fn new(index: u32) -> Self {
+        Self { index }
+    }
+    /// Get the index of the cleared block.
+    #[must_use]
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+impl fmt::Display for ClearedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Block {} is cleared.", self.index)
+    }
+}
+impl std::error::Error for ClearedError {}
+
+/// [InvalidStateError] is returned when the state record can not be parsed,
+/// e.g. after a torn write, the tree can be recovered from the block records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidStateError;
+impl fmt::Display for InvalidStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid state record.")
+    }
+}
+impl std::error::Error for InvalidStateError {}
 
 /// Save data to a desired storage backend.
+///
+/// With checkpoints enabled blocks may lack a tree signature,
+/// so every block record ends with a flag marking its presence.
+///
+/// Cleared blocks store the leaf [Hash] of their data instead of the data.
+/// They are told apart by a content length different from `Block::length`,
+/// the [Hash] is padded by a byte for blocks of [HASH_SIZE] bytes.
+///
+/// With tree nodes enabled, the record of a block also stores
+/// the parent [Node]s completed by the block between its content and `Block`,
+/// so any tree [Node] is read from a single record.
+///
+/// Reads take `&self`, the storage interface is locked only for
+/// the duration of a single read, writes need exclusive access.
+/// [IndexAccess::read] takes `&mut self`, so concurrent reads wait
+/// for each other on the lock, a slow storage read delays all others.
+///
+/// The bytes of block records besides their data and the bytes of
+/// cleared data are counted on every write and saved after the roots.
+/// Records replaced or dropped by the `Core` are passed to [Store::forget].
 pub struct Store<T> {
-    store: T,
+    store: Mutex<T>,
+    checkpoints: bool,
+    tree_nodes: bool,
+    metadata: u64,
+    cleared: u64,
+    state_length: u64,
+    header_length: u64,
 }
 impl<T> Store<T> {
     /// Create a new [Store] from storage interface.
     #[inline]
     pub fn new(store: T) -> Self {
-        Self { store }
+        Self {
+            store: Mutex::new(store),
+            checkpoints: false,
+            tree_nodes: false,
+            metadata: 0,
+            cleared: 0,
+            state_length: 0,
+            header_length: 0,
+        }
+    }
+
+    /// Set the block layout for checkpoints.
+    #[inline]
+    pub fn set_checkpoints(&mut self, checkpoints: bool) {
+        self.checkpoints = checkpoints;
+    }
+
+    /// Set the block layout for tree nodes.
+    #[inline]
+    pub fn set_tree_nodes(&mut self, tree_nodes: bool) {
+        self.tree_nodes = tree_nodes;
+    }
+    /// Check if block records store the tree nodes they complete.
+    #[inline]
+    pub fn tree_nodes(&self) -> bool {
+        self.tree_nodes
+    }
+
+    /// Get the number of stored bytes besides the block data.
+    #[inline]
+    pub fn metadata_len(&self) -> u64 {
+        self.metadata + self.state_length + self.header_length
+    }
+    /// Get the byte length of cleared block data.
+    #[inline]
+    pub fn cleared_len(&self) -> u64 {
+        self.cleared
+    }
+
+    /// Stop counting a block record, before it is replaced or dropped.
+    ///
+    /// The usage saturates at zero, e.g. if the saved usage was off.
+    #[inline]
+    pub fn forget(&mut self, index: u32, content: &Content, block: &Block) {
+        let cleared = matches!(content, Content::Cleared(_));
+        let metadata = self.record_metadata(index, cleared, block);
+        self.metadata = self.metadata.saturating_sub(metadata);
+        if cleared {
+            self.cleared = self.cleared.saturating_sub(u64::from(block.length()));
+        }
+    }
+
+    fn count(&mut self, index: u32, cleared: bool, block: &Block) {
+        self.metadata += self.record_metadata(index, cleared, block);
+        if cleared {
+            self.cleared += u64::from(block.length());
+        }
+    }
+
+    fn record_metadata(&self, index: u32, cleared: bool, block: &Block) -> u64 {
+        let mut length = match block.signature().tree() {
+            Some(_) => BLOCK_LENGTH,
+            None => BLOCK_WITHOUT_TREE_LENGTH,
+        };
+        if self.checkpoints {
+            length += 1;
+        }
+        if self.tree_nodes {
+            length += parent_count(index) * NODE_SIZE;
+        }
+        if cleared {
+            length += cleared_length(block);
+        }
+        length as u64
+    }
+}
+
+/// Length of the padded leaf [Hash] stored for a cleared `Block`.
+fn cleared_length(block: &Block) -> usize {
+    match block.length() as usize {
+        HASH_SIZE => HASH_SIZE + 1,
+        _ => HASH_SIZE,
     }
 }
 impl<T> Store<T>
@@ -23,31 +201,164 @@ where
     T: IndexAccess + Send,
     <T as IndexAccess>::Error: Into<anyhow::Error>,
 {
-    /// Write data for a `Block`.
+    /// Write data for a `Block` with the parent `nodes` it completes.
     #[inline]
-    pub async fn write(&mut self, index: u32, data: &[u8], block: &Block) -> Result<()> {
-        let mut bytes = Vec::with_capacity(data.len() + BLOCK_LENGTH);
+    pub async fn write(
+        &mut self,
+        index: u32,
+        data: &[u8],
+        nodes: &[Node],
+        block: &Block,
+    ) -> Result<()> {
+        self.write_record(index, data, nodes, block).await?;
+        self.count(index, false, block);
+        Ok(())
+    }
+
+    /// Write a cleared `Block`, keeping only the leaf [Hash] of its data.
+    #[inline]
+    pub async fn write_cleared(
+        &mut self,
+        index: u32,
+        leaf: &Hash,
+        nodes: &[Node],
+        block: &Block,
+    ) -> Result<()> {
+        let mut content = leaf.to_vec();
+        content.resize(cleared_length(block), 0);
+        self.write_record(index, &content, nodes, block).await?;
+        self.count(index, true, block);
+        Ok(())
+    }
+
+    /// Write [Content] for a `Block`.
+    #[inline]
+    pub async fn write_content(
+        &mut self,
+        index: u32,
+        content: &Content,
+        nodes: &[Node],
+        block: &Block,
+    ) -> Result<()> {
+        match content {
+            Content::Data(data) => self.write(index, data, nodes, block).await,
+            Content::Cleared(leaf) => self.write_cleared(index, leaf, nodes, block).await,
+        }
+    }
+
+    async fn write_record(
+        &mut self,
+        index: u32,
+        data: &[u8],
+        nodes: &[Node],
+        block: &Block,
+    ) -> Result<()> {
+        let has_tree = block.signature().tree().is_some();
+        ensure!(
+            has_tree || self.checkpoints,
+            "Missing tree signature, checkpoints are not enabled."
+        );
+        let mut bytes = Vec::with_capacity(data.len() + nodes.len() * NODE_SIZE + BLOCK_LENGTH + 1);
         bytes.extend_from_slice(data);
+        if self.tree_nodes {
+            ensure!(
+                nodes.len() == parent_count(index),
+                "Block {} completes {} tree nodes, not {}.",
+                index,
+                parent_count(index),
+                nodes.len()
+            );
+            for node in nodes {
+                bytes.extend_from_slice(&node.to_bytes()?);
+            }
+        }
         bytes.extend_from_slice(&block.to_bytes()?);
+        if self.checkpoints {
+            bytes.push(u8::from(has_tree));
+        }
         self.store
+            .get_mut()
             .write(index + 1, &bytes)
             .await
             .map_err(|e| anyhow!(e))
     }
 
-    /// Read data for a `Block`.
+    /// Read [Content] for a `Block`.
     #[inline]
-    pub async fn read(&mut self, index: u32) -> Result<Option<(Vec<u8>, Block)>> {
-        Ok(
-            match self.store.read(index + 1).await.map_err(|e| anyhow!(e))? {
-                None => None,
-                Some(mut raw) => {
-                    ensure!(raw.len() > BLOCK_LENGTH);
-                    let block = raw.split_off(raw.len() - BLOCK_LENGTH);
-                    Some((raw, Block::from_bytes(&block)?))
-                }
+    pub async fn read(&self, index: u32) -> Result<Option<(Content, Block)>> {
+        let record = self.read_record(index).await?;
+        Ok(record.map(|(content, block, _)| (content, block)))
+    }
+
+    /// Read [Content] for a `Block` and the parent [Node]s it completes,
+    /// no [Node]s are returned without tree nodes enabled.
+    pub async fn read_record(&self, index: u32) -> Result<Option<(Content, Block, Vec<Node>)>> {
+        let raw = self.store.lock().await.read(index + 1).await;
+        match raw.map_err(|e| anyhow!(e))? {
+            None => Ok(None),
+            Some(raw) => Ok(Some(self.parse_record(index, raw)?)),
+        }
+    }
+
+    /// Read [Content] for a `Block`, a record that can not be parsed,
+    /// e.g. after a torn write, is returned as missing.
+    pub async fn read_intact(&self, index: u32) -> Result<Option<(Content, Block)>> {
+        let raw = self.store.lock().await.read(index + 1).await;
+        Ok(match raw.map_err(|e| anyhow!(e))? {
+            None => None,
+            Some(raw) => self
+                .parse_record(index, raw)
+                .ok()
+                .map(|(content, block, _)| (content, block)),
+        })
+    }
+
+    fn parse_record(&self, index: u32, mut raw: Vec<u8>) -> Result<(Content, Block, Vec<Node>)> {
+        let block_length = self.block_length(&mut raw)?;
+        let block = Block::from_bytes(&raw.split_off(raw.len() - block_length))?;
+        let mut nodes = vec![];
+        if self.tree_nodes {
+            let nodes_length = parent_count(index) * NODE_SIZE;
+            ensure!(raw.len() > nodes_length, "Invalid block record.");
+            let bytes = raw.split_off(raw.len() - nodes_length);
+            for node in bytes.chunks(NODE_SIZE) {
+                nodes.push(Node::from_bytes(node)?);
+            }
+        }
+        let content = match raw.len() == block.length() as usize {
+            true => Content::Data(raw),
+            false => {
+                ensure!(raw.len() >= HASH_SIZE, "Invalid cleared block.");
+                Content::Cleared(Hash::from_bytes(&raw[..HASH_SIZE])?)
+            }
+        };
+        Ok((content, block, nodes))
+    }
+
+    /// Read only the `Block` of a record, without parsing its [Content].
+    pub async fn read_block(&self, index: u32) -> Result<Option<Block>> {
+        let raw = self.store.lock().await.read(index + 1).await;
+        Ok(match raw.map_err(|e| anyhow!(e))? {
+            None => None,
+            Some(mut raw) => {
+                let block_length = self.block_length(&mut raw)?;
+                Some(Block::from_bytes(&raw[raw.len() - block_length..])?)
+            }
+        })
+    }
+
+    /// Pop the checkpoints flag of a record, returns the length of its `Block`.
+    fn block_length(&self, raw: &mut Vec<u8>) -> Result<usize> {
+        let block_length = match self.checkpoints {
+            false => BLOCK_LENGTH,
+            true => match raw.pop() {
+                Some(1) => BLOCK_LENGTH,
+                Some(0) => BLOCK_WITHOUT_TREE_LENGTH,
+                _ => bail!("Invalid block record."),
             },
-        )
+        };
+        ensure!(raw.len() > block_length, "Invalid block record.");
+        Ok(block_length)
     }
 
     /// Write `Merkle` roots.
@@ -56,44 +367,162 @@ where
         let roots = merkle.roots();
         let length = roots.len();
 
-        let mut data = Vec::with_capacity(length * NODE_SIZE);
+        let mut data = Vec::with_capacity(STATE_PREFIX_LENGTH + length * NODE_SIZE);
+        data.extend_from_slice(&STATE_MARKER);
+        data.push(STATE_VERSION);
+        data.extend_from_slice(&self.metadata.to_le_bytes());
+        data.extend_from_slice(&self.cleared.to_le_bytes());
         for node in roots {
             data.extend_from_slice(&node.to_bytes()?);
         }
 
         self.store
+            .get_mut()
             .write(STATE_INDEX, &data)
             .await
-            .map_err(|e| anyhow!(e))
+            .map_err(|e| anyhow!(e))?;
+        self.state_length = data.len() as u64;
+        Ok(())
+    }
+
+    /// Write `Core` [Header].
+    #[inline]
+    pub async fn write_header(&mut self, header: &Header) -> Result<()> {
+        let data = header.to_bytes()?;
+        self.store
+            .get_mut()
+            .write(HEADER_INDEX, &data)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        self.header_length = data.len() as u64;
+        Ok(())
+    }
+
+    /// Read `Core` [Header].
+    #[inline]
+    pub async fn read_header(&mut self) -> Result<Option<Header>> {
+        let data = self.store.get_mut().read(HEADER_INDEX).await;
+        match data.map_err(|e| anyhow!(e))? {
+            None => Ok(None),
+            Some(data) => {
+                self.header_length = data.len() as u64;
+                Ok(Some(Header::from_bytes(&data)?))
+            }
+        }
     }
 
     /// Read roots and reconstruct `Merkle`.
+    ///
+    /// Returns `false` if the usage of the block records is unknown,
+    /// as for `Core`s written before it was saved.
+    /// A state record that can not be parsed returns an [InvalidStateError].
     #[inline]
-    pub async fn read_merkle(&mut self) -> Result<Merkle> {
+    pub async fn read_merkle(&mut self) -> Result<(Merkle, bool)> {
         // try reading length
-        let data = self.store.read(STATE_INDEX).await.map_err(|e| anyhow!(e))?;
+        let data = self.store.get_mut().read(STATE_INDEX).await;
+        let data = data.map_err(|e| anyhow!(e))?;
 
         // init [Merkle] from roots
         match data {
             // no data => no roots
-            None => Ok(Merkle::default()),
+            None => Ok((Merkle::default(), true)),
             // read roots
             Some(data) => {
-                ensure!(data.len() % NODE_SIZE == 0);
-                let length = data.len() / NODE_SIZE;
-
-                let mut roots = Vec::with_capacity(length as usize * size_of::<Node>());
-                let mut start = 0;
-                while start < data.len() {
-                    let end = start + NODE_SIZE;
-                    let root = Node::from_bytes(&data[start..end])?;
-                    roots.push(root);
-                    start = end;
+                self.state_length = data.len() as u64;
+                let version = data.strip_prefix(&STATE_MARKER[..]).and_then(<[u8]>::first);
+                if let Some(&version) = version {
+                    ensure!(
+                        version == STATE_VERSION,
+                        "Unsupported state record version {}.",
+                        version
+                    );
                 }
+                self.parse_state(&data).context(InvalidStateError)
+            }
+        }
+    }
 
-                Ok(Merkle::from_roots(roots))
+    fn parse_state(&mut self, data: &[u8]) -> Result<(Merkle, bool)> {
+        let (roots, usage) = match data.strip_prefix(&STATE_MARKER[..]) {
+            Some(state) => {
+                ensure!(state.len() > USAGE_LENGTH, "Invalid state record.");
+                self.read_usage(&state[1..=USAGE_LENGTH])?;
+                (&state[1 + USAGE_LENGTH..], true)
             }
+            // state records written before they were versioned
+            None => (data, false),
+        };
+        ensure!(roots.len() % NODE_SIZE == 0, "Invalid state record.");
+        let roots = roots
+            .chunks(NODE_SIZE)
+            .map(Node::from_bytes)
+            .collect::<Result<Vec<_>>>()?;
+        Ok((Merkle::from_roots(roots), usage))
+    }
+
+    fn read_usage(&mut self, usage: &[u8]) -> Result<()> {
+        self.metadata = u64::from_le_bytes(usage[..8].try_into()?);
+        self.cleared = u64::from_le_bytes(usage[8..].try_into()?);
+        Ok(())
+    }
+
+    /// Rewrite the records of the first `length` blocks, the `merkle` roots
+    /// and the `header` into the empty `backend` and swap it in.
+    ///
+    /// Records past `length` are left behind and cleared blocks stay cleared.
+    /// The tree is rebuilt from the rewritten blocks and must match `merkle`,
+    /// otherwise the [Store] is left unchanged.
+    /// A `backend` with a state or header record is refused.
+    /// Returns the replaced storage interface.
+    pub async fn compact(
+        &mut self,
+        backend: T,
+        length: u32,
+        merkle: &Merkle,
+        header: &Header,
+    ) -> Result<T> {
+        let mut fresh = Store::new(backend);
+        for index in [STATE_INDEX, HEADER_INDEX] {
+            let record = fresh.store.get_mut().read(index).await;
+            ensure!(
+                record.map_err(|e| anyhow!(e))?.is_none(),
+                "Storage backend to compact into is not empty."
+            );
+        }
+        fresh.set_checkpoints(self.checkpoints);
+        fresh.set_tree_nodes(self.tree_nodes);
+        let mut rebuilt = Merkle::default();
+        for index in 0..length {
+            let (content, block, nodes) = self
+                .read_record(index)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            rebuilt.next(content.leaf()?, block.length());
+            fresh.write_content(index, &content, &nodes, &block).await?;
+        }
+        ensure!(
+            rebuilt.roots() == merkle.roots(),
+            "Compacted blocks do not match the tree."
+        );
+        fresh.write_header(header).await?;
+        fresh.write_merkle(merkle).await?;
+
+        std::mem::swap(self, &mut fresh);
+        Ok(fresh.store.into_inner())
+    }
+
+    /// Count the block records of the first `length` blocks.
+    pub async fn scan(&mut self, length: u32) -> Result<()> {
+        self.metadata = 0;
+        self.cleared = 0;
+        for index in 0..length {
+            let (content, block) = self
+                .read(index)
+                .await?
+                .ok_or_else(|| anyhow!("Missing expected block."))?;
+            self.count(index, matches!(content, Content::Cleared(_)), &block);
         }
+        Ok(())
     }
 }
 
@@ -101,7 +530,11 @@ where
 mod tests {
     use super::*;
     use crate::block::Signature;
+    use crate::fault::{Fault, IndexAccessFault, InjectedError, Injection, Operation, Trigger};
     use crate::hash::Hash;
+    use crate::keys::SCHEME_SIGNATURE_LENGTH;
+    use crate::merkle::parent_nodes;
+    use crate::{NodeTrait, SignatureScheme};
     use index_access_memory::IndexAccessMemory;
 
     #[tokio::test]
@@ -115,17 +548,228 @@ mod tests {
         let mut store = Store::new(IndexAccessMemory::default());
         let data = b"hello world";
         let signature = Signature::new(
-            ed25519_compact::Signature::from_slice(&[2u8; ed25519_compact::Signature::BYTES])?,
-            ed25519_compact::Signature::from_slice(&[7u8; ed25519_compact::Signature::BYTES])?,
+            [2u8; SCHEME_SIGNATURE_LENGTH],
+            [7u8; SCHEME_SIGNATURE_LENGTH],
         );
-        let block = Block::new(1, 8, signature);
-        store.write(0, data, &block).await?;
+        let block = Block::new(1, data.len() as u32, signature);
+        store.write(0, data, &[], &block).await?;
         let (data2, block2) = store.read(0).await?.unwrap();
-        assert_eq!(data2, data);
+        assert_eq!(data2, Content::Data(data.to_vec()));
         assert_eq!(block2, block);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn data_cleared() -> Result<()> {
+        let mut store = Store::new(IndexAccessMemory::default());
+        for length in [1, HASH_SIZE - 1, HASH_SIZE, HASH_SIZE + 1, 1024] {
+            let data = vec![7u8; length];
+            let leaf = Hash::from_leaf(&data)?;
+            let signature = Signature::new(
+                [2u8; SCHEME_SIGNATURE_LENGTH],
+                [7u8; SCHEME_SIGNATURE_LENGTH],
+            );
+            let block = Block::new(0, length as u32, signature);
+            store.write(0, &data, &[], &block).await?;
+            assert_eq!(store.read(0).await?.unwrap().0.leaf()?, leaf);
+            store.write_cleared(0, &leaf, &[], &block).await?;
+            let (content, block2) = store.read(0).await?.unwrap();
+            assert_eq!(content, Content::Cleared(leaf));
+            assert_eq!(block2, block);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn usage() -> Result<()> {
+        let mut store = Store::new(IndexAccessMemory::default());
+        let data = b"hello world";
+        let mut merkle = Merkle::default();
+        for index in 0..2 {
+            let signature = Signature::new(
+                [2u8; SCHEME_SIGNATURE_LENGTH],
+                [7u8; SCHEME_SIGNATURE_LENGTH],
+            );
+            let block = Block::new(index * 11, 11, signature);
+            store.write(index as u32, data, &[], &block).await?;
+            merkle.next(Hash::from_leaf(data)?, 11);
+        }
+        let (content, block) = store.read(1).await?.unwrap();
+        store.forget(1, &content, &block);
+        store
+            .write_cleared(1, &content.leaf()?, &[], &block)
+            .await?;
+        store.write_merkle(&merkle).await?;
+        let metadata = (2 * BLOCK_LENGTH + HASH_SIZE + NODE_SIZE + STATE_PREFIX_LENGTH) as u64;
+        assert_eq!(store.metadata_len(), metadata);
+        assert_eq!(store.cleared_len(), 11);
+
+        // state written without usage
+        let roots = merkle.roots()[0].to_bytes()?;
+        store.store.get_mut().write(STATE_INDEX, &roots).await?;
+        let (_, usage) = store.read_merkle().await?;
+        assert!(!usage);
+        store.scan(2).await?;
+        assert_eq!(store.metadata_len(), metadata - STATE_PREFIX_LENGTH as u64);
+        assert_eq!(store.cleared_len(), 11);
+
+        // the usage does not underflow
+        let mut state = STATE_MARKER.to_vec();
+        state.push(STATE_VERSION);
+        state.extend_from_slice(&7u64.to_le_bytes());
+        state.extend_from_slice(&11u64.to_le_bytes());
+        state.extend_from_slice(&roots);
+        store.store.get_mut().write(STATE_INDEX, &state).await?;
+        let (merkle2, usage) = store.read_merkle().await?;
+        assert!(usage);
+        assert_eq!(merkle2.roots(), merkle.roots());
+        store.forget(1, &content, &block);
+        assert_eq!(store.metadata_len(), state.len() as u64);
+        store.scan(2).await?;
+
+        store.write_merkle(&merkle).await?;
+        let (_, usage) = store.read_merkle().await?;
+        assert!(usage);
+        assert_eq!(store.metadata_len(), metadata);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compact() -> Result<()> {
+        let mut store = Store::new(IndexAccessMemory::default());
+        let header = Header::new(SignatureScheme::Ed25519);
+        store.write_header(&header).await?;
+        let mut merkle = Merkle::default();
+        for index in 0..3u32 {
+            let data = [index as u8; 8];
+            let signature = Signature::new(
+                [2u8; SCHEME_SIGNATURE_LENGTH],
+                [7u8; SCHEME_SIGNATURE_LENGTH],
+            );
+            let block = Block::new(u64::from(index) * 8, 8, signature);
+            store.write(index, &data, &[], &block).await?;
+            if index < 2 {
+                merkle.next(Hash::from_leaf(&data)?, 8);
+            }
+        }
+        let (content, block) = store.read(0).await?.unwrap();
+        store.forget(0, &content, &block);
+        store
+            .write_cleared(0, &content.leaf()?, &[], &block)
+            .await?;
+        store.write_merkle(&merkle).await?;
+        let metadata = store.metadata_len();
+
+        // a tree of other blocks is refused
+        let mut other = merkle.clone();
+        other.next(Hash::from_leaf(b"x")?, 1);
+        let backend = IndexAccessMemory::default();
+        assert!(store.compact(backend, 3, &other, &header).await.is_err());
+        assert!(store.read(2).await?.is_some());
+
+        let backend = IndexAccessMemory::default();
+        let mut old = store.compact(backend, 2, &merkle, &header).await?;
+        assert!(old.read(3).await?.is_some());
+        assert!(store.read(2).await?.is_none());
+        let cleared = Content::Cleared(content.leaf()?);
+        assert_eq!(store.read(0).await?.unwrap(), (cleared, block));
+        assert_eq!(store.read(1).await?.unwrap().0, Content::Data(vec![1; 8]));
+        assert_eq!(store.read_header().await?, Some(header));
+        assert_eq!(store.read_merkle().await?.0.roots(), merkle.roots());
+        assert_eq!(store.metadata_len(), metadata - BLOCK_LENGTH as u64);
+        assert_eq!(store.cleared_len(), 8);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn data_checkpoints() -> Result<()> {
+        let mut store = Store::new(IndexAccessMemory::default());
+        let data = b"hello world";
+        let block = Block::new(1, 11, Signature::without_tree([2u8; SCHEME_SIGNATURE_LENGTH]));
+        assert!(store.write(0, data, &[], &block).await.is_err());
+
+        store.set_checkpoints(true);
+        store.write(0, data, &[], &block).await?;
+        let signature = Signature::new(
+            [2u8; SCHEME_SIGNATURE_LENGTH],
+            [7u8; SCHEME_SIGNATURE_LENGTH],
+        );
+        let block2 = Block::new(12, 11, signature);
+        store.write(1, data, &[], &block2).await?;
+        assert_eq!(store.read(0).await?.unwrap(), (Content::Data(data.to_vec()), block));
+        assert_eq!(store.read(1).await?.unwrap(), (Content::Data(data.to_vec()), block2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn data_tree_nodes() -> Result<()> {
+        let mut store = Store::new(IndexAccessMemory::default());
+        store.set_tree_nodes(true);
+        let mut merkle = Merkle::default();
+        for index in 0..4u32 {
+            let data = [index as u8; 8];
+            let signature = Signature::new(
+                [2u8; SCHEME_SIGNATURE_LENGTH],
+                [7u8; SCHEME_SIGNATURE_LENGTH],
+            );
+            let block = Block::new(u64::from(index) * 8, 8, signature);
+            let leaf = Node::new(2 * u64::from(index), Hash::from_leaf(&data)?, 8);
+            let nodes = parent_nodes(merkle.roots(), &leaf);
+            if !nodes.is_empty() {
+                assert!(store.write(index, &data, &[], &block).await.is_err());
+            }
+            store.write(index, &data, &nodes, &block).await?;
+            merkle.next(leaf.hash().clone(), 8);
+            let (content, block2, nodes2) = store.read_record(index).await?.unwrap();
+            assert_eq!(content, Content::Data(data.to_vec()));
+            assert_eq!(block2, block);
+            assert_eq!(nodes2, nodes);
+        }
+        // the root of all 4 blocks is stored with the last block
+        let (_, _, nodes) = store.read_record(3).await?.unwrap();
+        assert_eq!(nodes.last(), merkle.roots().last());
+        let metadata = 4 * BLOCK_LENGTH + 3 * NODE_SIZE;
+        assert_eq!(store.metadata_len(), metadata as u64);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn corruption() -> Result<()> {
+        let storage = IndexAccessFault::new(IndexAccessMemory::default());
+        let faults = storage.faults();
+        let mut store = Store::new(storage);
+        store.set_checkpoints(true);
+        let data = b"hello world";
+        let signature = Signature::new(
+            [2u8; SCHEME_SIGNATURE_LENGTH],
+            [7u8; SCHEME_SIGNATURE_LENGTH],
+        );
+        let block = Block::new(0, 11, signature);
+        store.write(0, data, &[], &block).await?;
+
+        let length_bit = (data.len() + size_of::<u64>()) * 8;
+        let flag_bit = (data.len() + BLOCK_LENGTH) * 8 + 1;
+        for fault in [
+            // the block length no longer matches the data
+            Fault::BitFlip(length_bit),
+            // the record lost its end
+            Fault::Torn(BLOCK_LENGTH),
+            // the tree signature flag is neither set nor unset
+            Fault::BitFlip(flag_bit),
+        ] {
+            faults.clear();
+            faults.inject(Injection::new(Operation::Read, Trigger::Index(1), fault));
+            assert!(store.read(0).await.is_err());
+        }
+
+        faults.clear();
+        faults.inject(Injection::new(Operation::Read, Trigger::Always, Fault::Error));
+        assert!(store.read(0).await.unwrap_err().is::<InjectedError>());
+        faults.clear();
+        assert_eq!(store.read(0).await?.unwrap(), (Content::Data(data.to_vec()), block));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn merkle() -> Result<()> {
         let mut store = Store::new(IndexAccessMemory::default());
@@ -134,8 +778,19 @@ mod tests {
         merkle.next(Hash::from_leaf(b"b")?, 1);
         merkle.next(Hash::from_leaf(b"c")?, 1);
         store.write_merkle(&merkle).await?;
-        let merkle2 = store.read_merkle().await?;
+        let (merkle2, usage) = store.read_merkle().await?;
         assert_eq!(merkle.roots(), merkle2.roots());
+        assert!(usage);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn header() -> Result<()> {
+        let mut store = Store::new(IndexAccessMemory::default());
+        assert_eq!(store.read_header().await?, None);
+        let header = Header::new(SignatureScheme::Ed25519);
+        store.write_header(&header).await?;
+        assert_eq!(store.read_header().await?, Some(header));
         Ok(())
     }
 }
@@ -0,0 +1,224 @@
+use datacore::{verify_proof, Core, KeyPair, Signature, VerifyError, MAX_CHECKPOINT_INTERVAL};
+use index_access_fs::IndexAccessFs;
+use index_access_memory::IndexAccessMemory;
+
+#[tokio::test]
+async fn checkpoint_interval() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(3).await.unwrap();
+
+    for i in 0..7u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+    assert_eq!(core.len(), 7);
+    assert_eq!(core.checkpoint_len(), 6);
+    for i in 0..7 {
+        let (_, signature) = core.get(i).await.unwrap().unwrap();
+        assert_eq!(signature.tree().is_some(), i == 2 || i == 5);
+    }
+    assert!(core.proof(6).await.is_err());
+
+    core.checkpoint().await.unwrap();
+    assert_eq!(core.checkpoint_len(), 7);
+    assert!(core.head().await.unwrap().unwrap().1.tree().is_some());
+
+    for i in 0..7u32 {
+        let proof = core.proof(i).await.unwrap();
+        let length = match i {
+            0..=2 => 3,
+            3..=5 => 6,
+            _ => 7,
+        };
+        assert_eq!(proof.length(), length);
+        verify_proof(&keypair.pk, &keypair.pk, &[], &[i as u8], &proof).unwrap();
+        assert!(verify_proof(&keypair.pk, &keypair.pk, &[], b"oops", &proof).is_err());
+    }
+}
+
+#[tokio::test]
+async fn checkpoint_on_demand() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.append(b"hello", None).await.unwrap();
+    assert!(core.enable_checkpoints(0).await.is_err());
+
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(0).await.unwrap();
+    for i in 0..5u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+    assert_eq!(core.checkpoint_len(), 0);
+    core.checkpoint().await.unwrap();
+    assert_eq!(core.checkpoint_len(), 5);
+    let proof = core.proof(1).await.unwrap();
+    verify_proof(&keypair.pk, &keypair.pk, &[], &[1], &proof).unwrap();
+}
+
+#[tokio::test]
+async fn checkpoint_persists() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(2).await.unwrap();
+    for i in 0..5u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+    drop(core);
+
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.checkpoints(), Some(2));
+    assert_eq!(core.len(), 5);
+    assert_eq!(core.checkpoint_len(), 4);
+    assert_eq!(core.get(4).await.unwrap().unwrap().0, [4]);
+    core.append(&[5], None).await.unwrap();
+    assert_eq!(core.checkpoint_len(), 6);
+}
+
+#[tokio::test]
+async fn checkpoint_replicate() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(4).await.unwrap();
+    for i in 0..8u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+
+    // replicas need checkpoints enabled
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None)
+        .await
+        .unwrap();
+    let (data, signature) = core.get(0).await.unwrap().unwrap();
+    assert!(replica.append(&data, Some(signature)).await.is_err());
+
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None)
+        .await
+        .unwrap();
+    replica.enable_checkpoints(0).await.unwrap();
+    for i in 0..8 {
+        let (data, signature) = core.get(i).await.unwrap().unwrap();
+        replica.append(&data, Some(signature)).await.unwrap();
+    }
+    assert_eq!(replica.len(), 8);
+    assert_eq!(replica.checkpoint_len(), 8);
+    let proof = core.proof(5).await.unwrap();
+    assert_eq!(replica.proof(5).await.unwrap(), proof);
+}
+
+#[tokio::test]
+async fn checkpoint_replicate_rollback() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(2).await.unwrap();
+    for i in 0..4u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None)
+        .await
+        .unwrap();
+    replica.enable_checkpoints(0).await.unwrap();
+    for i in 0..2 {
+        let (data, signature) = core.get(i).await.unwrap().unwrap();
+        replica.append(&data, Some(signature)).await.unwrap();
+    }
+
+    // block 0 replayed at index 2, only caught by the next checkpoint
+    let (data, signature) = core.get(0).await.unwrap().unwrap();
+    let signature = Signature::without_tree(*signature.data());
+    replica.append(&data, Some(signature)).await.unwrap();
+    assert_eq!(replica.len(), 3);
+    let (data, signature) = core.get(3).await.unwrap().unwrap();
+    let err = replica.append(&data, Some(signature)).await.unwrap_err();
+    assert_eq!(err.downcast_ref(), Some(&VerifyError::new(3)));
+    assert_eq!(replica.len(), 2);
+    assert_eq!(replica.checkpoint_len(), 2);
+
+    for i in 2..4 {
+        let (data, signature) = core.get(i).await.unwrap().unwrap();
+        replica.append(&data, Some(signature)).await.unwrap();
+    }
+    assert_eq!(replica.len(), 4);
+    assert_eq!(replica.checkpoint_len(), 4);
+}
+
+#[tokio::test]
+async fn checkpoint_max_interval() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert!(core.enable_checkpoints(MAX_CHECKPOINT_INTERVAL + 1).await.is_err());
+    core.enable_checkpoints(0).await.unwrap();
+    for i in 0..MAX_CHECKPOINT_INTERVAL {
+        core.append(&i.to_le_bytes(), None).await.unwrap();
+    }
+    assert_eq!(core.checkpoint_len(), MAX_CHECKPOINT_INTERVAL);
+
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None)
+        .await
+        .unwrap();
+    replica.enable_checkpoints(0).await.unwrap();
+    for i in 0..MAX_CHECKPOINT_INTERVAL - 1 {
+        let (data, signature) = core.get(i).await.unwrap().unwrap();
+        replica.append(&data, Some(signature)).await.unwrap();
+    }
+    // blocks are not served before a checkpoint covers them
+    assert_eq!(replica.len(), MAX_CHECKPOINT_INTERVAL - 1);
+    assert_eq!(replica.get(0).await.unwrap(), None);
+
+    // the writer must sign the tree within the interval
+    let (data, signature) = core.head().await.unwrap().unwrap();
+    let without_tree = Signature::without_tree(*signature.data());
+    assert!(replica.append(&data, Some(without_tree)).await.is_err());
+    replica.append(&data, Some(signature)).await.unwrap();
+    assert_eq!(replica.checkpoint_len(), MAX_CHECKPOINT_INTERVAL);
+    assert_eq!(replica.get(0).await.unwrap(), core.get(0).await.unwrap());
+}
@@ -0,0 +1,118 @@
+use datacore::{
+    hash_roots, verify_consistency, verify_signed_consistency, Core, KeyPair, Verifier,
+};
+use index_access_memory::IndexAccessMemory;
+
+#[tokio::test]
+async fn consistency_proof_all_lengths() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    for i in 0..13u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+
+    for old in 0..=13 {
+        let old_roots = core.roots(old).await.unwrap();
+        for new in old..=13 {
+            let new_roots = core.roots(new).await.unwrap();
+            let proof = core.consistency_proof(old, new).await.unwrap();
+            verify_consistency(&old_roots, &new_roots, &proof).unwrap();
+        }
+    }
+    assert!(core.consistency_proof(5, 4).await.is_err());
+    assert!(core.consistency_proof(5, 14).await.is_err());
+}
+
+#[tokio::test]
+async fn consistency_proof_signed_roots() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    for i in 0..9u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+
+    let old_roots = core.roots(4).await.unwrap();
+    let (_, signature) = core.get(3).await.unwrap().unwrap();
+    let tree = signature.tree().unwrap();
+    Verifier::verify(&keypair.pk, &hash_roots(&old_roots), tree).unwrap();
+
+    let new_roots = core.roots(9).await.unwrap();
+    let proof = core.consistency_proof(4, 9).await.unwrap();
+    verify_consistency(&old_roots, &new_roots, &proof).unwrap();
+}
+
+#[tokio::test]
+async fn consistency_proof_signed_heads() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    for i in 0..4u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+    let old = core.signed_head().await.unwrap().unwrap();
+    for i in 4..9u8 {
+        core.append(&[i], None).await.unwrap();
+    }
+    let new = core.signed_head().await.unwrap().unwrap();
+
+    let pk = &keypair.pk;
+    let proof = core.consistency_proof(4, 9).await.unwrap();
+    verify_signed_consistency(pk, pk, &[], &old, &new, &proof).unwrap();
+    let other = KeyPair::generate().pk;
+    assert!(verify_signed_consistency(pk, &other, &[], &old, &new, &proof).is_err());
+    // the heads have to match the lengths of the proof
+    assert!(verify_signed_consistency(pk, pk, &[], &new, &new, &proof).is_err());
+    let proof = core.consistency_proof(5, 9).await.unwrap();
+    assert!(verify_signed_consistency(pk, pk, &[], &old, &new, &proof).is_err());
+}
+
+#[tokio::test]
+async fn consistency_proof_detects_fork() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    let mut fork = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    for i in 0..10u8 {
+        core.append(&[i], None).await.unwrap();
+        let data = if i == 5 { [42] } else { [i] };
+        fork.append(&data, None).await.unwrap();
+    }
+
+    let old_roots = core.roots(7).await.unwrap();
+    let new_roots = fork.roots(10).await.unwrap();
+    let proof = fork.consistency_proof(7, 10).await.unwrap();
+    assert!(verify_consistency(&old_roots, &new_roots, &proof).is_err());
+
+    // history before the fork is still consistent
+    let old_roots = core.roots(5).await.unwrap();
+    let proof = fork.consistency_proof(5, 10).await.unwrap();
+    verify_consistency(&old_roots, &new_roots, &proof).unwrap();
+}
@@ -1,4 +1,12 @@
-use datacore::{sign, Core, Hash, KeyPair, Merkle, NodeTrait, Signature};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use std::num::NonZeroU8;
+
+use datacore::{
+    sign, verify_proof, verify_schema, verify_signed_head, ClearedError, Core, Hash,
+    IndexAccessLog, KeyPair, Merkle, NodeTrait, PublicKey, Signature, SignatureScheme, SignedHead,
+    Signer, Verifier, ZeroizingKey, MAX_BLOCK_SIZE, SCHEME_SIGNATURE_LENGTH,
+};
 use index_access_fs::IndexAccessFs;
 use index_access_memory::IndexAccessMemory;
 
@@ -67,13 +75,13 @@ async fn core_signatures() {
     let mut merkle = Merkle::default();
     merkle.next(Hash::from_leaf(data1).unwrap(), data1.len() as u32);
     let signature1 = Signature::new(
-        sign(&keypair2.sk, &Hash::from_leaf(data1).unwrap()),
-        sign(&keypair2.sk, &hash_tree(&merkle)),
+        *sign(keypair2.sk.expose(), &Hash::from_leaf(data1).unwrap()),
+        *sign(keypair2.sk.expose(), &hash_tree(&merkle)),
     );
     merkle.next(Hash::from_leaf(data2).unwrap(), data2.len() as u32);
     let signature2 = Signature::new(
-        sign(&keypair2.sk, &Hash::from_leaf(data2).unwrap()),
-        sign(&keypair2.sk, &hash_tree(&merkle)),
+        *sign(keypair2.sk.expose(), &Hash::from_leaf(data2).unwrap()),
+        *sign(keypair2.sk.expose(), &hash_tree(&merkle)),
     );
 
     assert_eq!(core.len(), 2);
@@ -131,6 +139,26 @@ async fn core_append_no_secret_key() {
     assert_eq!(core.len(), 0);
 }
 
+#[tokio::test]
+async fn core_secret_key() {
+    let keypair = KeyPair::generate();
+    let core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+
+    let secret_key = core.secret_key().unwrap();
+    assert_eq!(secret_key.expose(), keypair.sk.expose());
+    assert_eq!(secret_key.public_key(), keypair.pk);
+    // only the public key is shown
+    let debug = format!("{:?}", secret_key);
+    assert!(debug.contains(&format!("{:?}", keypair.pk)));
+    assert!(!debug.contains(&format!("{:?}", &keypair.sk.expose()[..32])));
+}
+
 #[tokio::test]
 async fn core_disk_append() {
     let dir = tempfile::tempdir().unwrap().into_path();
@@ -167,7 +195,7 @@ async fn core_disk_persists() {
     core.append(b"hello world", None).await.unwrap();
     core.append(b"this is datacore", None).await.unwrap();
 
-    let mut core = Core::new(
+    let core = Core::new(
         IndexAccessFs::new(&dir).await.unwrap(),
         keypair2.pk,
         Some(keypair2.sk),
@@ -180,6 +208,498 @@ async fn core_disk_persists() {
     assert_eq!(core.get(1).await.unwrap().unwrap().0, b"this is datacore",);
 }
 
+fn custom_scheme() -> SignatureScheme {
+    SignatureScheme::Custom(NonZeroU8::new(42).unwrap())
+}
+
+struct CustomSigner(ZeroizingKey);
+#[async_trait]
+impl Signer for CustomSigner {
+    fn scheme(&self) -> SignatureScheme {
+        custom_scheme()
+    }
+    async fn sign(&self, msg: &[u8]) -> Result<[u8; SCHEME_SIGNATURE_LENGTH]> {
+        Signer::sign(&self.0, msg).await
+    }
+}
+struct CustomVerifier(PublicKey);
+impl Verifier for CustomVerifier {
+    fn scheme(&self) -> SignatureScheme {
+        custom_scheme()
+    }
+    fn verify(&self, msg: &[u8], signature: &[u8; SCHEME_SIGNATURE_LENGTH]) -> Result<()> {
+        Verifier::verify(&self.0, msg, signature)
+    }
+}
+
+#[tokio::test]
+async fn core_custom_scheme() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let mut core = Core::with_scheme(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Box::new(CustomVerifier(keypair.pk)),
+        Some(Box::new(CustomSigner(keypair.sk.clone()))),
+    )
+    .await
+    .unwrap();
+
+    core.append(b"hello world", None).await.unwrap();
+    assert_eq!(core.scheme(), custom_scheme());
+    assert_eq!(core.get(0).await.unwrap().unwrap().0, b"hello world");
+
+    // the scheme is recorded in the header
+    assert!(Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .is_err());
+    let core = Core::with_scheme(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Box::new(CustomVerifier(keypair.pk)),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.len(), 1);
+}
+
+#[tokio::test]
+async fn core_scheme_mismatch() {
+    let keypair = KeyPair::generate();
+    let result = Core::with_scheme(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Box::new(keypair.pk),
+        Some(Box::new(CustomSigner(keypair.sk))),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn core_signed_head() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.signed_head().await.unwrap(), None);
+
+    core.append(b"hello", None).await.unwrap();
+    core.append(b"world", None).await.unwrap();
+    let head = core.signed_head().await.unwrap().unwrap();
+    assert_eq!(head.length(), 2);
+    assert_eq!(head.byte_length(), 10);
+    assert_eq!(
+        *core.head().await.unwrap().unwrap().1.tree().unwrap(),
+        *head.signature()
+    );
+
+    let head = SignedHead::from_bytes(&head.to_bytes().unwrap()).unwrap();
+    verify_signed_head(&keypair.pk, &keypair.pk, &[], &head).unwrap();
+    assert!(verify_signed_head(&keypair.pk, &KeyPair::generate().pk, &[], &head).is_err());
+}
+
+#[tokio::test]
+async fn core_signed_head_checkpoints() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(2).await.unwrap();
+    core.append(b"a", None).await.unwrap();
+    assert_eq!(core.signed_head().await.unwrap(), None);
+    core.append(b"b", None).await.unwrap();
+    core.append(b"c", None).await.unwrap();
+
+    let head = core.signed_head().await.unwrap().unwrap();
+    assert_eq!(head.length(), 2);
+    assert_eq!(head.byte_length(), 2);
+    assert_eq!(head.roots(), core.roots(2).await.unwrap());
+    verify_signed_head(&keypair.pk, &keypair.pk, &[], &head).unwrap();
+}
+
+#[tokio::test]
+async fn core_clear() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    for i in 0..5u8 {
+        core.append(&[i; 32], None).await.unwrap();
+    }
+    let head = core.signed_head().await.unwrap();
+    core.clear(1..3).await.unwrap();
+    assert!(core.clear(4..6).await.is_err());
+
+    assert!(!core.is_cleared(0).await.unwrap());
+    assert!(core.is_cleared(1).await.unwrap());
+    let err = core.get(2).await.unwrap_err();
+    assert_eq!(err.downcast_ref::<ClearedError>().unwrap().index(), 2);
+    assert_eq!(core.get(3).await.unwrap().unwrap().0, [3; 32]);
+
+    // the tree is kept
+    assert_eq!(core.signed_head().await.unwrap(), head);
+    let proof = core.proof(1).await.unwrap();
+    verify_proof(&keypair.pk, &keypair.pk, &[], &[1; 32], &proof).unwrap();
+    core.append(b"more", None).await.unwrap();
+    core.refetch(0..5).await.unwrap();
+    drop(core);
+
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.len(), 6);
+    assert_eq!(core.next_refetch(), Some(1));
+    assert!(core.get(1).await.is_err());
+    assert!(core.restore(1, &[2; 32]).await.is_err());
+    core.restore(1, &[1; 32]).await.unwrap();
+    assert_eq!(core.get(1).await.unwrap().unwrap().0, [1; 32]);
+    assert!(core.is_cleared(2).await.unwrap());
+    assert_eq!(core.next_refetch(), Some(2));
+}
+
+#[tokio::test]
+async fn core_stats() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    let empty = core.stats();
+    assert_eq!(empty.blocks(), 0);
+    assert_eq!(empty.average_block_size(), 0);
+    assert_eq!(empty.storage_bytes(), empty.metadata_bytes());
+
+    for i in 0..4u8 {
+        core.append(&[i; 40], None).await.unwrap();
+    }
+    let stats = core.stats();
+    assert_eq!(stats.blocks(), 4);
+    assert_eq!(stats.bytes(), 160);
+    assert_eq!(stats.average_block_size(), 40);
+    assert_eq!(stats.cleared_bytes(), 0);
+    assert_eq!(stats.storage_bytes(), 160 + stats.metadata_bytes());
+
+    // cleared blocks keep the leaf hash of their data
+    core.clear(1..3).await.unwrap();
+    let cleared = core.stats();
+    assert_eq!(cleared.bytes(), 160);
+    assert_eq!(cleared.cleared_bytes(), 80);
+    assert_eq!(cleared.metadata_bytes(), stats.metadata_bytes() + 64);
+    assert_eq!(cleared.storage_bytes(), 80 + cleared.metadata_bytes());
+    drop(core);
+
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.stats(), cleared);
+    core.restore(1, &[1; 40]).await.unwrap();
+    core.restore(2, &[2; 40]).await.unwrap();
+    assert_eq!(core.stats(), stats);
+
+    // a checkpoint adds the tree signature to the head block
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.enable_checkpoints(0).await.unwrap();
+    core.append(b"a", None).await.unwrap();
+    let metadata = core.stats().metadata_bytes();
+    core.checkpoint().await.unwrap();
+    assert_eq!(
+        core.stats().metadata_bytes(),
+        metadata + SCHEME_SIGNATURE_LENGTH as u64
+    );
+}
+
+#[tokio::test]
+async fn core_compact() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let dir2 = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    core.set_schema("test").await.unwrap();
+    for i in 0..4u8 {
+        core.append(&[i; 64], None).await.unwrap();
+    }
+    core.clear(0..2).await.unwrap();
+    let stats = core.stats();
+    let head = core.signed_head().await.unwrap();
+
+    // the backend needs to be empty
+    let used = IndexAccessFs::new(&dir).await.unwrap();
+    assert!(core.compact(used).await.is_err());
+    assert_eq!(core.stats(), stats);
+
+    core.compact(IndexAccessFs::new(&dir2).await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(core.stats(), stats);
+    assert_eq!(core.signed_head().await.unwrap(), head);
+    core.append(b"more", None).await.unwrap();
+    drop(core);
+
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir2).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.len(), 5);
+    assert_eq!(core.schema(), Some("test"));
+    assert!(core.is_cleared(1).await.unwrap());
+    assert_eq!(core.get(2).await.unwrap().unwrap().0, [2; 64]);
+    assert_eq!(core.get(4).await.unwrap().unwrap().0, b"more");
+    core.restore(0, &[0; 64]).await.unwrap();
+    let proof = core.proof(0).await.unwrap();
+    verify_proof(&keypair.pk, &keypair.pk, &[], &[0; 64], &proof).unwrap();
+}
+
+#[tokio::test]
+async fn core_log_storage() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let dir2 = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessLog::open(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    for i in 0..10u8 {
+        core.append(&[i; 16], None).await.unwrap();
+    }
+    core.clear(0..5).await.unwrap();
+    drop(core);
+
+    let mut core = Core::new(
+        IndexAccessLog::open(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.len(), 10);
+    assert!(core.is_cleared(4).await.unwrap());
+    assert_eq!(core.get(5).await.unwrap().unwrap().0, [5; 16]);
+
+    // replaced records are left behind
+    core.compact(IndexAccessLog::open(&dir2).await.unwrap())
+        .await
+        .unwrap();
+    drop(core);
+    let size = |dir: &std::path::Path| std::fs::metadata(dir.join("0000000000.log")).unwrap().len();
+    assert!(size(&dir2) < size(&dir));
+
+    let core = Core::new(
+        IndexAccessLog::open(&dir2).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.len(), 10);
+    assert_eq!(core.get(9).await.unwrap().unwrap().0, [9; 16]);
+}
+
+#[tokio::test]
+async fn core_append_batch() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.append(b"a", None).await.unwrap();
+    core.append_batch(&[b"bc", b"d", b"efg"]).await.unwrap();
+    assert_eq!(core.len(), 4);
+    assert_eq!(core.byte_len(), 7);
+    assert_eq!(core.get(3).await.unwrap().unwrap().0, b"efg");
+    let proof = core.proof(2).await.unwrap();
+    verify_proof(&keypair.pk, &keypair.pk, &[], b"d", &proof).unwrap();
+
+    // a failing block drops the whole batch
+    let stats = core.stats();
+    let large = vec![0u8; MAX_BLOCK_SIZE + 1];
+    assert!(core.append_batch(&[b"h", &large]).await.is_err());
+    assert_eq!(core.len(), 4);
+    assert_eq!(core.byte_len(), 7);
+    assert_eq!(core.stats(), stats);
+    assert_eq!(core.head().await.unwrap().unwrap().0, b"efg");
+    core.append_batch(&[b"i"]).await.unwrap();
+    assert_eq!(core.len(), 5);
+    let proof = core.proof(4).await.unwrap();
+    verify_proof(&keypair.pk, &keypair.pk, &[], b"i", &proof).unwrap();
+}
+
+#[tokio::test]
+async fn core_seek() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert!(core.seek(0, 0..1).await.is_err());
+    for data in [&b"abc"[..], b"de", b"f", b"ghij"] {
+        core.append(data, None).await.unwrap();
+    }
+    assert_eq!(core.byte_len(), 10);
+
+    assert_eq!(core.seek(0, 0..4).await.unwrap(), (0, 0));
+    assert_eq!(core.seek(2, 0..4).await.unwrap(), (0, 2));
+    assert_eq!(core.seek(3, 0..4).await.unwrap(), (1, 0));
+    assert_eq!(core.seek(4, 0..4).await.unwrap(), (1, 1));
+    assert_eq!(core.seek(5, 0..4).await.unwrap(), (2, 0));
+    assert_eq!(core.seek(9, 0..4).await.unwrap(), (3, 3));
+    assert!(core.seek(10, 0..4).await.is_err());
+
+    // the search is bound to the blocks
+    assert_eq!(core.seek(4, 1..3).await.unwrap(), (1, 1));
+    assert!(core.seek(9, 1..3).await.is_err());
+    assert!(core.seek(2, 1..3).await.is_err());
+    assert!(core.seek(2, 0..5).await.is_err());
+}
+
+#[tokio::test]
+async fn core_snapshot() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert!(core.snapshot().is_empty());
+    core.append(b"ab", None).await.unwrap();
+    core.append(b"c", None).await.unwrap();
+
+    let snapshot = core.snapshot();
+    core.append(b"d", None).await.unwrap();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot.byte_len(), 3);
+    assert_eq!(snapshot.checkpoint_len(), 2);
+    assert_eq!(snapshot.roots(), core.roots(2).await.unwrap().as_slice());
+    assert_ne!(core.snapshot(), snapshot);
+}
+
+#[tokio::test]
+async fn core_schema() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.set_schema("a").await.unwrap();
+    assert!(core.set_schema("b").await.is_err());
+    let signature = *core.schema_signature().unwrap();
+    verify_schema(&keypair.pk, &keypair.pk, "a", &signature).unwrap();
+
+    // the signed schema is adopted by a replica expecting it
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None)
+        .await
+        .unwrap();
+    replica.set_schema("a").await.unwrap();
+    assert_eq!(replica.schema_signature(), None);
+    assert!(replica.set_signed_schema("b", &signature).await.is_err());
+    replica.set_signed_schema("a", &signature).await.unwrap();
+    assert_eq!(replica.schema_signature(), Some(&signature));
+
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None)
+        .await
+        .unwrap();
+    replica.set_schema("b").await.unwrap();
+    assert!(replica.set_signed_schema("a", &signature).await.is_err());
+}
+
+#[tokio::test]
+async fn core_schema_check_blocks() {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.append(b"a", None).await.unwrap();
+    core.append(b"b", None).await.unwrap();
+    assert!(core.set_schema("a").await.is_err());
+
+    let only_a = |data: &[u8]| {
+        ensure!(data == b"a", "Not a.");
+        Ok(())
+    };
+    assert!(core.set_schema_with("a", only_a).await.is_err());
+    assert_eq!(core.schema(), None);
+    core.set_schema_with("ab", |_| Ok(())).await.unwrap();
+    assert_eq!(core.schema(), Some("ab"));
+    assert!(core.schema_signature().is_some());
+
+    // cleared blocks can not be checked
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    core.append(b"a", None).await.unwrap();
+    core.clear(0..1).await.unwrap();
+    let err = core.set_schema_with("a", |_| Ok(())).await.unwrap_err();
+    assert!(err.is::<ClearedError>());
+}
+
 fn hash_tree(merkle: &Merkle) -> Hash {
     let roots = merkle.roots();
     let hashes = roots.iter().map(|root| root.hash()).collect::<Vec<&Hash>>();
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use std::path::Path;
+
+use datacore::{
+    verify_proof, Core, Fault, Faults, Hash, IndexAccessFault, InjectedError, Injection, KeyPair,
+    Merkle, Operation, Trigger,
+};
+use index_access_fs::IndexAccessFs;
+
+// the roots of the tree are stored at index `0`, block `i` at index `i + 1`
+const STATE_INDEX: u32 = 0;
+
+type FaultCore = Core<IndexAccessFault<IndexAccessFs>>;
+
+async fn open(dir: &Path, keypair: &KeyPair) -> Result<(FaultCore, Faults)> {
+    let storage = IndexAccessFault::new(IndexAccessFs::new(dir).await?);
+    let faults = storage.faults();
+    let core = Core::new(storage, keypair.pk, Some(keypair.sk.clone())).await?;
+    Ok((core, faults))
+}
+
+async fn open_with(dir: &Path, keypair: &KeyPair, injection: Injection) -> Result<FaultCore> {
+    let storage = IndexAccessFault::new(IndexAccessFs::new(dir).await?);
+    storage.faults().inject(injection);
+    Core::new(storage, keypair.pk, Some(keypair.sk.clone())).await
+}
+
+#[tokio::test]
+async fn fault_failed_state_write() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+    for data in [b"a", b"b", b"c"] {
+        core.append(data, None).await?;
+    }
+
+    // the block is written, the tree is not
+    faults.inject(Injection::new(
+        Operation::Write,
+        Trigger::Index(STATE_INDEX),
+        Fault::Error,
+    ));
+    let err = core.append(b"d", None).await.unwrap_err();
+    assert!(err.is::<InjectedError>());
+    drop(core);
+
+    let (mut core, _) = open(dir.path(), &keypair).await?;
+    assert_eq!(core.len(), 3);
+    assert_eq!(core.byte_len(), 3);
+    core.append(b"e", None).await?;
+    assert_eq!(core.get(3).await?.unwrap().0, b"e");
+    let proof = core.proof(3).await?;
+    verify_proof(&keypair.pk, &keypair.pk, &[], b"e", &proof)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_torn_block_write() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+    core.append(b"hello", None).await?;
+
+    faults.inject(Injection::new(Operation::Write, Trigger::Index(2), Fault::Torn(3)).times(1));
+    assert!(core.append(b"world", None).await.is_err());
+    assert_eq!(core.len(), 1);
+    assert_eq!(core.stats().blocks(), 1);
+
+    // the torn record is overwritten by the next append
+    core.append(b"world", None).await?;
+    drop(core);
+
+    let (core, _) = open(dir.path(), &keypair).await?;
+    assert_eq!(core.len(), 2);
+    assert_eq!(core.get(1).await?.unwrap().0, b"world");
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_writes_fail_after() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+
+    // writes after the header, the first block and its tree fail
+    faults.inject(Injection::new(
+        Operation::Write,
+        Trigger::After(3),
+        Fault::Error,
+    ));
+    core.append(b"a", None).await?;
+    assert!(core.append_batch(&[b"b", b"c"]).await.is_err());
+    drop(core);
+
+    let (core, _) = open(dir.path(), &keypair).await?;
+    assert_eq!(core.len(), 1);
+    assert_eq!(core.get(0).await?.unwrap().0, b"a");
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_torn_state_write() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+    core.append(b"a", None).await?;
+
+    faults.inject(Injection::new(
+        Operation::Write,
+        Trigger::Index(STATE_INDEX),
+        Fault::Torn(7),
+    ));
+    assert!(core.append(b"b", None).await.is_err());
+    drop(core);
+
+    // the tree is recovered from the intact blocks signing it
+    let (mut core, _) = open(dir.path(), &keypair).await?;
+    assert_eq!(core.len(), 2);
+    assert_eq!(core.byte_len(), 2);
+    assert_eq!(core.get(1).await?.unwrap().0, b"b");
+    core.append(b"c", None).await?;
+    let proof = core.proof(2).await?;
+    verify_proof(&keypair.pk, &keypair.pk, &[], b"c", &proof)?;
+    drop(core);
+
+    let (core, _) = open(dir.path(), &keypair).await?;
+    assert_eq!(core.len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_torn_state_after_checkpoint() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+    core.enable_checkpoints(3).await?;
+    for data in [b"a", b"b", b"c", b"d"] {
+        core.append(data, None).await?;
+    }
+
+    faults.inject(Injection::new(
+        Operation::Write,
+        Trigger::Index(STATE_INDEX),
+        Fault::Torn(30),
+    ));
+    assert!(core.append(b"e", None).await.is_err());
+    drop(core);
+
+    // blocks after the last checkpoint are dropped
+    let (mut core, _) = open(dir.path(), &keypair).await?;
+    assert_eq!(core.len(), 3);
+    assert_eq!(core.byte_len(), 3);
+    core.append(b"f", None).await?;
+    assert_eq!(core.get(3).await?.unwrap().0, b"f");
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_new_read_errors() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, _) = open(dir.path(), &keypair).await?;
+    core.append(b"hello", None).await?;
+    drop(core);
+
+    for index in [STATE_INDEX, u32::MAX, 1] {
+        let injection = Injection::new(Operation::Read, Trigger::Index(index), Fault::Error);
+        let err = open_with(dir.path(), &keypair, injection)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&InjectedError::new(Operation::Read, index))
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_failed_rotation_write() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let next = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+    core.append(b"a", None).await?;
+
+    // the rotation is in the header, its block is not written
+    faults.inject(Injection::new(
+        Operation::Write,
+        Trigger::Index(2),
+        Fault::Error,
+    ));
+    assert!(core.rotate(next.sk).await.is_err());
+    assert!(core.rotations().is_empty());
+    faults.clear();
+    core.append(b"b", None).await?;
+    drop(core);
+
+    // the rotation does not match the block at its index
+    let (mut core, _) = open(dir.path(), &keypair).await?;
+    assert!(core.rotations().is_empty());
+    assert_eq!(core.writer_key(), &keypair.pk);
+    assert_eq!(core.get(1).await?.unwrap().0, b"b");
+    core.append(b"c", None).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_roots_read_tree_nodes() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let keypair = KeyPair::generate();
+    let (mut core, faults) = open(dir.path(), &keypair).await?;
+    let mut merkle = Merkle::default();
+    for data in [b"a", b"b", b"c", b"d", b"e", b"f", b"g", b"h"] {
+        core.append(data, None).await?;
+        if merkle.blocks() < 7 {
+            merkle.next(Hash::from_leaf(data)?, 1);
+        }
+    }
+
+    // the roots of 7 blocks are read from blocks 3, 5 and 6,
+    // the blocks under them are not read
+    for index in [0, 1, 2, 4] {
+        faults.inject(Injection::new(
+            Operation::Read,
+            Trigger::Index(index + 1),
+            Fault::Error,
+        ));
+    }
+    assert_eq!(&core.roots(7).await?, merkle.roots());
+    Ok(())
+}
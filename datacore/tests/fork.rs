@@ -0,0 +1,113 @@
+use datacore::{verify_fork_proof, Core, ForkError, KeyPair, PublicKey, ZeroizingKey};
+use index_access_fs::IndexAccessFs;
+use index_access_memory::IndexAccessMemory;
+
+async fn new_core(pk: PublicKey, sk: ZeroizingKey, data: &[&[u8]]) -> Core<IndexAccessMemory> {
+    let mut core = Core::new(IndexAccessMemory::default(), pk, Some(sk))
+        .await
+        .unwrap();
+    for data in data {
+        core.append(data, None).await.unwrap();
+    }
+    core
+}
+
+#[tokio::test]
+async fn fork_check_equal() {
+    let keypair = KeyPair::generate();
+    let mut core = new_core(keypair.pk, keypair.sk.clone(), &[b"a", b"b"]).await;
+    let longer = new_core(keypair.pk, keypair.sk, &[b"a", b"b", b"c"]).await;
+    let head = longer.signed_head_at(2).await.unwrap().unwrap();
+    assert!(core.check_fork(&head).await.unwrap());
+    assert_eq!(core.signed_head_at(2).await.unwrap(), Some(head));
+
+    // heads past the core or of another key are refused
+    let head = longer.signed_head().await.unwrap().unwrap();
+    assert!(core.check_fork(&head).await.is_err());
+    let other = KeyPair::generate();
+    let other = new_core(other.pk, other.sk, &[b"X"]).await;
+    let head = other.signed_head().await.unwrap().unwrap();
+    assert!(core.check_fork(&head).await.is_err());
+    assert_eq!(core.fork_proof(), None);
+}
+
+#[tokio::test]
+async fn fork_detect() {
+    let dir = tempfile::tempdir().unwrap().into_path();
+    let keypair = KeyPair::generate();
+    let fork = new_core(keypair.pk, keypair.sk.clone(), &[b"a", b"b", b"X"]).await;
+    let mut core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    for data in [b"a", b"b", b"c"] {
+        core.append(data, None).await.unwrap();
+    }
+
+    // forked history is not consistent with the local tree
+    let head = fork.signed_head().await.unwrap().unwrap();
+    let err = core.check_fork(&head).await.unwrap_err();
+    let proof = err.downcast_ref::<ForkError>().unwrap().proof().clone();
+    assert_eq!(proof.length(), 3);
+    verify_fork_proof(&keypair.pk, &keypair.pk, &[], &proof).unwrap();
+    assert!(verify_fork_proof(&keypair.pk, &KeyPair::generate().pk, &[], &proof).is_err());
+    assert_eq!(core.fork_proof(), Some(&proof));
+
+    // forked cores refuse to append
+    let err = core.append(b"d", None).await.unwrap_err();
+    assert!(err.downcast_ref::<ForkError>().is_some());
+    drop(core);
+
+    let core = Core::new(
+        IndexAccessFs::new(&dir).await.unwrap(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+    assert_eq!(core.fork_proof(), Some(&proof));
+}
+
+#[tokio::test]
+async fn fork_detect_checkpoints() {
+    let keypair = KeyPair::generate();
+    let mut fork = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+    .unwrap();
+    let mut core = Core::new(IndexAccessMemory::default(), keypair.pk, Some(keypair.sk))
+        .await
+        .unwrap();
+    fork.enable_checkpoints(4).await.unwrap();
+    core.enable_checkpoints(4).await.unwrap();
+    for data in [b"a", b"X", b"c"] {
+        fork.append(data, None).await.unwrap();
+    }
+    for data in [b"a", b"b", b"c"] {
+        core.append(data, None).await.unwrap();
+    }
+
+    // the local block at the length of the head does not sign the tree
+    fork.checkpoint().await.unwrap();
+    let head = fork.signed_head().await.unwrap().unwrap();
+    assert_eq!(head.length(), 3);
+    assert!(!core.check_fork(&head).await.unwrap());
+    assert_eq!(core.fork_proof(), None);
+
+    // checkpoints prove the fork, though the forked block signs no tree
+    fork.append(b"d", None).await.unwrap();
+    core.append(b"d", None).await.unwrap();
+    assert!(core.get(1).await.unwrap().unwrap().1.tree().is_none());
+    let head = fork.signed_head().await.unwrap().unwrap();
+    assert_eq!(head.length(), 4);
+    let err = core.check_fork(&head).await.unwrap_err();
+    let proof = err.downcast_ref::<ForkError>().unwrap().proof();
+    assert_eq!(proof.length(), 4);
+    verify_fork_proof(&keypair.pk, &keypair.pk, &[], proof).unwrap();
+}
@@ -2,7 +2,9 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
-use datacore::{sign, verify, Core, Hash, KeyPair, Merkle, NodeTrait, Signature};
+use datacore::{
+    sign, verify, Core, Hash, KeyPair, Merkle, NodeTrait, Signature, SCHEME_SIGNATURE_LENGTH,
+};
 use index_access_fs::IndexAccessFs;
 
 fn read_bytes(dir: &Path, s: &str) -> Vec<u8> {
@@ -52,19 +54,19 @@ pub async fn replicate_manual() {
 
     let mut merkle = Merkle::default();
     let data_hash = Hash::from_leaf(data1).unwrap();
-    let data_sign = sign(&keypair3.sk, &data_hash);
+    let data_sign = sign(keypair3.sk.expose(), &data_hash);
     merkle.next(data_hash.clone(), data1.len() as u32);
     verify(&keypair3.pk, &data_hash, &data_sign).unwrap();
     let tree_hash = hash_tree(&merkle);
-    let tree_sign = sign(&keypair3.sk, &tree_hash);
+    let tree_sign = sign(keypair3.sk.expose(), &tree_hash);
     verify(&keypair3.pk, &tree_hash, &tree_sign).unwrap();
-    let signature = Signature::new(data_sign, tree_sign);
+    let signature = Signature::new(*data_sign, *tree_sign);
     replica.append(data1, Some(signature)).await.unwrap();
     let data_hash = Hash::from_leaf(data2).unwrap();
     merkle.next(data_hash.clone(), data2.len() as u32);
     let signature = Signature::new(
-        sign(&keypair3.sk, &data_hash),
-        sign(&keypair3.sk, &hash_tree(&merkle)),
+        *sign(keypair3.sk.expose(), &data_hash),
+        *sign(keypair3.sk.expose(), &hash_tree(&merkle)),
     );
     replica.append(data2, Some(signature)).await.unwrap();
     assert_eq!(replica.len(), 2);
@@ -79,8 +81,8 @@ pub async fn replicate_manual_no_secret_key() {
     let dir = tempfile::tempdir().unwrap().into_path();
     let dir2 = tempfile::tempdir().unwrap().into_path();
     let keypair = KeyPair::generate();
-    let keypair2 = KeyPair::from_slice(keypair.as_slice()).unwrap();
-    let keypair3 = KeyPair::from_slice(keypair.as_slice()).unwrap();
+    let keypair2 = keypair.clone();
+    let keypair3 = keypair.clone();
 
     let mut core = Core::new(
         IndexAccessFs::new(&dir).await.unwrap(),
@@ -108,15 +110,15 @@ pub async fn replicate_manual_no_secret_key() {
     let data_hash = Hash::from_leaf(data1).unwrap();
     merkle.next(data_hash.clone(), data1.len() as u32);
     let signature = Signature::new(
-        sign(&keypair3.sk, &data_hash),
-        sign(&keypair3.sk, &hash_tree(&merkle)),
+        *sign(keypair3.sk.expose(), &data_hash),
+        *sign(keypair3.sk.expose(), &hash_tree(&merkle)),
     );
     replica.append(data1, Some(signature)).await.unwrap();
     let data_hash = Hash::from_leaf(data2).unwrap();
     merkle.next(data_hash.clone(), data2.len() as u32);
     let signature = Signature::new(
-        sign(&keypair3.sk, &data_hash),
-        sign(&keypair3.sk, &hash_tree(&merkle)),
+        *sign(keypair3.sk.expose(), &data_hash),
+        *sign(keypair3.sk.expose(), &hash_tree(&merkle)),
     );
     replica.append(data2, Some(signature)).await.unwrap();
     assert_eq!(replica.len(), 2);
@@ -248,15 +250,15 @@ pub async fn replicate_fail_verify_then_append() {
     let (data2, signature) = core.get(1).await.unwrap().unwrap();
     let invalid_signature_1 = Signature::new(
         *signature.data(),
-        ed25519_compact::Signature::from_slice(&[0u8; ed25519_compact::Signature::BYTES]).unwrap(),
+        [0u8; SCHEME_SIGNATURE_LENGTH],
     );
     let invalid_signature_2 = Signature::new(
-        ed25519_compact::Signature::from_slice(&[0u8; ed25519_compact::Signature::BYTES]).unwrap(),
-        ed25519_compact::Signature::from_slice(&[0u8; ed25519_compact::Signature::BYTES]).unwrap(),
+        [0u8; SCHEME_SIGNATURE_LENGTH],
+        [0u8; SCHEME_SIGNATURE_LENGTH],
     );
     let invalid_signature_3 = Signature::new(
-        ed25519_compact::Signature::from_slice(&[0u8; ed25519_compact::Signature::BYTES]).unwrap(),
-        *signature.tree(),
+        [0u8; SCHEME_SIGNATURE_LENGTH],
+        *signature.tree().unwrap(),
     );
     assert!(replica
         .append(&data2, Some(invalid_signature_1))
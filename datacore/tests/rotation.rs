@@ -0,0 +1,245 @@
+use anyhow::Result;
+
+use datacore::{
+    sign, verify_key_rotation, verify_proof, verify_signed_head, Core, Hash, KeyPair, KeyRotation,
+    Merkle, NodeTrait, PublicKey, Signature,
+};
+use index_access_fs::IndexAccessFs;
+use index_access_memory::IndexAccessMemory;
+
+async fn replicate(
+    core: &Core<IndexAccessMemory>,
+    replica: &mut Core<IndexAccessMemory>,
+) -> Result<()> {
+    for index in replica.len()..core.len() {
+        let (data, signature) = core.get(index).await?.unwrap();
+        replica.append(&data, Some(signature)).await?;
+    }
+    Ok(())
+}
+
+async fn new_replica(public_key: PublicKey) -> Result<Core<IndexAccessMemory>> {
+    Core::new(IndexAccessMemory::default(), public_key, None).await
+}
+
+/// Sign `data` with `keypair` as the next block of `core`, marked as a key rotation.
+async fn sign_rotation(
+    core: &Core<IndexAccessMemory>,
+    keypair: &KeyPair,
+    data: &[u8],
+) -> Result<Signature> {
+    let mut merkle = Merkle::from_roots(core.roots(core.len()).await?);
+    merkle.next(Hash::from_leaf(data)?, u32::try_from(data.len())?);
+    let roots = merkle.roots();
+    let hashes = roots.iter().map(|root| root.hash()).collect::<Vec<&Hash>>();
+    let lengths = roots.iter().map(|root| root.length()).collect::<Vec<u32>>();
+    let tree = Hash::from_roots(&hashes, &lengths);
+    let signature = Signature::new(
+        *sign(keypair.sk.expose(), &Hash::from_key_rotation(data)?),
+        *sign(keypair.sk.expose(), &tree),
+    );
+    Ok(signature.into_key_rotation())
+}
+
+#[tokio::test]
+async fn rotation_append() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let old = KeyPair::generate();
+    let new = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessFs::new(dir.path()).await?,
+        old.pk,
+        Some(old.sk.clone()),
+    )
+    .await?;
+    core.append(b"a", None).await?;
+    core.append(b"b", None).await?;
+    core.rotate(new.sk.clone()).await?;
+    core.append(b"c", None).await?;
+
+    assert_eq!(core.len(), 4);
+    assert_eq!(core.public_key(), &old.pk);
+    assert_eq!(core.writer_key(), &new.pk);
+    assert_eq!(core.secret_key().unwrap().expose(), new.sk.expose());
+    assert_eq!(core.rotations().len(), 1);
+    let rotation = &core.rotations()[0];
+    assert_eq!(rotation.index(), 2);
+    assert_eq!(rotation.public_key(), &new.pk);
+    verify_key_rotation(&old.pk, &old.pk, rotation)?;
+
+    // the record is a block signed by the old key
+    let (data, signature) = core.get(2).await?.unwrap();
+    assert!(signature.is_key_rotation());
+    assert!(core.is_key_rotation(2));
+    assert!(!core.get(1).await?.unwrap().1.is_key_rotation());
+    assert_eq!(&KeyRotation::from_bytes(&data)?, rotation);
+    let proof = core.proof(2).await?;
+    verify_proof(&old.pk, &old.pk, core.rotations(), &data, &proof)?;
+    verify_proof(&old.pk, &old.pk, &[], &data, &proof)?;
+
+    // proofs after the rotation are resolved to the new key from the core key
+    let proof = core.proof(3).await?;
+    verify_proof(&old.pk, &old.pk, core.rotations(), b"c", &proof)?;
+    assert!(verify_proof(&old.pk, &old.pk, &[], b"c", &proof).is_err());
+    let forged = KeyRotation::sign(&new.sk, &old.pk, 2, new.pk).await?;
+    assert!(verify_proof(&old.pk, &old.pk, &[forged], b"c", &proof).is_err());
+    let head = core.signed_head().await?.unwrap();
+    verify_signed_head(&old.pk, &old.pk, core.rotations(), &head)?;
+    assert!(verify_signed_head(&old.pk, &old.pk, &[], &head).is_err());
+    drop(core);
+
+    let mut core = Core::new(
+        IndexAccessFs::new(dir.path()).await?,
+        old.pk,
+        Some(new.sk.clone()),
+    )
+    .await?;
+    assert_eq!(core.writer_key(), &new.pk);
+    core.append(b"d", None).await?;
+    drop(core);
+
+    // the old key can not write anymore
+    let mut core = Core::new(IndexAccessFs::new(dir.path()).await?, old.pk, Some(old.sk)).await?;
+    assert!(core.append(b"e", None).await.is_err());
+    assert_eq!(core.len(), 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rotation_replicate() -> Result<()> {
+    let old = KeyPair::generate();
+    let new = KeyPair::generate();
+    let newest = KeyPair::generate();
+    let mut core = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk.clone())).await?;
+    let mut replica = new_replica(old.pk).await?;
+
+    core.append(b"a", None).await?;
+    core.rotate(new.sk.clone()).await?;
+    core.append(b"b", None).await?;
+    replicate(&core, &mut replica).await?;
+    assert_eq!(replica.writer_key(), &new.pk);
+
+    // replicas follow the chain of keys
+    core.rotate(newest.sk.clone()).await?;
+    core.append(b"c", None).await?;
+    replicate(&core, &mut replica).await?;
+    assert_eq!(replica.len(), 5);
+    assert_eq!(replica.rotations(), core.rotations());
+    assert_eq!(replica.writer_key(), &newest.pk);
+    assert_eq!(replica.get(4).await?.unwrap().0, b"c");
+    Ok(())
+}
+
+#[tokio::test]
+async fn rotation_old_key_refused() -> Result<()> {
+    let old = KeyPair::generate();
+    let new = KeyPair::generate();
+    let mut core = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk.clone())).await?;
+    core.append(b"a", None).await?;
+    core.rotate(new.sk).await?;
+
+    // a replica of the old key can not append
+    let mut stolen = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk.clone())).await?;
+    replicate(&core, &mut stolen).await?;
+    assert!(stolen.append(b"b", None).await.is_err());
+
+    // blocks of a compromised old key are refused after the rotation
+    let mut fork = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk)).await?;
+    for data in [b"a", b"x", b"y"] {
+        fork.append(data, None).await?;
+    }
+    let mut replica = new_replica(old.pk).await?;
+    replicate(&core, &mut replica).await?;
+    let (data, signature) = fork.get(2).await?.unwrap();
+    assert!(replica.append(&data, Some(signature)).await.is_err());
+    assert_eq!(replica.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rotation_invalid() -> Result<()> {
+    let old = KeyPair::generate();
+    let new = KeyPair::generate();
+    let mut core = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk.clone())).await?;
+    core.append(b"a", None).await?;
+    let mut replica = new_replica(old.pk).await?;
+    replicate(&core, &mut replica).await?;
+
+    // signed by a key not writing the `Core`
+    let rotation = KeyRotation::sign(&new.sk, &old.pk, 1, new.pk).await?;
+    let data = rotation.to_bytes()?;
+    let signature = sign_rotation(&replica, &old, &data).await?;
+    assert!(replica.append(&data, Some(signature)).await.is_err());
+    // signed for another block
+    let rotation = KeyRotation::sign(&old.sk, &old.pk, 2, new.pk).await?;
+    let data = rotation.to_bytes()?;
+    let signature = sign_rotation(&replica, &old, &data).await?;
+    assert!(replica.append(&data, Some(signature)).await.is_err());
+    // signed for another `Core`
+    let rotation = KeyRotation::sign(&old.sk, &new.pk, 1, new.pk).await?;
+    let data = rotation.to_bytes()?;
+    let signature = sign_rotation(&replica, &old, &data).await?;
+    assert!(replica.append(&data, Some(signature)).await.is_err());
+    // rotated to the current key
+    assert!(core.rotate(old.sk).await.is_err());
+
+    assert_eq!(core.len(), 1);
+    assert!(core.rotations().is_empty());
+    assert_eq!(core.writer_key(), &old.pk);
+    assert_eq!(replica.len(), 1);
+    assert!(replica.rotations().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn rotation_marked_out_of_band() -> Result<()> {
+    let old = KeyPair::generate();
+    let new = KeyPair::generate();
+    let mut core = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk.clone())).await?;
+
+    // data looking like a record is not a rotation
+    let record = KeyRotation::sign(&old.sk, &old.pk, 0, new.pk).await?;
+    core.append(&record.to_bytes()?, None).await?;
+    assert!(core.rotations().is_empty());
+    assert!(!core.get(0).await?.unwrap().1.is_key_rotation());
+    assert_eq!(core.writer_key(), &old.pk);
+    core.rotate(new.sk).await?;
+
+    // the mark is covered by the data signature
+    let mut replica = new_replica(old.pk).await?;
+    let (data, signature) = core.get(0).await?.unwrap();
+    let marked = signature.clone().into_key_rotation();
+    assert!(replica.append(&data, Some(marked)).await.is_err());
+    replica.append(&data, Some(signature)).await?;
+    let (data, signature) = core.get(1).await?.unwrap();
+    let unmarked = Signature::from_bytes(signature.data(), signature.tree().unwrap())?;
+    assert!(replica.append(&data, Some(unmarked)).await.is_err());
+    replica.append(&data, Some(signature)).await?;
+    assert_eq!(replica.rotations(), core.rotations());
+    Ok(())
+}
+
+#[tokio::test]
+async fn rotation_checkpoints() -> Result<()> {
+    let old = KeyPair::generate();
+    let new = KeyPair::generate();
+    let mut core = Core::new(IndexAccessMemory::default(), old.pk, Some(old.sk)).await?;
+    core.enable_checkpoints(0).await?;
+    core.append(b"a", None).await?;
+    core.append(b"b", None).await?;
+    assert_eq!(core.checkpoint_len(), 0);
+
+    // the rotation signs the tree
+    core.rotate(new.sk).await?;
+    assert_eq!(core.checkpoint_len(), 3);
+    core.append(b"c", None).await?;
+    core.checkpoint().await?;
+    assert_eq!(core.checkpoint_len(), 4);
+
+    let mut replica = new_replica(*core.public_key()).await?;
+    replica.enable_checkpoints(0).await?;
+    replicate(&core, &mut replica).await?;
+    assert_eq!(replica.checkpoint_len(), 4);
+    assert_eq!(replica.writer_key(), &new.pk);
+    Ok(())
+}
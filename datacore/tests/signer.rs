@@ -0,0 +1,100 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+use tokio::task;
+
+use datacore::{Core, KeyPair, SignatureScheme, Signer, ZeroizingKey, SCHEME_SIGNATURE_LENGTH};
+use index_access_memory::IndexAccessMemory;
+
+/// Stand-in for a signing service running in a separate process.
+/// Signs length prefixed messages received over a Unix socket.
+async fn signing_service(listener: UnixListener, secret: ZeroizingKey) -> Result<()> {
+    let (mut stream, _) = listener.accept().await?;
+    while let Ok(length) = stream.read_u32_le().await {
+        let mut msg = vec![0u8; length as usize];
+        stream.read_exact(&mut msg).await?;
+        let signature = Signer::sign(&secret, &msg).await?;
+        stream.write_all(&signature).await?;
+    }
+    Ok(())
+}
+
+struct RemoteSigner {
+    stream: Mutex<UnixStream>,
+}
+impl RemoteSigner {
+    async fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path).await?;
+        Ok(Self {
+            stream: Mutex::new(stream),
+        })
+    }
+}
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+    async fn sign(&self, msg: &[u8]) -> Result<[u8; SCHEME_SIGNATURE_LENGTH]> {
+        let mut stream = self.stream.lock().await;
+        stream.write_u32_le(u32::try_from(msg.len())?).await?;
+        stream.write_all(msg).await?;
+        let mut signature = [0u8; SCHEME_SIGNATURE_LENGTH];
+        stream.read_exact(&mut signature).await?;
+        Ok(signature)
+    }
+}
+
+async fn remote_signer(secret: ZeroizingKey) -> Result<RemoteSigner> {
+    let dir = tempfile::tempdir()?.into_path();
+    let path = dir.join("signer.sock");
+    let listener = UnixListener::bind(&path)?;
+    task::spawn(signing_service(listener, secret));
+    RemoteSigner::connect(&path).await
+}
+
+#[tokio::test]
+async fn signer_remote_append() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let signer = remote_signer(keypair.sk.clone()).await?;
+    let mut core = Core::with_scheme(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Box::new(keypair.pk),
+        Some(Box::new(signer)),
+    )
+    .await?;
+    let mut local = Core::new(IndexAccessMemory::default(), keypair.pk, Some(keypair.sk)).await?;
+    assert!(core.secret_key().is_none());
+
+    for data in [&b"hello"[..], b"world"] {
+        core.append(data, None).await?;
+        local.append(data, None).await?;
+    }
+
+    assert_eq!(core.len(), 2);
+    assert_eq!(core.get(0).await?, local.get(0).await?);
+    assert_eq!(core.get(1).await?, local.get(1).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn signer_remote_wrong_key() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let other = KeyPair::generate();
+    let signer = remote_signer(other.sk).await?;
+    let mut core = Core::with_scheme(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Box::new(keypair.pk),
+        Some(Box::new(signer)),
+    )
+    .await?;
+
+    assert!(core.append(b"hello", None).await.is_err());
+    assert_eq!(core.len(), 0);
+    Ok(())
+}
@@ -0,0 +1,193 @@
+//! Bech32m encoding, as specified by BIP 350.
+//!
+//! Strings are a human readable prefix, the separator `1`,
+//! the data in 5 bit characters and a 6 character checksum.
+
+use anyhow::{anyhow, ensure, Result};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+const BECH32M: u32 = 0x2bc8_30a3;
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_LENGTH: usize = 90;
+
+/// Encode `data` with the prefix `hrp`.
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    encode_values(hrp, &convert(data, 8, 5, true).expect("padded conversion"))
+}
+
+/// Decode a string with the prefix `hrp`.
+pub fn decode(hrp: &str, string: &str) -> Result<Vec<u8>> {
+    let (prefix, values) = decode_values(string)?;
+    ensure!(
+        prefix == hrp,
+        "Expected prefix {:?}, found {:?}.",
+        hrp,
+        prefix
+    );
+    convert(&values, 5, 8, false)
+}
+
+fn encode_values(hrp: &str, values: &[u8]) -> String {
+    let checksum = polymod(
+        expand(hrp)
+            .chain(values.iter().copied())
+            .chain([0; CHECKSUM_LENGTH]),
+    ) ^ BECH32M;
+
+    let mut string = String::with_capacity(hrp.len() + 1 + values.len() + CHECKSUM_LENGTH);
+    string.push_str(hrp);
+    string.push('1');
+    for &value in values {
+        string.push(char::from(CHARSET[usize::from(value)]));
+    }
+    for i in (0..CHECKSUM_LENGTH).rev() {
+        let value = (checksum >> (5 * i)) & 31;
+        string.push(char::from(CHARSET[value as usize]));
+    }
+    string
+}
+
+fn decode_values(string: &str) -> Result<(String, Vec<u8>)> {
+    ensure!(
+        string.len() <= MAX_LENGTH,
+        "Encoded string is longer than {} characters.",
+        MAX_LENGTH
+    );
+    let lower = string.to_ascii_lowercase();
+    ensure!(
+        string == lower || string == string.to_ascii_uppercase(),
+        "Encoded string {:?} mixes upper and lower case.",
+        string
+    );
+    let separator = lower
+        .rfind('1')
+        .ok_or_else(|| anyhow!("Encoded string {:?} has no separator.", string))?;
+    ensure!(
+        separator > 0 && lower.len() - separator > CHECKSUM_LENGTH,
+        "Encoded string {:?} is too short.",
+        string
+    );
+    let (hrp, data) = lower.split_at(separator);
+    ensure!(
+        hrp.bytes().all(|c| (33..=126).contains(&c)),
+        "Invalid prefix {:?}.",
+        hrp
+    );
+
+    let mut values = Vec::with_capacity(data.len() - 1);
+    for (position, c) in data.char_indices().skip(1) {
+        let value = CHARSET
+            .iter()
+            .position(|&v| char::from(v) == c)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid character {:?} at position {}.",
+                    c,
+                    separator + position
+                )
+            })?;
+        values.push(value as u8);
+    }
+    ensure!(
+        polymod(expand(hrp).chain(values.iter().copied())) == BECH32M,
+        "Invalid checksum of {:?}, it may be mistyped.",
+        string
+    );
+    values.truncate(values.len() - CHECKSUM_LENGTH);
+    Ok((hrp.to_owned(), values))
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ff_ffff) << 5 ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|c| c & 31))
+}
+
+/// Regroup `data` of `from` bit values into `to` bit values.
+fn convert(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>> {
+    let max = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut values = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        acc = ((acc << from) | u32::from(value)) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            values.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            values.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else {
+        ensure!(
+            bits < from && (acc << (to - bits)) & max == 0,
+            "Invalid padding of encoded data."
+        );
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors() -> Result<()> {
+        // valid Bech32m strings of BIP 350
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ] {
+            let (hrp, values) = decode_values(valid)?;
+            assert_eq!(encode_values(&hrp, &values), valid.to_ascii_lowercase());
+        }
+        // a Bech32 checksum is not a Bech32m checksum
+        assert!(decode_values("a12uel5l").is_err());
+        assert!(decode_values("A1lqfn3a").is_err());
+        assert!(decode_values("1lqfn3a").is_err());
+        assert!(decode_values("a1lqfn3b").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode() -> Result<()> {
+        for length in [0, 1, 20, 32] {
+            let data: Vec<u8> = (0..length).map(|i| i * 7).collect();
+            let string = encode("test", &data);
+            assert_eq!(decode("test", &string)?, data);
+            assert_eq!(decode("test", &string.to_ascii_uppercase())?, data);
+            assert!(decode("other", &string).is_err());
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,19 @@
 use multi_map::MultiMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
-use crate::{key, Core, IndexAccess};
+use crate::{key, Core, IndexAccess, Stats};
 
 type PublicKeyBytes = [u8; 32];
 
 /// [Cores] is a container for storing and quickly accessing multiple [Core]s.
 ///
 /// Stored [Core]s can be accessed by [key::Public] or [key::Discovery].
+/// Each [Core] is behind a [RwLock], any number of readers
+/// can access it while no one appends.
 pub struct Cores<T> {
     length: usize,
-    map: MultiMap<key::Discovery, PublicKeyBytes, Arc<Mutex<Core<T>>>>,
+    map: MultiMap<key::Discovery, PublicKeyBytes, Arc<RwLock<Core<T>>>>,
 }
 impl<T> Default for Cores<T> {
     fn default() -> Self {
@@ -26,12 +28,12 @@ impl<T: IndexAccess + Send> Cores<T> {
     #[inline]
     pub fn insert(&mut self, core: Core<T>) {
         let public = *core.public_key();
-        let core = Arc::new(Mutex::new(core));
+        let core = Arc::new(RwLock::new(core));
 
         self.put(&public, core);
     }
-    /// Put a [Arc<Mutex<Core>>] under [PublicKey].
-    pub fn put(&mut self, public: &key::Public, core: Arc<Mutex<Core<T>>>) {
+    /// Put a [Arc<RwLock<Core>>] under [PublicKey].
+    pub fn put(&mut self, public: &key::Public, core: Arc<RwLock<Core<T>>>) {
         let public = public.as_slice().try_into().unwrap();
         let discovery = key::discovery(&public);
 
@@ -42,14 +44,14 @@ impl<T: IndexAccess + Send> Cores<T> {
     /// Try getting a [Core] by [PublicKey].
     #[must_use]
     #[inline]
-    pub fn get_by_public(&self, key: &key::Public) -> Option<Arc<Mutex<Core<T>>>> {
+    pub fn get_by_public(&self, key: &key::Public) -> Option<Arc<RwLock<Core<T>>>> {
         self.map.get_alt(key.as_slice().try_into().unwrap()).map(Arc::clone)
     }
 
     /// Try getting a [Core] by [DiscoveryKey].
     #[must_use]
     #[inline]
-    pub fn get_by_discovery(&self, key: &key::Discovery) -> Option<Arc<Mutex<Core<T>>>> {
+    pub fn get_by_discovery(&self, key: &key::Discovery) -> Option<Arc<RwLock<Core<T>>>> {
         self.map.get(key).map(Arc::clone)
     }
 
@@ -83,9 +85,18 @@ impl<T: IndexAccess + Send> Cores<T> {
     }
     /// Access the contained [Core]s.
     #[inline]
-    pub fn entries(&self) -> impl Iterator<Item = (key::Public, Arc<Mutex<Core<T>>>)> + '_ {
+    pub fn entries(&self) -> impl Iterator<Item = (key::Public, Arc<RwLock<Core<T>>>)> + '_ {
         self.map.iter().map(|(_discovery, (public, core))| {
             (key::Public::from_slice(public).unwrap(), Arc::clone(core))
         })
     }
+
+    /// Get the [Stats] of all contained [Core]s added up.
+    pub async fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for (_public, core) in self.entries() {
+            stats += core.read().await.stats();
+        }
+        stats
+    }
 }
@@ -0,0 +1,325 @@
+//! Filesystem-like [Drive] over two [Core]s.
+//!
+//! The metadata [Core] is a [KeyValue] store mapping file paths to [Stat]s,
+//! the content [Core] stores the file contents in blocks of
+//! at most [BLOCK_SIZE] bytes.
+//! Each file is a contiguous range of content blocks,
+//! located with [Core::seek] within the range when reading from an offset.
+
+use anyhow::{anyhow, ensure, Result};
+use std::ops::Range;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::kv::KeyValue;
+use crate::{Core, IndexAccess};
+
+/// Maximum size of a content block.
+pub const BLOCK_SIZE: usize = 64 * 1024;
+
+const STAT_LENGTH: usize = 4 + 8 + 8 + 4 + 4;
+
+/// [Stat] is the metadata of a file in a [Drive].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stat {
+    mode: u32,
+    size: u64,
+    offset: u64,
+    blocks: Range<u32>,
+}
+impl Stat {
+    /// Get the file mode.
+    #[must_use]
+    #[inline]
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+    /// Get the file size in bytes.
+    #[must_use]
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    /// Get the byte offset of the file in the content [Core].
+    #[must_use]
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    /// Get the range of content [Core] blocks of the file.
+    #[must_use]
+    #[inline]
+    pub fn blocks(&self) -> &Range<u32> {
+        &self.blocks
+    }
+
+    /// Serialize [Stat].
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(STAT_LENGTH);
+        data.extend_from_slice(&self.mode.to_le_bytes());
+        data.extend_from_slice(&self.size.to_le_bytes());
+        data.extend_from_slice(&self.offset.to_le_bytes());
+        data.extend_from_slice(&self.blocks.start.to_le_bytes());
+        data.extend_from_slice(&self.blocks.end.to_le_bytes());
+        data
+    }
+    /// Deserialize [Stat].
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() == STAT_LENGTH, "Invalid stat length.");
+        let mode = u32::from_le_bytes(data[0..4].try_into()?);
+        let size = u64::from_le_bytes(data[4..12].try_into()?);
+        let offset = u64::from_le_bytes(data[12..20].try_into()?);
+        let start = u32::from_le_bytes(data[20..24].try_into()?);
+        let end = u32::from_le_bytes(data[24..28].try_into()?);
+        ensure!(start <= end, "Invalid stat block range.");
+        Ok(Self {
+            mode,
+            size,
+            offset,
+            blocks: start..end,
+        })
+    }
+}
+
+/// Normalize `path` to `/a/b`, the root directory is `/`.
+fn normalize(path: &str) -> Result<String> {
+    ensure!(path.starts_with('/'), "Path {} is not absolute.", path);
+    let mut normalized = String::new();
+    for component in path.split('/').filter(|component| !component.is_empty()) {
+        ensure!(
+            component != "." && component != "..",
+            "Path {} is not normalized.",
+            path
+        );
+        normalized.push('/');
+        normalized.push_str(component);
+    }
+    if normalized.is_empty() {
+        normalized.push('/');
+    }
+    Ok(normalized)
+}
+
+/// [Drive] is a filesystem-like tree of files
+/// stored in a metadata and a content [Core].
+pub struct Drive<T> {
+    metadata: KeyValue<T>,
+    content: Arc<RwLock<Core<T>>>,
+}
+impl<T> Drive<T>
+where
+    T: IndexAccess + Send,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    /// Create a new [Drive] from its metadata and content [Core]s.
+    #[must_use]
+    #[inline]
+    pub fn new(metadata: Arc<RwLock<Core<T>>>, content: Arc<RwLock<Core<T>>>) -> Self {
+        Self {
+            metadata: KeyValue::new(metadata),
+            content,
+        }
+    }
+
+    /// Access the metadata [Core].
+    #[must_use]
+    #[inline]
+    pub fn metadata(&self) -> &Arc<RwLock<Core<T>>> {
+        self.metadata.core()
+    }
+    /// Access the content [Core].
+    #[must_use]
+    #[inline]
+    pub fn content(&self) -> &Arc<RwLock<Core<T>>> {
+        &self.content
+    }
+
+    /// Get the version of the [Drive], the length of its metadata [Core].
+    pub async fn version(&self) -> u32 {
+        self.metadata.version().await
+    }
+    /// Create a read-only [Drive] as it was at metadata length `version`.
+    #[must_use]
+    #[inline]
+    pub fn checkout(&self, version: u32) -> Self {
+        Self {
+            metadata: self.metadata.checkout(version),
+            content: Arc::clone(&self.content),
+        }
+    }
+
+    /// Write a file at `path`, replacing any previous file.
+    pub async fn write_file(&self, path: &str, data: &[u8], mode: u32) -> Result<()> {
+        let path = normalize(path)?;
+        ensure!(path != "/", "Cannot write a file at the root directory.");
+        // refuse early, before appending any content
+        ensure!(!self.metadata.is_checkout(), "Cannot write to a checkout.");
+
+        let stat = {
+            let mut content = self.content.write().await;
+            let start = content.len();
+            let offset = content.byte_len();
+            for chunk in data.chunks(BLOCK_SIZE) {
+                content.append(chunk, None).await?;
+            }
+            Stat {
+                mode,
+                size: data.len() as u64,
+                offset,
+                blocks: start..content.len(),
+            }
+        };
+        self.metadata.put(path.as_bytes(), &stat.to_bytes()).await
+    }
+
+    /// Get the [Stat] of the file at `path`.
+    pub async fn stat(&self, path: &str) -> Result<Option<Stat>> {
+        let path = normalize(path)?;
+        match self.metadata.get(path.as_bytes()).await? {
+            Some(data) => Ok(Some(Stat::from_bytes(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Open the file at `path` for reading.
+    pub async fn read_file(&self, path: &str) -> Result<Option<FileReader<T>>> {
+        Ok(self
+            .stat(path)
+            .await?
+            .map(|stat| FileReader::new(Arc::clone(&self.content), stat)))
+    }
+
+    /// Delete the file at `path`, return `false` if there was no file.
+    ///
+    /// The content stays readable from older versions.
+    pub async fn delete(&self, path: &str) -> Result<bool> {
+        let path = normalize(path)?;
+        self.metadata.del(path.as_bytes()).await
+    }
+
+    /// List the names of files and directories in directory `dir`.
+    pub async fn list(&self, dir: &str) -> Result<Vec<String>> {
+        let mut prefix = normalize(dir)?;
+        if prefix != "/" {
+            prefix.push('/');
+        }
+        let mut names = vec![];
+        for (path, _) in self.metadata.prefix(prefix.as_bytes()).await? {
+            let path = String::from_utf8(path)?;
+            if let Some(name) = path[prefix.len()..].split('/').next() {
+                names.push(name.to_owned());
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+}
+
+/// [FileReader] reads a file of a [Drive] from its content [Core].
+pub struct FileReader<T> {
+    content: Arc<RwLock<Core<T>>>,
+    stat: Stat,
+    position: u64,
+    // index, position in the file, and length of the last block read
+    block: Option<(u32, u64, u64)>,
+}
+impl<T> FileReader<T>
+where
+    T: IndexAccess + Send,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    fn new(content: Arc<RwLock<Core<T>>>, stat: Stat) -> Self {
+        Self {
+            content,
+            stat,
+            position: 0,
+            block: None,
+        }
+    }
+
+    /// Get the [Stat] of the file.
+    #[must_use]
+    #[inline]
+    pub fn stat(&self) -> &Stat {
+        &self.stat
+    }
+    /// Get the current position in the file.
+    #[must_use]
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+    /// Move to `position` in the file.
+    pub fn seek(&mut self, position: u64) -> Result<()> {
+        ensure!(
+            position <= self.stat.size,
+            "Position {} is beyond the end of the file.",
+            position
+        );
+        self.position = position;
+        Ok(())
+    }
+
+    /// Read up to `length` bytes from the current position,
+    /// at most until the end of the current block.
+    ///
+    /// Returns an empty buffer at the end of the file.
+    pub async fn read(&mut self, length: usize) -> Result<Vec<u8>> {
+        let remaining = self.stat.size - self.position;
+        if remaining == 0 || length == 0 {
+            return Ok(vec![]);
+        }
+        let content = self.content.read().await;
+        let (index, offset) = match self.block {
+            Some((index, start, length)) if (start..start + length).contains(&self.position) => {
+                (index, u32::try_from(self.position - start)?)
+            }
+            // sequential reads continue with the next block
+            Some((index, start, length)) if start + length == self.position => (index + 1, 0),
+            _ => {
+                let offset = self.stat.offset + self.position;
+                content.seek(offset, self.stat.blocks.clone()).await?
+            }
+        };
+        ensure!(
+            self.stat.blocks.contains(&index),
+            "File content is not in its blocks."
+        );
+        let (data, signature) = content
+            .get(index)
+            .await?
+            .ok_or_else(|| anyhow!("Missing file content block {}.", index))?;
+        ensure!(
+            !signature.is_key_rotation(),
+            "File content block {} is a key rotation.",
+            index
+        );
+        let start = offset as usize;
+        ensure!(
+            start < data.len(),
+            "File content block {} is too short.",
+            index
+        );
+        self.block = Some((index, self.position - u64::from(offset), data.len() as u64));
+        let end = data
+            .len()
+            .min(start + length)
+            .min(start + usize::try_from(remaining).unwrap_or(usize::MAX));
+        self.position += (end - start) as u64;
+        Ok(data[start..end].to_vec())
+    }
+
+    /// Read the rest of the file.
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        let mut data = vec![];
+        loop {
+            let chunk = self.read(BLOCK_SIZE).await?;
+            if chunk.is_empty() {
+                return Ok(data);
+            }
+            data.extend_from_slice(&chunk);
+        }
+    }
+}
@@ -2,18 +2,22 @@ use anyhow::Result;
 use futures_lite::future::FutureExt;
 use futures_lite::stream::Stream;
 use std::future::Future;
+use std::ops::Range;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
 use crate::{Signature, Core, IndexAccess};
 
 type ReadTask<T> = Pin<Box<dyn Future<Output = T>>>;
 
 /// Async [Stream] iterator over [Core].
+///
+/// Key rotation records of the [Core] are skipped.
 pub struct CoreIterator<T> {
-    core: Arc<Mutex<Core<T>>>,
+    core: Arc<RwLock<Core<T>>>,
+    end: u32,
     task: ReadTask<(u32, Option<Vec<u8>>)>,
 }
 impl<T> CoreIterator<T>
@@ -23,26 +27,42 @@ where
 {
     /// Create a new [CoreIterator].
     #[must_use]
-    pub fn new(core: Arc<Mutex<Core<T>>>, index: u32) -> Self {
-        let task = Self::create_read_task(Arc::clone(&core), index);
-        Self { core, task }
+    pub fn new(core: Arc<RwLock<Core<T>>>, index: u32) -> Self {
+        Self::with_range(core, index..u32::MAX)
+    }
+    /// Create a new [CoreIterator] over blocks in `range`.
+    #[must_use]
+    pub fn with_range(core: Arc<RwLock<Core<T>>>, range: Range<u32>) -> Self {
+        let task = Self::create_read_task(Arc::clone(&core), range.start, range.end);
+        Self {
+            core,
+            end: range.end,
+            task,
+        }
     }
 
     #[inline]
     fn create_read_task(
-        core: Arc<Mutex<Core<T>>>,
+        core: Arc<RwLock<Core<T>>>,
         index: u32,
+        end: u32,
     ) -> ReadTask<(u32, Option<Vec<u8>>)> {
         async move {
-            let result: Result<Option<(Vec<u8>, Signature)>>;
-            {
-                let mut core = core.lock().await;
-                result = core.get(index).await;
-            }
-            if let Ok(Some(data)) = result {
-                (index, Some(data.0))
-            } else {
-                (index, None)
+            let mut index = index;
+            loop {
+                if index >= end {
+                    return (index, None);
+                }
+                let result: Result<Option<(Vec<u8>, Signature)>>;
+                {
+                    let core = core.read().await;
+                    result = core.get(index).await;
+                }
+                match result {
+                    Ok(Some((_, signature))) if signature.is_key_rotation() => index += 1,
+                    Ok(Some((data, _))) => return (index, Some(data)),
+                    _ => return (index, None),
+                }
             }
         }
         .boxed()
@@ -58,7 +78,7 @@ where
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
         if let Poll::Ready((index, data)) = Pin::new(&mut this.task).poll(cx) {
-            this.task = Self::create_read_task(Arc::clone(&this.core), index + 1);
+            this.task = Self::create_read_task(Arc::clone(&this.core), index + 1, this.end);
             return Poll::Ready(data.map(|data| (index, data)));
         }
         Poll::Pending
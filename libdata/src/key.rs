@@ -1,5 +1,216 @@
 //! Export [Public] key and [Secret] key.
 //! Export [Discovery] key and a [discover] to derive it from a [Public] key.
+//!
+//! ## Encoding
+//! Keys are written as Bech32m strings, a prefix naming the kind of key
+//! followed by the key and a checksum catching typos, e.g. `ldpk1…`.
+//! [Url]s share a [Core](crate::Core) by its [Public] key, optionally with
+//! a length and a [ReadKey]: `libdata://ldpk1…?length=42&read=ldrk1…`.
 
-pub use datacore::{PublicKey as Public, SecretKey as Secret};
+use anyhow::{anyhow, bail, ensure, Result};
+use getrandom::getrandom;
+use std::fmt;
+use std::str::FromStr;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::bech32;
+
+pub use datacore::{PublicKey as Public, SecretKey as Secret, ZeroizingKey};
 pub use protocol::{discovery_key as discovery, DiscoveryKey as Discovery};
+
+/// Prefix of encoded [Public] keys.
+pub const PUBLIC_PREFIX: &str = "ldpk";
+/// Prefix of encoded [Discovery] keys.
+pub const DISCOVERY_PREFIX: &str = "lddk";
+/// Prefix of encoded [ReadKey]s.
+pub const READ_KEY_PREFIX: &str = "ldrk";
+/// Scheme of [Url]s.
+pub const URL_SCHEME: &str = "libdata";
+
+/// Encode a [Public] key.
+#[must_use]
+pub fn encode_public(key: &Public) -> String {
+    bech32::encode(PUBLIC_PREFIX, key.as_slice())
+}
+/// Decode a [Public] key.
+pub fn decode_public(string: &str) -> Result<Public> {
+    let key = bech32::decode(PUBLIC_PREFIX, string)?;
+    Public::from_slice(&key).map_err(|_| anyhow!("Invalid public key {:?}.", string))
+}
+
+/// Encode a [Discovery] key.
+#[must_use]
+pub fn encode_discovery(key: &Discovery) -> String {
+    bech32::encode(DISCOVERY_PREFIX, key)
+}
+/// Decode a [Discovery] key.
+pub fn decode_discovery(string: &str) -> Result<Discovery> {
+    let key = bech32::decode(DISCOVERY_PREFIX, string)?;
+    key.try_into()
+        .map_err(|_| anyhow!("Invalid discovery key {:?}.", string))
+}
+
+/// [ReadKey] is a symmetric key granting read access to the data of a `Core`,
+/// for applications encrypting their blocks.
+///
+/// The key is wiped from memory on drop and redacted in `Debug`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ReadKey {
+    key: [u8; 32],
+}
+impl ReadKey {
+    /// Create a [ReadKey] from bytes.
+    #[must_use]
+    #[inline]
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+    /// Generate a random [ReadKey].
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        getrandom(&mut key).expect("Could not get RNG");
+        Self { key }
+    }
+    /// Get the [ReadKey] as bytes.
+    #[must_use]
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+}
+impl Drop for ReadKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+impl fmt::Debug for ReadKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReadKey(..)")
+    }
+}
+impl fmt::Display for ReadKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bech32::encode(READ_KEY_PREFIX, &self.key))
+    }
+}
+impl FromStr for ReadKey {
+    type Err = anyhow::Error;
+
+    fn from_str(string: &str) -> Result<Self> {
+        let key = Zeroizing::new(bech32::decode(READ_KEY_PREFIX, string)?);
+        let key = key
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Invalid read key {:?}.", string))?;
+        Ok(Self { key })
+    }
+}
+
+/// [Url] shares a `Core` by its [Public] key.
+///
+/// The optional `length` pins the version of the `Core` at that length,
+/// the optional [ReadKey] grants access to encrypted data.
+/// Unknown query parameters are ignored when parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    public: Public,
+    length: Option<u32>,
+    read_key: Option<ReadKey>,
+}
+impl Url {
+    /// Create a new [Url] of the `Core` with [Public] key `public`.
+    #[must_use]
+    #[inline]
+    pub fn new(public: Public) -> Self {
+        Self {
+            public,
+            length: None,
+            read_key: None,
+        }
+    }
+    /// Pin the [Url] to the version of the `Core` at `length`.
+    #[must_use]
+    #[inline]
+    pub fn with_length(mut self, length: u32) -> Self {
+        self.length = Some(length);
+        self
+    }
+    /// Add a [ReadKey] to the [Url].
+    #[must_use]
+    #[inline]
+    pub fn with_read_key(mut self, read_key: ReadKey) -> Self {
+        self.read_key = Some(read_key);
+        self
+    }
+
+    /// Get the [Public] key.
+    #[must_use]
+    #[inline]
+    pub fn public_key(&self) -> &Public {
+        &self.public
+    }
+    /// Get the [Discovery] key.
+    #[must_use]
+    #[inline]
+    pub fn discovery_key(&self) -> Discovery {
+        let public: &[u8; 32] = self.public.as_slice().try_into().expect("32 byte key");
+        discovery(public)
+    }
+    /// Get the length.
+    #[must_use]
+    #[inline]
+    pub fn length(&self) -> Option<u32> {
+        self.length
+    }
+    /// Get the [ReadKey].
+    #[must_use]
+    #[inline]
+    pub fn read_key(&self) -> Option<&ReadKey> {
+        self.read_key.as_ref()
+    }
+}
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", URL_SCHEME, encode_public(&self.public))?;
+        let mut separator = '?';
+        if let Some(length) = self.length {
+            write!(f, "{}length={}", separator, length)?;
+            separator = '&';
+        }
+        if let Some(read_key) = &self.read_key {
+            write!(f, "{}read={}", separator, read_key)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for Url {
+    type Err = anyhow::Error;
+
+    fn from_str(string: &str) -> Result<Self> {
+        let rest = match string.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case(URL_SCHEME) => rest,
+            _ => bail!("Url {:?} does not start with `{}://`.", string, URL_SCHEME),
+        };
+        let (public, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut url = Self::new(decode_public(public)?);
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            match name {
+                "length" => {
+                    ensure!(url.length.is_none(), "Url has more than one length.");
+                    let length = value
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid length {:?} in Url.", value))?;
+                    url.length = Some(length);
+                }
+                "read" => {
+                    ensure!(url.read_key.is_none(), "Url has more than one read key.");
+                    url.read_key = Some(value.parse()?);
+                }
+                _ => (),
+            }
+        }
+        Ok(url)
+    }
+}
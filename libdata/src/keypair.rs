@@ -1,47 +1,430 @@
 //! Export [Keypair].
 //! Define utility functions to [generate] and [derive] [Keypair]s.
+//! Back up [Keypair]s as BIP39 mnemonics, see [generate_bip39_with].
+//!
+//! Seeds, [ChainKey]s and mnemonics are wiped from memory when dropped.
+//! Keys are returned as a [PublicKey] and a [ZeroizingKey],
+//! which wipes its [SecretKey] on drop and redacts it in `Debug`,
+//! like the `sk` of a [KeyPair].
+//!
+//! ## Hierarchy
+//! [derive_path] derives a [KeyPair] for every [DerivationPath],
+//! e.g. `m/app/channel/3`, from one root [SecretKey].
+//! Each segment of the path derives a [ChainKey] from the [ChainKey]
+//! of its parent, the root [ChainKey] is derived from the [SecretKey].
+//! The [KeyPair] of a path is seeded by its [ChainKey].
+//!
+//! All derivations are `blake3::derive_key` calls with the contexts
+//! [ROOT_CONTEXT], [CHILD_CONTEXT] and [SEED_CONTEXT].
+//! The contexts are part of the format, changing them changes every derived key.
+//!
+//! Every level is hardened: a [ChainKey] derives the keys of its subtree,
+//! but public keys of children can not be derived from a public key,
+//! as `Ed25519` keys are derived from secret seeds.
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use blake3::derive_key;
-use datacore::SecretKey;
-use bip39_dict::{Entropy, Mnemonics, ENGLISH, seed_from_mnemonics};
+use datacore::{PublicKey, SecretKey, ZeroizingKey};
+use bip39_dict::{
+    seed_from_mnemonics, Dictionary, Entropy, Mnemonics, CHINESE_SIMPLIFIED, CHINESE_TRADITIONAL,
+    ENGLISH, FRENCH, ITALIAN, JAPANESE, KOREAN, SPANISH,
+};
 use getrandom::getrandom;
+use std::fmt;
+use std::str::FromStr;
+use zeroize::{Zeroize, Zeroizing};
 
 pub use datacore::{KeyPair, Seed};
 
-/// Derive a named [KeyPair] from a base [SecretKey].
+/// Context of the root [ChainKey] of a [SecretKey].
+pub const ROOT_CONTEXT: &str = "libdata 2026-10-18 keypair hierarchy root";
+/// Context of a child [ChainKey], derived from its parent and a path segment.
+pub const CHILD_CONTEXT: &str = "libdata 2026-10-18 keypair hierarchy child";
+/// Context of the [Seed] of the [KeyPair] of a [ChainKey].
+pub const SEED_CONTEXT: &str = "libdata 2026-10-18 keypair hierarchy seed";
+
+/// Derive a named key pair from a base [SecretKey].
+///
+/// The `name` is used as the derivation context,
+/// use [derive_path] for a hierarchy of keys.
 #[must_use]
-pub fn derive(key: &SecretKey, name: &str) -> KeyPair {
-    let seed = Seed::new(derive_key(name, key.as_slice()));
-    KeyPair::from_seed(seed)
+pub fn derive(key: &SecretKey, name: &str) -> (PublicKey, ZeroizingKey) {
+    keypair_from_seed(derive_key(name, key.as_slice()))
 }
 
-/// Generate a new [Keypair] with a BIP39 mnemonic.
+/// Derive the key pair at `path` in the hierarchy of a root [SecretKey].
 #[must_use]
-pub fn generate_bip39() -> (KeyPair, String) {
-    let mut seed: [u8; 32] = Default::default();
-    getrandom(&mut seed).expect("Could not get RNG");
+pub fn derive_path(key: &SecretKey, path: &DerivationPath) -> (PublicKey, ZeroizingKey) {
+    ChainKey::root(key).derive(path).keypair()
+}
+
+/// [DerivationPath] names a [KeyPair] in the hierarchy of a root [SecretKey].
+///
+/// Paths are written as `m` followed by `/`-separated segments,
+/// e.g. `m/app/channel/3`. Segments are non-empty strings without `/`,
+/// numbers are segments like any other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DerivationPath {
+    segments: Vec<String>,
+}
+impl DerivationPath {
+    /// Create the root [DerivationPath] `m`.
+    #[must_use]
+    #[inline]
+    pub fn root() -> Self {
+        Self::default()
+    }
+    /// Get the [DerivationPath] of the child named `segment`.
+    pub fn child(&self, segment: &str) -> Result<Self> {
+        check_segment(segment)?;
+        let mut path = self.clone();
+        path.segments.push(segment.to_owned());
+        Ok(path)
+    }
+    /// Get the parent [DerivationPath], `None` for the root.
+    #[must_use]
+    pub fn parent(&self) -> Option<Self> {
+        let (_, parent) = self.segments.split_last()?;
+        Some(Self {
+            segments: parent.to_vec(),
+        })
+    }
+
+    /// Get the segments.
+    #[must_use]
+    #[inline]
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+    /// Get the number of segments, `0` for the root.
+    #[must_use]
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.segments.len()
+    }
+}
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
 
-    let entropy = Entropy::<32>::from_slice(&seed).expect("Could not seed entropy");
+    fn from_str(path: &str) -> Result<Self> {
+        let mut segments = path.split('/');
+        if segments.next() != Some("m") {
+            bail!("Derivation path {:?} does not start with `m`.", path);
+        }
+        let mut path = Self::root();
+        for segment in segments {
+            path = path.child(segment)?;
+        }
+        Ok(path)
+    }
+}
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for segment in &self.segments {
+            write!(f, "/{}", segment)?;
+        }
+        Ok(())
+    }
+}
 
-    let mnemonics = entropy.to_mnemonics::<24, 8>().expect("Could not get mnemonics");
-    let seed = Seed::new(seed_from_mnemonics(
-        &ENGLISH, &mnemonics, b"libdata_keypair_generate_bip39", 2048));
+fn check_segment(segment: &str) -> Result<()> {
+    ensure!(
+        !segment.is_empty() && !segment.contains('/'),
+        "Invalid derivation path segment {:?}.",
+        segment
+    );
+    Ok(())
+}
 
-    let keypair = KeyPair::from_seed(seed);
+/// [ChainKey] is the secret of a node in the hierarchy of a root [SecretKey].
+///
+/// A [ChainKey] derives the [KeyPair]s of all paths below its node,
+/// so it can be handed out to manage a subtree without the root [SecretKey].
+/// It is wiped from memory on drop and redacted in `Debug`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ChainKey {
+    key: [u8; 32],
+}
+impl ChainKey {
+    /// Derive the root [ChainKey] of a [SecretKey].
+    #[must_use]
+    pub fn root(key: &SecretKey) -> Self {
+        Self {
+            key: derive_key(ROOT_CONTEXT, key.as_slice()),
+        }
+    }
+    /// Derive the [ChainKey] of the child named `segment`.
+    pub fn child(&self, segment: &str) -> Result<Self> {
+        check_segment(segment)?;
+        Ok(self.derive_child(segment))
+    }
+    /// Derive the [ChainKey] at `path` relative to this [ChainKey].
+    #[must_use]
+    pub fn derive(&self, path: &DerivationPath) -> Self {
+        path.segments
+            .iter()
+            .fold(self.clone(), |key, segment| key.derive_child(segment))
+    }
+    /// Derive the key pair of this [ChainKey].
+    #[must_use]
+    pub fn keypair(&self) -> (PublicKey, ZeroizingKey) {
+        keypair_from_seed(derive_key(SEED_CONTEXT, &self.key))
+    }
 
-    (keypair, mnemonics.to_string(&ENGLISH))
+    /// Get the [ChainKey] as bytes.
+    #[must_use]
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+    /// Restore a [ChainKey] from bytes.
+    #[must_use]
+    #[inline]
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    fn derive_child(&self, segment: &str) -> Self {
+        let mut hasher = blake3::Hasher::new_derive_key(CHILD_CONTEXT);
+        hasher.update(&self.key);
+        hasher.update(&(segment.len() as u64).to_le_bytes());
+        hasher.update(segment.as_bytes());
+        Self {
+            key: *hasher.finalize().as_bytes(),
+        }
+    }
+}
+impl Drop for ChainKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+impl fmt::Debug for ChainKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChainKey(..)")
+    }
+}
+
+/// Create a key pair from `seed`, wiping it.
+///
+/// [Seed] is `Copy` and not wiped on drop, so the [Seed] is wiped here too.
+fn keypair_from_seed(mut bytes: [u8; 32]) -> (PublicKey, ZeroizingKey) {
+    let mut seed = Seed::new(bytes);
+    bytes.zeroize();
+    let KeyPair { pk, sk } = KeyPair::from_seed(seed);
+    let seed: &mut [u8; 32] = &mut seed;
+    seed.zeroize();
+    (pk, sk)
+}
+
+/// Salt of the seed of a BIP39 mnemonic, followed by the passphrase.
+const BIP39_SALT: &[u8] = b"libdata_keypair_generate_bip39";
+const BIP39_ITERATIONS: u32 = 2048;
+
+/// Number of words of a BIP39 mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WordCount {
+    /// 12 words, 128 bits of entropy.
+    Words12,
+    /// 15 words, 160 bits of entropy.
+    Words15,
+    /// 18 words, 192 bits of entropy.
+    Words18,
+    /// 21 words, 224 bits of entropy.
+    Words21,
+    /// 24 words, 256 bits of entropy.
+    #[default]
+    Words24,
+}
+impl WordCount {
+    /// Get the [WordCount] of a mnemonic with `words` words.
+    pub fn from_words(words: usize) -> Result<Self> {
+        Ok(match words {
+            12 => Self::Words12,
+            15 => Self::Words15,
+            18 => Self::Words18,
+            21 => Self::Words21,
+            24 => Self::Words24,
+            _ => bail!(
+                "Mnemonic has {} words, expected 12, 15, 18, 21 or 24.",
+                words
+            ),
+        })
+    }
+    /// Get the number of words.
+    #[must_use]
+    #[inline]
+    pub fn words(self) -> usize {
+        match self {
+            Self::Words12 => 12,
+            Self::Words15 => 15,
+            Self::Words18 => 18,
+            Self::Words21 => 21,
+            Self::Words24 => 24,
+        }
+    }
 }
 
-/// Recover a [Keypair] from a BIP39 mnemonic.
+/// Wordlist of a BIP39 mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    /// English.
+    #[default]
+    English,
+    /// French.
+    French,
+    /// Italian.
+    Italian,
+    /// Spanish.
+    Spanish,
+    /// Japanese, words are separated by ideographic spaces.
+    Japanese,
+    /// Korean.
+    Korean,
+    /// Simplified Chinese.
+    ChineseSimplified,
+    /// Traditional Chinese.
+    ChineseTraditional,
+}
+impl Language {
+    fn dictionary(self) -> &'static Dictionary {
+        match self {
+            Self::English => &ENGLISH,
+            Self::French => &FRENCH,
+            Self::Italian => &ITALIAN,
+            Self::Spanish => &SPANISH,
+            Self::Japanese => &JAPANESE,
+            Self::Korean => &KOREAN,
+            Self::ChineseSimplified => &CHINESE_SIMPLIFIED,
+            Self::ChineseTraditional => &CHINESE_TRADITIONAL,
+        }
+    }
+    fn separator(self) -> &'static str {
+        match self {
+            Self::Japanese => "\u{3000}",
+            _ => " ",
+        }
+    }
+}
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::English => "English",
+            Self::French => "French",
+            Self::Italian => "Italian",
+            Self::Spanish => "Spanish",
+            Self::Japanese => "Japanese",
+            Self::Korean => "Korean",
+            Self::ChineseSimplified => "Simplified Chinese",
+            Self::ChineseTraditional => "Traditional Chinese",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Generate a new [Keypair] with a 24 word English BIP39 mnemonic.
+///
+/// The secret key and the mnemonic are wiped from memory on drop.
 #[must_use]
-pub fn recover_bip39(phrase: &str) -> Result<KeyPair> {
-    let mnemonics = Mnemonics::<24>::from_string(&ENGLISH, phrase)?;
+pub fn generate_bip39() -> ((PublicKey, ZeroizingKey), Zeroizing<String>) {
+    generate_bip39_with(WordCount::default(), Language::default(), "")
+}
+
+/// Generate a new [Keypair] with a BIP39 mnemonic of `words` words
+/// from the wordlist of `language`, protected by `passphrase`.
+///
+/// The same mnemonic with another `passphrase` recovers another [KeyPair].
+/// An empty `passphrase` recovers the same [KeyPair] as [recover_bip39].
+#[must_use]
+pub fn generate_bip39_with(
+    words: WordCount,
+    language: Language,
+    passphrase: &str,
+) -> ((PublicKey, ZeroizingKey), Zeroizing<String>) {
+    match words {
+        WordCount::Words12 => generate::<16, 12, 4>(language, passphrase),
+        WordCount::Words15 => generate::<20, 15, 5>(language, passphrase),
+        WordCount::Words18 => generate::<24, 18, 6>(language, passphrase),
+        WordCount::Words21 => generate::<28, 21, 7>(language, passphrase),
+        WordCount::Words24 => generate::<32, 24, 8>(language, passphrase),
+    }
+}
+
+fn generate<const N: usize, const W: usize, const CS: usize>(
+    language: Language,
+    passphrase: &str,
+) -> ((PublicKey, ZeroizingKey), Zeroizing<String>) {
+    let mut seed = Zeroizing::new([0u8; N]);
+    getrandom(seed.as_mut_slice()).expect("Could not get RNG");
 
-    let seed = Seed::new(seed_from_mnemonics(
-        &ENGLISH, &mnemonics, b"libdata_keypair_generate_bip39", 2048));
+    let entropy = Entropy::<N>::from_slice(seed.as_slice()).expect("Could not seed entropy");
 
-    let keypair = KeyPair::from_seed(seed);
+    let mnemonics = entropy.to_mnemonics::<W, CS>().expect("Could not get mnemonics");
+    let dictionary = language.dictionary();
+    let keypair = keypair_from_mnemonics(dictionary, &mnemonics, passphrase);
+
+    (keypair, Zeroizing::new(mnemonics.to_string(dictionary)))
+}
+
+/// Recover a [Keypair] from a 24 word English BIP39 mnemonic.
+pub fn recover_bip39(phrase: &str) -> Result<(PublicKey, ZeroizingKey)> {
+    recover_bip39_with(phrase, Language::default(), "")
+}
+
+/// Recover a [Keypair] from a BIP39 mnemonic of any [WordCount]
+/// from the wordlist of `language`, protected by `passphrase`.
+///
+/// Errors name the first word missing from the wordlist.
+/// A checksum mismatch can not be traced to one word,
+/// many substitutions of any word restore a checksum of 4 to 8 bits.
+pub fn recover_bip39_with(
+    phrase: &str,
+    language: Language,
+    passphrase: &str,
+) -> Result<(PublicKey, ZeroizingKey)> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    let count = WordCount::from_words(words.len())?;
+    for (i, word) in words.iter().enumerate() {
+        ensure!(
+            Mnemonics::<1>::from_string(language.dictionary(), word).is_ok(),
+            "Word {} {:?} is not in the {} wordlist.",
+            i + 1,
+            word,
+            language
+        );
+    }
+    let phrase = Zeroizing::new(words.join(language.separator()));
+    match count {
+        WordCount::Words12 => recover::<16, 12, 4>(&phrase, language, passphrase),
+        WordCount::Words15 => recover::<20, 15, 5>(&phrase, language, passphrase),
+        WordCount::Words18 => recover::<24, 18, 6>(&phrase, language, passphrase),
+        WordCount::Words21 => recover::<28, 21, 7>(&phrase, language, passphrase),
+        WordCount::Words24 => recover::<32, 24, 8>(&phrase, language, passphrase),
+    }
+}
+
+fn recover<const N: usize, const W: usize, const CS: usize>(
+    phrase: &str,
+    language: Language,
+    passphrase: &str,
+) -> Result<(PublicKey, ZeroizingKey)> {
+    let dictionary = language.dictionary();
+    let mnemonics = Mnemonics::<W>::from_string(dictionary, phrase)?;
+    ensure!(
+        Entropy::<N>::from_mnemonics::<W, CS>(&mnemonics).is_ok(),
+        "Mnemonic checksum does not match, a word may be mistyped or out of order."
+    );
+
+    Ok(keypair_from_mnemonics(dictionary, &mnemonics, passphrase))
+}
 
-    Ok(keypair)
+fn keypair_from_mnemonics<const W: usize>(
+    dictionary: &Dictionary,
+    mnemonics: &Mnemonics<W>,
+    passphrase: &str,
+) -> (PublicKey, ZeroizingKey) {
+    let password = Zeroizing::new([BIP39_SALT, passphrase.as_bytes()].concat());
+    keypair_from_seed(seed_from_mnemonics(
+        dictionary, mnemonics, &password, BIP39_ITERATIONS))
 }
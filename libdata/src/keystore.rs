@@ -0,0 +1,425 @@
+//! Password encrypted storage of named secret keys.
+//!
+//! A [Keystore] holds named [Secret] keys and named keys derived from them.
+//! Derived keys only store the name of their root and a [DerivationPath],
+//! so only root secrets are persisted.
+//!
+//! [Keystore::encrypt] serializes the keys into a versioned file,
+//! encrypted with `XChaCha20Poly1305` under a key derived from the password
+//! with `Argon2id`. The file layout is:
+//! - `LDKS` magic and a version byte
+//! - [KdfParams], memory in KiB, iterations and parallelism as `u32`s
+//! - a random salt and nonce
+//! - the encrypted keys, authenticated together with the preceding bytes
+//!
+//! Secret keys, the file key and the decrypted keys are wiped from memory
+//! when dropped.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use getrandom::getrandom;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::key::{Public, Secret, ZeroizingKey};
+use crate::keypair::{self, DerivationPath};
+
+const MAGIC: &[u8; 4] = b"LDKS";
+const VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LENGTH + NONCE_LENGTH;
+// refuse files asking for more than 4 GiB of memory,
+// 64 iterations or 64 lanes, opening them would take unbounded time
+const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 64;
+
+const SECRET: u8 = 0;
+const DERIVED: u8 = 1;
+
+/// [KdfParams] set the cost of deriving the file key from the password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+impl KdfParams {
+    /// Create new [KdfParams].
+    #[must_use]
+    #[inline]
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        }
+    }
+
+    /// Get the memory in KiB.
+    #[must_use]
+    #[inline]
+    pub fn memory_kib(&self) -> u32 {
+        self.memory_kib
+    }
+    /// Get the number of iterations.
+    #[must_use]
+    #[inline]
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+    /// Get the degree of parallelism.
+    #[must_use]
+    #[inline]
+    pub fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
+
+    fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+        ensure!(
+            self.memory_kib <= MAX_MEMORY_KIB,
+            "Keystore asks for {} KiB of memory, at most {} KiB are allowed.",
+            self.memory_kib,
+            MAX_MEMORY_KIB
+        );
+        ensure!(
+            self.iterations <= MAX_ITERATIONS,
+            "Keystore asks for {} iterations, at most {} are allowed.",
+            self.iterations,
+            MAX_ITERATIONS
+        );
+        ensure!(
+            self.parallelism <= MAX_PARALLELISM,
+            "Keystore asks for a parallelism of {}, at most {} is allowed.",
+            self.parallelism,
+            MAX_PARALLELISM
+        );
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|err| anyhow!("Invalid key derivation parameters: {}.", err))?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let mut key = Zeroizing::new([0u8; 32]);
+        argon2
+            .hash_password_into(password.as_bytes(), salt, key.as_mut_slice())
+            .map_err(|err| anyhow!("Could not derive keystore key: {}.", err))?;
+        Ok(key)
+    }
+}
+impl Default for KdfParams {
+    /// 19 MiB of memory, 2 iterations and no parallelism.
+    fn default() -> Self {
+        Self::new(19 * 1024, 2, 1)
+    }
+}
+
+#[derive(Clone)]
+enum Entry {
+    Secret(ZeroizingKey),
+    Derived { root: String, path: DerivationPath },
+}
+
+/// [Keystore] is a collection of named keys, stored encrypted with a password.
+#[derive(Clone, Default)]
+pub struct Keystore {
+    entries: BTreeMap<String, Entry>,
+}
+impl Keystore {
+    /// Create a new empty [Keystore].
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a [Secret] key named `name`, replacing a key of the same name.
+    pub fn insert(&mut self, name: &str, key: ZeroizingKey) -> Result<()> {
+        check_name(name)?;
+        ensure!(
+            !self.is_root(name),
+            "Key {:?} is the root of derived keys and can not be replaced.",
+            name
+        );
+        self.entries.insert(name.to_owned(), Entry::Secret(key));
+        Ok(())
+    }
+    /// Insert a key named `name` derived at `path` from the [Secret] key `root`,
+    /// replacing a derived key of the same name.
+    ///
+    /// Only `root` and `path` are stored, see [keypair::derive_path].
+    /// A [Secret] key is never replaced by a derived key.
+    pub fn insert_derived(&mut self, name: &str, root: &str, path: DerivationPath) -> Result<()> {
+        check_name(name)?;
+        ensure!(
+            name != root,
+            "Key {:?} can not be derived from itself.",
+            name
+        );
+        ensure!(
+            matches!(self.entries.get(root), Some(Entry::Secret(_))),
+            "Root key {:?} is not a stored secret key.",
+            root
+        );
+        ensure!(
+            !matches!(self.entries.get(name), Some(Entry::Secret(_))),
+            "Key {:?} is a secret key and can not be replaced by a derived key.",
+            name
+        );
+        let root = root.to_owned();
+        self.entries
+            .insert(name.to_owned(), Entry::Derived { root, path });
+        Ok(())
+    }
+    /// Remove the key named `name`, return `false` if there is none.
+    ///
+    /// Roots of derived keys can only be removed after the derived keys.
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        ensure!(
+            !self.is_root(name),
+            "Key {:?} is the root of derived keys and can not be removed.",
+            name
+        );
+        Ok(self.entries.remove(name).is_some())
+    }
+
+    /// Get the names of the keys, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+    /// Get the number of keys.
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// Check if there are no keys.
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the key pair named `name`, derived keys are derived on access.
+    #[must_use]
+    pub fn keypair(&self, name: &str) -> Option<(Public, ZeroizingKey)> {
+        match self.entries.get(name)? {
+            Entry::Secret(key) => Some((key.public_key(), key.clone())),
+            Entry::Derived { root, path } => match self.entries.get(root)? {
+                Entry::Secret(key) => Some(keypair::derive_path(key.expose(), path)),
+                Entry::Derived { .. } => None,
+            },
+        }
+    }
+    /// Get the [DerivationPath] and the root name of the derived key named `name`.
+    #[must_use]
+    pub fn derivation(&self, name: &str) -> Option<(&str, &DerivationPath)> {
+        match self.entries.get(name)? {
+            Entry::Secret(_) => None,
+            Entry::Derived { root, path } => Some((root.as_str(), path)),
+        }
+    }
+    /// Derive a named key pair from the [Secret] key `root` with [keypair::derive],
+    /// without storing it.
+    pub fn derive(&self, root: &str, name: &str) -> Result<(Public, ZeroizingKey)> {
+        match self.entries.get(root) {
+            Some(Entry::Secret(key)) => Ok(keypair::derive(key.expose(), name)),
+            _ => bail!("Root key {:?} is not a stored secret key.", root),
+        }
+    }
+
+    /// Serialize and encrypt the keys with `password` and default [KdfParams].
+    pub fn encrypt(&self, password: &str) -> Result<Vec<u8>> {
+        self.encrypt_with(password, KdfParams::default())
+    }
+    /// Serialize and encrypt the keys with `password` and `params`.
+    pub fn encrypt_with(&self, password: &str, params: KdfParams) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_LENGTH];
+        let mut nonce = [0u8; NONCE_LENGTH];
+        getrandom(&mut salt).map_err(|err| anyhow!("Could not get RNG: {}.", err))?;
+        getrandom(&mut nonce).map_err(|err| anyhow!("Could not get RNG: {}.", err))?;
+
+        let mut data = Vec::with_capacity(HEADER_LENGTH);
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&params.memory_kib.to_le_bytes());
+        data.extend_from_slice(&params.iterations.to_le_bytes());
+        data.extend_from_slice(&params.parallelism.to_le_bytes());
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+
+        let key = params.derive_key(password, &salt)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+        let payload = Payload {
+            msg: &self.to_bytes()?,
+            aad: &data,
+        };
+        let encrypted = cipher
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .map_err(|_| anyhow!("Could not encrypt keystore."))?;
+        data.extend_from_slice(&encrypted);
+        Ok(data)
+    }
+    /// Decrypt and deserialize a [Keystore] encrypted with `password`.
+    pub fn decrypt(data: &[u8], password: &str) -> Result<Self> {
+        ensure!(
+            data.len() >= HEADER_LENGTH && data.starts_with(MAGIC),
+            "Not a keystore."
+        );
+        let (header, encrypted) = data.split_at(HEADER_LENGTH);
+        let rdr = &mut &header[MAGIC.len()..];
+        let [version] = read_array(rdr)?;
+        ensure!(
+            version == VERSION,
+            "Keystore version {} is not supported.",
+            version
+        );
+        let params = KdfParams::new(
+            u32::from_le_bytes(read_array(rdr)?),
+            u32::from_le_bytes(read_array(rdr)?),
+            u32::from_le_bytes(read_array(rdr)?),
+        );
+        let salt: [u8; SALT_LENGTH] = read_array(rdr)?;
+        let nonce: [u8; NONCE_LENGTH] = read_array(rdr)?;
+
+        let key = params.derive_key(password, &salt)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+        let payload = Payload {
+            msg: encrypted,
+            aad: header,
+        };
+        let decrypted = cipher
+            .decrypt(XNonce::from_slice(&nonce), payload)
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("Could not decrypt keystore, wrong password or corrupted."))?;
+        Self::from_bytes(&decrypted)
+    }
+
+    /// Encrypt the keys with `password` and write them to the file at `path`.
+    ///
+    /// The file is replaced atomically, it is synced before it replaces
+    /// the old file and the directory is synced after.
+    pub fn save(&self, path: &Path, password: &str) -> Result<()> {
+        let data = self.encrypt(password)?;
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temporary, path)?;
+        sync_dir(path.parent().unwrap_or(Path::new("")))?;
+        Ok(())
+    }
+    /// Read and decrypt a [Keystore] from the file at `path`.
+    pub fn load(path: &Path, password: &str) -> Result<Self> {
+        Self::decrypt(&fs::read(path)?, password)
+    }
+
+    fn is_root(&self, name: &str) -> bool {
+        self.entries
+            .values()
+            .any(|entry| matches!(entry, Entry::Derived { root, .. } if root == name))
+    }
+
+    fn to_bytes(&self) -> Result<Zeroizing<Vec<u8>>> {
+        // sized up front, so no copy of the keys is left behind by a reallocation
+        let length = self
+            .entries
+            .iter()
+            .map(|(name, entry)| {
+                let entry = match entry {
+                    Entry::Secret(_) => 4 + Secret::BYTES,
+                    Entry::Derived { root, path } => 8 + root.len() + path.to_string().len(),
+                };
+                4 + name.len() + 1 + entry
+            })
+            .sum::<usize>();
+        let mut data = Zeroizing::new(Vec::with_capacity(4 + length));
+        data.extend_from_slice(&u32::try_from(self.entries.len())?.to_le_bytes());
+        for (name, entry) in &self.entries {
+            write_bytes(&mut data, name.as_bytes())?;
+            match entry {
+                Entry::Secret(key) => {
+                    data.push(SECRET);
+                    write_bytes(&mut data, key.expose().as_slice())?;
+                }
+                Entry::Derived { root, path } => {
+                    data.push(DERIVED);
+                    write_bytes(&mut data, root.as_bytes())?;
+                    write_bytes(&mut data, path.to_string().as_bytes())?;
+                }
+            }
+        }
+        Ok(data)
+    }
+    fn from_bytes(mut data: &[u8]) -> Result<Self> {
+        let rdr = &mut data;
+        let count = u32::from_le_bytes(read_array(rdr)?);
+        let mut keystore = Self::new();
+        let mut derived = vec![];
+        for _ in 0..count {
+            let name = read_string(rdr)?;
+            match read_array::<1>(rdr)? {
+                [SECRET] => {
+                    let key = Secret::from_slice(&Zeroizing::new(read_bytes(rdr)?))?;
+                    keystore.insert(&name, ZeroizingKey::new(key))?;
+                }
+                [DERIVED] => {
+                    let root = read_string(rdr)?;
+                    let path = read_string(rdr)?.parse()?;
+                    derived.push((name, root, path));
+                }
+                [tag] => bail!("Invalid keystore entry {}.", tag),
+            }
+        }
+        ensure!(rdr.is_empty(), "Trailing bytes after keystore entries.");
+        for (name, root, path) in derived {
+            keystore.insert_derived(&name, &root, path)?;
+        }
+        Ok(keystore)
+    }
+}
+
+/// Sync the directory `dir`, so a renamed file is kept after a crash.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    let dir = match dir.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => dir,
+    };
+    fs::File::open(dir)?.sync_all()
+}
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<()> {
+    ensure!(!name.is_empty(), "Key name can not be empty.");
+    Ok(())
+}
+
+fn write_bytes(data: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+    data.extend_from_slice(&u32::try_from(bytes.len())?.to_le_bytes());
+    data.extend_from_slice(bytes);
+    Ok(())
+}
+fn read_array<const N: usize>(rdr: &mut &[u8]) -> Result<[u8; N]> {
+    let mut array = [0u8; N];
+    rdr.read_exact(&mut array)?;
+    Ok(array)
+}
+fn read_bytes(rdr: &mut &[u8]) -> Result<Vec<u8>> {
+    let length = u32::from_le_bytes(read_array(rdr)?) as usize;
+    ensure!(rdr.len() >= length, "Keystore entry is truncated.");
+    let (bytes, rest) = rdr.split_at(length);
+    *rdr = rest;
+    Ok(bytes.to_vec())
+}
+fn read_string(rdr: &mut &[u8]) -> Result<String> {
+    Ok(String::from_utf8(read_bytes(rdr)?)?)
+}
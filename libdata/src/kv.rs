@@ -0,0 +1,483 @@
+//! Persistent key-value store with [Core] blocks as storage.
+//!
+//! [KeyValue] is an append-only B-tree:
+//! every `put` or `del` appends one block to the [Core] carrying
+//! the key, the value and the copies of the tree [Node]s it changed.
+//! Unchanged [Node]s are referenced by the block and position they were
+//! written at, the root is the last [Node] of the last block.
+//! Deleted keys point to a tombstone block without a value.
+//! Older versions stay readable with [KeyValue::checkout].
+//!
+//! Being a plain [Core], [KeyValue] replicates with `CoreReplica`,
+//! a replica with a read-only [Core] can `get`, `range` and `prefix`.
+
+use anyhow::{bail, ensure, Result};
+use std::io::Read;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{Core, IndexAccess};
+
+/// Maximum number of keys in a tree [Node].
+pub const MAX_KEYS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pointer {
+    seq: u32,
+    offset: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Node {
+    keys: Vec<(Vec<u8>, u32)>,
+    children: Vec<Pointer>,
+}
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+    fn search(&self, key: &[u8]) -> std::result::Result<usize, usize> {
+        self.keys.binary_search_by(|(k, _)| k.as_slice().cmp(key))
+    }
+    fn split(mut self) -> (Self, (Vec<u8>, u32), Self) {
+        let middle = self.keys.len() / 2;
+        let keys = self.keys.split_off(middle + 1);
+        let median = self.keys.pop().unwrap();
+        let children = if self.is_leaf() {
+            vec![]
+        } else {
+            self.children.split_off(middle + 1)
+        };
+        (self, median, Self { keys, children })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    key: Vec<u8>,
+    value: Option<Vec<u8>>,
+    nodes: Vec<Node>,
+}
+impl Record {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = vec![];
+        data.push(u8::from(self.value.is_some()));
+        write_bytes(&mut data, &self.key)?;
+        if let Some(value) = &self.value {
+            write_bytes(&mut data, value)?;
+        }
+        data.extend_from_slice(&u16::try_from(self.nodes.len())?.to_le_bytes());
+        for node in &self.nodes {
+            data.extend_from_slice(&u16::try_from(node.keys.len())?.to_le_bytes());
+            for (key, seq) in &node.keys {
+                write_bytes(&mut data, key)?;
+                data.extend_from_slice(&seq.to_le_bytes());
+            }
+            data.extend_from_slice(&u16::try_from(node.children.len())?.to_le_bytes());
+            for child in &node.children {
+                data.extend_from_slice(&child.seq.to_le_bytes());
+                data.extend_from_slice(&child.offset.to_le_bytes());
+            }
+        }
+        Ok(data)
+    }
+    fn from_bytes(mut data: &[u8]) -> Result<Self> {
+        let rdr = &mut data;
+        let value = match read_array::<1>(rdr)? {
+            [0] => false,
+            [1] => true,
+            [flag] => bail!("Invalid key-value record flag {}.", flag),
+        };
+        let key = read_bytes(rdr)?;
+        let value = if value { Some(read_bytes(rdr)?) } else { None };
+        let count = u16::from_le_bytes(read_array(rdr)?);
+        let mut nodes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let count = u16::from_le_bytes(read_array(rdr)?);
+            let mut keys = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let key = read_bytes(rdr)?;
+                keys.push((key, u32::from_le_bytes(read_array(rdr)?)));
+            }
+            let count = u16::from_le_bytes(read_array(rdr)?);
+            let mut children = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let seq = u32::from_le_bytes(read_array(rdr)?);
+                let offset = u16::from_le_bytes(read_array(rdr)?);
+                children.push(Pointer { seq, offset });
+            }
+            ensure!(
+                children.is_empty() || children.len() == keys.len() + 1,
+                "Invalid key-value node."
+            );
+            nodes.push(Node { keys, children });
+        }
+        ensure!(rdr.is_empty(), "Trailing bytes after key-value record.");
+        Ok(Self { key, value, nodes })
+    }
+}
+
+fn write_bytes(data: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+    data.extend_from_slice(&u32::try_from(bytes.len())?.to_le_bytes());
+    data.extend_from_slice(bytes);
+    Ok(())
+}
+fn read_array<const N: usize>(rdr: &mut &[u8]) -> Result<[u8; N]> {
+    let mut array = [0u8; N];
+    rdr.read_exact(&mut array)?;
+    Ok(array)
+}
+fn read_bytes(rdr: &mut &[u8]) -> Result<Vec<u8>> {
+    let length = u32::from_le_bytes(read_array(rdr)?) as usize;
+    ensure!(rdr.len() >= length, "Key-value record is truncated.");
+    let (bytes, rest) = rdr.split_at(length);
+    *rdr = rest;
+    Ok(bytes.to_vec())
+}
+
+enum Frame {
+    Child(Pointer),
+    Key(Vec<u8>, u32),
+}
+
+/// [KeyValue] is an ordered key-value store persisted in a [Core].
+pub struct KeyValue<T> {
+    core: Arc<RwLock<Core<T>>>,
+    version: Option<u32>,
+}
+impl<T> KeyValue<T>
+where
+    T: IndexAccess + Send,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    /// Create a new [KeyValue] over a [Core],
+    /// an empty [Core] is an empty store.
+    #[must_use]
+    #[inline]
+    pub fn new(core: Arc<RwLock<Core<T>>>) -> Self {
+        Self {
+            core,
+            version: None,
+        }
+    }
+
+    /// Create a read-only [KeyValue] of the store
+    /// as it was when its [Core] had `version` blocks.
+    #[must_use]
+    #[inline]
+    pub fn checkout(&self, version: u32) -> Self {
+        Self {
+            core: Arc::clone(&self.core),
+            version: Some(version),
+        }
+    }
+
+    /// Access the underlying [Core].
+    #[must_use]
+    #[inline]
+    pub fn core(&self) -> &Arc<RwLock<Core<T>>> {
+        &self.core
+    }
+
+    /// Check if this is a read-only [KeyValue::checkout].
+    #[must_use]
+    #[inline]
+    pub fn is_checkout(&self) -> bool {
+        self.version.is_some()
+    }
+    /// Get the version of the store, the length of its [Core].
+    pub async fn version(&self) -> u32 {
+        let core = self.core.read().await;
+        self.version.unwrap_or_else(|| core.len())
+    }
+
+    /// Get the value of `key`.
+    pub async fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let core = self.core.read().await;
+        let length = self.length(&core)?;
+        match Self::lookup(&core, length, key).await? {
+            Some(seq) => Ok(Self::record(&core, seq).await?.value),
+            None => Ok(None),
+        }
+    }
+
+    /// Put `value` under `key`.
+    pub async fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        ensure!(self.version.is_none(), "Cannot write to a checkout.");
+        let mut core = self.core.write().await;
+        Self::write(&mut core, key, Some(value)).await
+    }
+
+    /// Delete `key`, return `false` if there was nothing to delete.
+    pub async fn del(&self, key: &[u8]) -> Result<bool> {
+        ensure!(self.version.is_none(), "Cannot write to a checkout.");
+        let mut core = self.core.write().await;
+        let length = core.len();
+        let seq = match Self::lookup(&core, length, key).await? {
+            Some(seq) => seq,
+            None => return Ok(false),
+        };
+        if Self::record(&core, seq).await?.value.is_none() {
+            return Ok(false);
+        }
+        Self::write(&mut core, key, None).await?;
+        Ok(true)
+    }
+
+    /// Get all key-value pairs with keys in `range`, ordered by key.
+    pub async fn range(&self, range: impl RangeBounds<Vec<u8>>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+        let before_start = |key: &[u8]| match &start {
+            Bound::Included(start) => key < start.as_slice(),
+            Bound::Excluded(start) => key <= start.as_slice(),
+            Bound::Unbounded => false,
+        };
+        let after_end = |key: &[u8]| match &end {
+            Bound::Included(end) => key > end.as_slice(),
+            Bound::Excluded(end) => key >= end.as_slice(),
+            Bound::Unbounded => false,
+        };
+
+        let core = self.core.read().await;
+        let length = self.length(&core)?;
+        let mut entries = vec![];
+        let mut stack = vec![];
+        if let Some(root) = Self::root(&core, length).await? {
+            Self::expand(&mut stack, root, &before_start, &after_end);
+        }
+        while let Some(frame) = stack.pop() {
+            match frame {
+                Frame::Child(pointer) => {
+                    let node = Self::node(&core, pointer).await?;
+                    Self::expand(&mut stack, node, &before_start, &after_end);
+                }
+                Frame::Key(key, _) if after_end(&key) => break,
+                Frame::Key(key, _) if before_start(&key) => continue,
+                Frame::Key(key, seq) => {
+                    if let Some(value) = Self::record(&core, seq).await?.value {
+                        entries.push((key, value));
+                    }
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Get all key-value pairs with keys starting with `prefix`, ordered by key.
+    pub async fn prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut end = prefix.to_vec();
+        while let Some(last) = end.pop() {
+            if last < u8::MAX {
+                end.push(last + 1);
+                return self.range(prefix.to_vec()..end).await;
+            }
+        }
+        self.range(prefix.to_vec()..).await
+    }
+
+    /// Push the keys and children of `node` in reverse order,
+    /// skipping children outside of the range.
+    fn expand(
+        stack: &mut Vec<Frame>,
+        node: Node,
+        before_start: &impl Fn(&[u8]) -> bool,
+        after_end: &impl Fn(&[u8]) -> bool,
+    ) {
+        let Node { keys, children } = node;
+        for i in (0..=keys.len()).rev() {
+            if let Some(child) = children.get(i) {
+                let below = keys.get(i).is_some_and(|(key, _)| before_start(key));
+                let above = i > 0 && after_end(&keys[i - 1].0);
+                if !below && !above {
+                    stack.push(Frame::Child(*child));
+                }
+            }
+            if i > 0 {
+                let (key, seq) = keys[i - 1].clone();
+                stack.push(Frame::Key(key, seq));
+            }
+        }
+    }
+
+    /// Find the block `key` was last written at.
+    async fn lookup(core: &Core<T>, length: u32, key: &[u8]) -> Result<Option<u32>> {
+        let mut node = match Self::root(core, length).await? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        loop {
+            match node.search(key) {
+                Ok(i) => return Ok(Some(node.keys[i].1)),
+                Err(_) if node.is_leaf() => return Ok(None),
+                Err(i) => node = Self::node(core, node.children[i]).await?,
+            }
+        }
+    }
+
+    async fn write(core: &mut Core<T>, key: &[u8], value: Option<&[u8]>) -> Result<()> {
+        let seq = core.len();
+        let mut nodes = vec![];
+        let mut path: Vec<(Node, usize)> = vec![];
+        let mut current = Self::root(core, seq).await?.unwrap_or_default();
+        loop {
+            match current.search(key) {
+                Ok(i) => {
+                    current.keys[i].1 = seq;
+                    break;
+                }
+                Err(i) if current.is_leaf() => {
+                    current.keys.insert(i, (key.to_vec(), seq));
+                    break;
+                }
+                Err(i) => {
+                    let child = Self::node(core, current.children[i]).await?;
+                    path.push((current, i));
+                    current = child;
+                }
+            }
+        }
+
+        // copy the changed path bottom-up, splitting full nodes
+        loop {
+            let (left, split) = if current.keys.len() > MAX_KEYS {
+                let (left, median, right) = current.split();
+                (left, Some((median, right)))
+            } else {
+                (current, None)
+            };
+            let left = Self::push(&mut nodes, left, seq)?;
+            let split = match split {
+                Some((median, right)) => Some((median, Self::push(&mut nodes, right, seq)?)),
+                None => None,
+            };
+            match path.pop() {
+                Some((mut parent, i)) => {
+                    parent.children[i] = left;
+                    if let Some((median, right)) = split {
+                        parent.keys.insert(i, median);
+                        parent.children.insert(i + 1, right);
+                    }
+                    current = parent;
+                }
+                None => {
+                    if let Some((median, right)) = split {
+                        nodes.push(Node {
+                            keys: vec![median],
+                            children: vec![left, right],
+                        });
+                    }
+                    break;
+                }
+            }
+        }
+
+        let record = Record {
+            key: key.to_vec(),
+            value: value.map(<[u8]>::to_vec),
+            nodes,
+        };
+        core.append(&record.to_bytes()?, None).await
+    }
+
+    fn push(nodes: &mut Vec<Node>, node: Node, seq: u32) -> Result<Pointer> {
+        let offset = u16::try_from(nodes.len())?;
+        nodes.push(node);
+        Ok(Pointer { seq, offset })
+    }
+
+    async fn record(core: &Core<T>, seq: u32) -> Result<Record> {
+        match core.get(seq).await? {
+            Some((data, _)) => Record::from_bytes(&data),
+            None => bail!("Key-value block {} is not available.", seq),
+        }
+    }
+    async fn node(core: &Core<T>, pointer: Pointer) -> Result<Node> {
+        let mut record = Self::record(core, pointer.seq).await?;
+        let offset = pointer.offset as usize;
+        ensure!(
+            offset < record.nodes.len(),
+            "Key-value block {} has no node {}.",
+            pointer.seq,
+            offset
+        );
+        Ok(record.nodes.swap_remove(offset))
+    }
+    /// Get the root [Node] of the store with `length` blocks,
+    /// stored in the last block besides key rotations.
+    async fn root(core: &Core<T>, length: u32) -> Result<Option<Node>> {
+        for seq in (0..length).rev() {
+            match core.get(seq).await? {
+                Some((_, signature)) if signature.is_key_rotation() => continue,
+                Some((data, _)) => return Ok(Record::from_bytes(&data)?.nodes.into_iter().last()),
+                None => bail!("Key-value block {} is not available.", seq),
+            }
+        }
+        Ok(None)
+    }
+
+    fn length(&self, core: &Core<T>) -> Result<u32> {
+        match self.version {
+            Some(version) => {
+                ensure!(
+                    version <= core.len(),
+                    "Version {} is newer than the store.",
+                    version
+                );
+                Ok(version)
+            }
+            None => Ok(core.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_to_bytes_from_bytes() -> Result<()> {
+        let record = Record {
+            key: b"key".to_vec(),
+            value: Some(b"value".to_vec()),
+            nodes: vec![
+                Node {
+                    keys: vec![(b"a".to_vec(), 0), (b"key".to_vec(), 3)],
+                    children: vec![],
+                },
+                Node {
+                    keys: vec![(b"b".to_vec(), 1)],
+                    children: vec![Pointer { seq: 3, offset: 0 }, Pointer { seq: 2, offset: 1 }],
+                },
+            ],
+        };
+        let bytes = record.to_bytes()?;
+        assert_eq!(Record::from_bytes(&bytes)?, record);
+        assert!(Record::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let tombstone = Record {
+            key: b"key".to_vec(),
+            value: None,
+            nodes: vec![],
+        };
+        assert_eq!(Record::from_bytes(&tombstone.to_bytes()?)?, tombstone);
+        Ok(())
+    }
+
+    #[test]
+    fn node_split() {
+        let keys = (0..=MAX_KEYS as u32)
+            .map(|i| (vec![i as u8], i))
+            .collect::<Vec<_>>();
+        let children = (0..=MAX_KEYS as u32 + 1)
+            .map(|seq| Pointer { seq, offset: 0 })
+            .collect::<Vec<_>>();
+        let (left, median, right) = Node { keys, children }.split();
+        assert_eq!(left.keys.len() + right.keys.len() + 1, MAX_KEYS + 1);
+        assert_eq!(left.children.len(), left.keys.len() + 1);
+        assert_eq!(right.children.len(), right.keys.len() + 1);
+        assert!(left.keys.last().unwrap().0 < median.0);
+        assert!(median.0 < right.keys[0].0);
+    }
+}
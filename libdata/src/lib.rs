@@ -5,19 +5,36 @@
 #![cfg_attr(test, deny(warnings))]
 
 //! Libdata re-exports public interface from [datacore],
-//! defines async [CoreIterator],
+//! defines async [CoreIterator] and read-only [CoreSnapshot],
 //! defines interface for managing collection of [Cores],
+//! combines several writer [Core]s into a [multi] writer log,
+//! stores an ordered [kv] index in a [Core],
+//! shares directory trees as a [drive],
+//! encodes [typed] records into blocks,
+//! keeps secret keys in a password encrypted [keystore],
+//! encodes [key]s as checksummed strings and `libdata://` URLs,
 //! and specifies [replication] over [protocol].
 
+mod bech32;
 mod cores;
 mod iter;
+mod snapshot;
 
+pub mod drive;
 pub mod key;
 pub mod keypair;
+pub mod keystore;
+pub mod kv;
+pub mod multi;
 pub mod replication;
+pub mod typed;
 
-pub use datacore::{Core, IndexAccess, Signature, MAX_CORE_LENGTH};
+pub use datacore::{
+    verify_proof, ClearedError, Core, ForkError, ForkProof, IndexAccess, KeyRotation, Proof,
+    Signature, SignatureScheme, Signer, Snapshot, Stats, Verifier, VerifyError, MAX_CORE_LENGTH,
+};
 
 pub use cores::Cores;
 pub use iter::CoreIterator;
 pub use keypair::KeyPair;
+pub use snapshot::CoreSnapshot;
@@ -0,0 +1,302 @@
+//! Combine several single-writer [Core]s into one causally ordered log.
+//!
+//! Every writer (usually one per device) appends to its own [Core].
+//! Each block starts with a [Clock], the lengths of the other writers'
+//! [Core]s seen at the time of the append.
+//! [MultiWriter::linearize] orders all blocks so that every block
+//! comes after the blocks it has seen, concurrent blocks are ordered
+//! by the [key::Public] of their writer.
+//! Every replica with the same blocks computes the same order.
+
+use anyhow::{anyhow, ensure, Result};
+use futures_lite::future::FutureExt;
+use futures_lite::stream::Stream;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{key, ClearedError, Core, Cores, IndexAccess};
+
+type PublicKeyBytes = [u8; 32];
+type ReadTask = Pin<Box<dyn Future<Output = Result<Entry>>>>;
+type Position<T> = (key::Public, Arc<RwLock<Core<T>>>, u32);
+
+const CLOCK_ENTRY_LENGTH: usize = 32 + 4;
+
+/// [Clock] maps writers to the number of their blocks seen.
+///
+/// Writers with no blocks seen are not stored.
+pub type Clock = BTreeMap<PublicKeyBytes, u32>;
+
+/// Encode `data` with its causal [Clock] into a block.
+pub fn encode(clock: &Clock, data: &[u8]) -> Result<Vec<u8>> {
+    let count = u16::try_from(clock.len())?;
+    let mut block = Vec::with_capacity(2 + clock.len() * CLOCK_ENTRY_LENGTH + data.len());
+    block.extend_from_slice(&count.to_le_bytes());
+    for (public, length) in clock {
+        block.extend_from_slice(public);
+        block.extend_from_slice(&length.to_le_bytes());
+    }
+    block.extend_from_slice(data);
+    Ok(block)
+}
+/// Decode a block into its causal [Clock] and data.
+pub fn decode(block: &[u8]) -> Result<(Clock, &[u8])> {
+    ensure!(block.len() >= 2, "Block is missing a clock.");
+    let count = u16::from_le_bytes([block[0], block[1]]) as usize;
+    let end = 2 + count * CLOCK_ENTRY_LENGTH;
+    ensure!(block.len() >= end, "Block clock is truncated.");
+
+    let mut clock = Clock::new();
+    for entry in block[2..end].chunks_exact(CLOCK_ENTRY_LENGTH) {
+        let public: PublicKeyBytes = entry[..32].try_into()?;
+        let length = u32::from_le_bytes(entry[32..].try_into()?);
+        ensure!(length > 0, "Block clock has an empty entry.");
+        ensure!(
+            clock.insert(public, length).is_none(),
+            "Block clock has a duplicate entry."
+        );
+    }
+    Ok((clock, &block[end..]))
+}
+
+/// [Entry] is a block of the merged log of a [MultiWriter].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    writer: key::Public,
+    index: u32,
+    data: Vec<u8>,
+}
+impl Entry {
+    /// Get the [key::Public] of the writer.
+    #[must_use]
+    #[inline]
+    pub fn writer(&self) -> &key::Public {
+        &self.writer
+    }
+    /// Get the index of the block in the writer's [Core].
+    #[must_use]
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    /// Get the data, without the [Clock].
+    #[must_use]
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// [MultiWriter] is a causally ordered log written by several writers,
+/// each writer appends to its own [Core] tracked in [Cores].
+pub struct MultiWriter<T> {
+    cores: Cores<T>,
+    local: key::Public,
+    // `None` for key rotations, they hold no entries
+    clocks: Mutex<BTreeMap<PublicKeyBytes, Vec<Option<Clock>>>>,
+}
+impl<T> MultiWriter<T>
+where
+    T: IndexAccess + Send + 'static,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    /// Create a new [MultiWriter] appending to the `local` writer's [Core].
+    pub fn new(cores: Cores<T>, local: key::Public) -> Result<Self> {
+        ensure!(
+            cores.get_by_public(&local).is_some(),
+            "Local writer is missing from Cores."
+        );
+        Ok(Self {
+            cores,
+            local,
+            clocks: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Get the [key::Public] of the local writer.
+    #[must_use]
+    #[inline]
+    pub fn local(&self) -> &key::Public {
+        &self.local
+    }
+    /// Access the writers' [Cores], e.g. for replication.
+    #[must_use]
+    #[inline]
+    pub fn cores(&self) -> &Cores<T> {
+        &self.cores
+    }
+    /// Add a writer [Core].
+    #[inline]
+    pub fn add_writer(&mut self, core: Core<T>) {
+        self.cores.insert(core);
+    }
+
+    /// Append `data` to the local writer's [Core],
+    /// after every block currently in the other writers' [Core]s.
+    pub async fn append(&self, data: &[u8]) -> Result<()> {
+        let local = self
+            .cores
+            .get_by_public(&self.local)
+            .ok_or_else(|| anyhow!("Local writer is missing from Cores."))?;
+        let mut clock = Clock::new();
+        for (public, core) in self.cores.entries() {
+            if public == self.local {
+                continue;
+            }
+            let length = core.read().await.len();
+            if length > 0 {
+                clock.insert(public.as_slice().try_into()?, length);
+            }
+        }
+        let block = encode(&clock, data)?;
+        let mut core = local.write().await;
+        core.append(&block, None).await
+    }
+
+    /// Compute the deterministic order of all blocks.
+    ///
+    /// Blocks which have seen blocks not yet available,
+    /// are left out together with their successors.
+    ///
+    /// The [Clock]s are decoded once and kept,
+    /// later calls only read the blocks appended since.
+    /// Cleared blocks not read yet are left out until they are restored.
+    /// Blocks without a valid [Clock] stop their writer the same way,
+    /// so one bad writer does not stop the merged log.
+    /// Key rotations of the writers' [Core]s are left out.
+    pub async fn linearize(&self) -> Result<Vec<(key::Public, u32)>> {
+        let mut clocks = self.clocks.lock().await;
+        for (public, core) in self.cores.entries() {
+            let core = core.read().await;
+            let writer = clocks.entry(public.as_slice().try_into()?).or_default();
+            writer.truncate(core.len() as usize);
+            for index in writer.len() as u32..core.len() {
+                let block = match core.get(index).await {
+                    Ok(Some((_, signature))) if signature.is_key_rotation() => {
+                        writer.push(None);
+                        continue;
+                    }
+                    Ok(Some((block, _))) => block,
+                    Ok(None) => break,
+                    Err(err) if err.is::<ClearedError>() => break,
+                    Err(err) => return Err(err),
+                };
+                let Ok((clock, _)) = decode(&block) else {
+                    break;
+                };
+                writer.push(Some(clock));
+            }
+        }
+
+        let mut next: BTreeMap<PublicKeyBytes, u32> =
+            clocks.keys().map(|public| (*public, 0)).collect();
+        let mut order = vec![];
+        loop {
+            let ready = next.iter().find_map(|(public, index)| {
+                let clock = clocks[public].get(*index as usize)?;
+                // a key rotation has seen nothing
+                clock
+                    .iter()
+                    .flatten()
+                    .all(|(dependency, length)| {
+                        next.get(dependency).is_some_and(|seen| seen >= length)
+                    })
+                    .then_some(*public)
+            });
+            let Some(public) = ready else {
+                break;
+            };
+            let index = next.entry(public).or_default();
+            if clocks[&public][*index as usize].is_some() {
+                order.push((key::Public::from_slice(&public)?, *index));
+            }
+            *index += 1;
+        }
+        Ok(order)
+    }
+
+    /// Create a [MultiIterator] over the merged log.
+    pub async fn iter(&self) -> Result<MultiIterator<T>> {
+        let positions = self
+            .linearize()
+            .await?
+            .into_iter()
+            .map(|(public, index)| {
+                let core = self
+                    .cores
+                    .get_by_public(&public)
+                    .ok_or_else(|| anyhow!("Writer is missing from Cores."))?;
+                Ok((public, core, index))
+            })
+            .collect::<Result<_>>()?;
+        Ok(MultiIterator::new(positions))
+    }
+}
+
+/// Async [Stream] iterator over the merged log of a [MultiWriter].
+///
+/// Failed reads, e.g. of cleared blocks, are yielded as errors.
+pub struct MultiIterator<T> {
+    positions: VecDeque<Position<T>>,
+    task: Option<ReadTask>,
+}
+impl<T> MultiIterator<T>
+where
+    T: IndexAccess + Send + 'static,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    fn new(positions: VecDeque<Position<T>>) -> Self {
+        Self {
+            positions,
+            task: None,
+        }
+    }
+
+    #[inline]
+    fn create_read_task(writer: key::Public, core: Arc<RwLock<Core<T>>>, index: u32) -> ReadTask {
+        async move {
+            let result;
+            {
+                let core = core.read().await;
+                result = core.get(index).await;
+            }
+            let (block, _) = result?.ok_or_else(|| anyhow!("Missing block {}.", index))?;
+            let (_, data) = decode(&block)?;
+            Ok(Entry {
+                writer,
+                index,
+                data: data.to_vec(),
+            })
+        }
+        .boxed()
+    }
+}
+impl<T> Stream for MultiIterator<T>
+where
+    T: IndexAccess + Send + 'static,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    type Item = Result<Entry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.task.is_none() {
+            match this.positions.pop_front() {
+                Some((writer, core, index)) => {
+                    this.task = Some(Self::create_read_task(writer, core, index));
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+        let task = this.task.as_mut().unwrap();
+        if let Poll::Ready(entry) = Pin::new(task).poll(cx) {
+            this.task = None;
+            return Poll::Ready(Some(entry));
+        }
+        Poll::Pending
+    }
+}
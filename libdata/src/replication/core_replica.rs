@@ -1,25 +1,54 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use async_trait::async_trait;
+use datacore::SignedHead;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
 use crate::replication::{Data, DataOrRequest, ReplicaTrait, Request};
-use crate::{Signature, Core, IndexAccess, MAX_CORE_LENGTH};
+use crate::{
+    ClearedError, ForkError, Signature, SignatureScheme, Core, IndexAccess, VerifyError,
+    MAX_CORE_LENGTH,
+};
 
 /// CoreReplica describes eager, full, and sequential synchronization logic
 /// for replicating [Core] over [Link].
+///
+/// When a remote block fails verification with a [VerifyError],
+/// or the remote has the same length, the [SignedHead] of the remote
+/// at the latest checkpoint is requested and compared with [Core::check_fork].
+/// Other errors, e.g. of the storage, fail the [Link] right away.
+/// A detected fork fails the [Link] with a [ForkError],
+/// a forked [Core] is not replicated anymore.
+///
+/// Cleared blocks marked with [Core::refetch] are requested again on open.
+/// A remote that cleared a requested block answers with a [Request]
+/// for the same block, it is skipped for the next marked block or the length.
+///
+/// The signed schema of the [Core] is sent with the first block,
+/// a replica expecting a different schema fails the [Link].
+///
+/// Blocks are verified with the key writing the [Core] at their index,
+/// replicated key rotations of [Core::rotate] hand it over to the next key.
 pub struct CoreReplica<T> {
-    core: Arc<Mutex<Core<T>>>,
+    core: Arc<RwLock<Core<T>>>,
     remote_index: Option<u32>,
+    refetch: Option<u32>,
+    schema_sent: bool,
+    head_requested: bool,
+    conflict: Option<anyhow::Error>,
 }
 
 impl<T> CoreReplica<T> {
     /// Create a new [CoreReplica].
     #[must_use]
-    pub fn new(core: Arc<Mutex<Core<T>>>) -> Self {
+    pub fn new(core: Arc<RwLock<Core<T>>>) -> Self {
         Self {
             core,
             remote_index: None,
+            refetch: None,
+            schema_sent: false,
+            head_requested: false,
+            conflict: None,
         }
     }
 
@@ -32,6 +61,7 @@ impl<T> CoreReplica<T> {
         self.remote_index = Some(index);
     }
 }
+
 #[async_trait]
 impl<T> ReplicaTrait for CoreReplica<T>
 where
@@ -39,60 +69,161 @@ where
     <T as IndexAccess>::Error: Into<anyhow::Error>,
 {
     async fn on_open(&mut self) -> Result<Option<Request>> {
-        let core = self.core.lock().await;
-        let request = Request { index: core.len() };
+        let core = self.core.read().await;
+        ensure_not_forked(&core)?;
+        self.refetch = core.next_refetch();
+        let index = self.refetch.unwrap_or_else(|| core.len());
+        let request = Request {
+            index,
+            signed_head: None,
+        };
         Ok(Some(request))
     }
     async fn on_request(&mut self, request: Request) -> Result<Option<DataOrRequest>> {
         self.update_remote_index(request.index);
 
-        let mut core = self.core.lock().await;
-        let data = core.get(request.index).await?;
+        let core = self.core.read().await;
+        ensure_not_forked(&core)?;
+        if request.signed_head == Some(true) {
+            let head = match request.index <= core.len() {
+                true => core.signed_head_at(request.index).await?,
+                false => None,
+            };
+            let head = match head {
+                Some(head) => head.to_bytes()?,
+                None => vec![],
+            };
+            return Ok(Some(DataOrRequest::Data(Data {
+                index: request.index,
+                signed_head: Some(head),
+                ..Data::default()
+            })));
+        }
+        let (data, cleared) = match core.get(request.index).await {
+            Err(err) if err.is::<ClearedError>() => (None, true),
+            data => (data?, false),
+        };
         Ok(
             if let Some((data, signature)) = data {
+                // the signed schema is sent once
+                let schema = core
+                    .schema()
+                    .zip(core.schema_signature())
+                    .filter(|_| !self.schema_sent);
+                self.schema_sent |= schema.is_some();
                 let response = Data {
                     index: request.index,
                     data,
-                    data_signature: signature.data().as_slice().to_vec(),
-                    tree_signature: signature.tree().as_slice().to_vec(),
+                    data_signature: signature.data().to_vec(),
+                    tree_signature: signature.tree().map(|tree| tree.to_vec()).unwrap_or_default(),
+                    signature_scheme: Some(u32::from(core.scheme().to_u8())),
+                    schema: schema.map(|(schema, _)| schema.to_owned()),
+                    schema_signature: schema.map(|(_, signature)| signature.to_vec()),
+                    key_rotation: signature.is_key_rotation().then_some(true),
+                    signed_head: None,
                 };
                 Some(DataOrRequest::Data(response))
+            } else if self.refetch == Some(request.index) {
+                // the remote cleared the refetched block too
+                self.refetch = core.next_refetch_after(request.index);
+                let index = self.refetch.unwrap_or_else(|| core.len());
+                Some(DataOrRequest::Request(Request {
+                    index,
+                    signed_head: None,
+                }))
+            } else if cleared {
+                // request the cleared block back, so the remote skips it
+                let index = request.index;
+                Some(DataOrRequest::Request(Request {
+                    index,
+                    signed_head: None,
+                }))
             } else {
                 let index = core.len();
                 let remote_index = self.remote_index.unwrap_or(0);
-                if index as usize >= MAX_CORE_LENGTH || remote_index <= index {
+                if remote_index == index {
+                    // compare the trees of the same length
+                    request_signed_head(&mut self.head_requested, &core).map(DataOrRequest::Request)
+                } else if index as usize >= MAX_CORE_LENGTH || remote_index < index {
                     None
                 } else {
-                    let response = Request { index };
+                    let response = Request {
+                        index,
+                        signed_head: None,
+                    };
                     Some(DataOrRequest::Request(response))
                 }
             }
         )
     }
     async fn on_data(&mut self, data: Data) -> Result<Option<Request>> {
-        let mut core = self.core.lock().await;
+        let mut core = self.core.write().await;
+        ensure_not_forked(&core)?;
+        if let (Some(schema), Some(signature)) = (&data.schema, &data.schema_signature) {
+            core.set_signed_schema(schema, signature.as_slice().try_into()?)
+                .await?;
+        }
+        if let Some(head) = &data.signed_head {
+            let equal = match head.is_empty() {
+                true => None,
+                false => Some(core.check_fork(&SignedHead::from_bytes(head)?).await?),
+            };
+            if let Some(conflict) = self.conflict.take() {
+                // no provable fork behind the conflicting block
+                return Err(conflict);
+            }
+            ensure!(
+                equal != Some(false),
+                "Remote signed head conflicts with the Core."
+            );
+            return Ok(None);
+        }
         let len = core.len();
-        if data.index == len {
-            let signature = Signature::from_bytes(
-                data.data_signature.try_into().unwrap(),
-                data.tree_signature.try_into().unwrap()
-                );
-            core.append(&data.data, Some(signature)).await?;
+        if data.index == len && self.conflict.is_none() {
+            let signature = parse_signature(&core, &data)?;
+            if let Err(err) = core.append(&data.data, Some(signature)).await {
+                // look for the fork the remote block may come from
+                if !err.is::<VerifyError>() {
+                    return Err(err);
+                }
+                self.head_requested = false;
+                return match request_signed_head(&mut self.head_requested, &core) {
+                    Some(request) => {
+                        self.conflict = Some(err);
+                        Ok(Some(request))
+                    }
+                    None => Err(err),
+                };
+            }
 
             if core.len() as usize >= MAX_CORE_LENGTH {
                 Ok(None)
             } else {
                 Ok(Some(Request {
                     index: data.index + 1,
+                    signed_head: None,
                 }))
             }
+        } else if self.conflict.is_some() {
+            Ok(None)
+        } else if data.index < len && core.is_cleared(data.index).await? {
+            core.restore(data.index, &data.data).await?;
+            self.refetch = core.next_refetch_after(data.index);
+            let index = self.refetch.unwrap_or(len);
+            Ok(Some(Request {
+                index,
+                signed_head: None,
+            }))
         } else {
-            Ok(Some(Request { index: len }))
+            Ok(Some(Request {
+                index: len,
+                signed_head: None,
+            }))
         }
     }
     async fn on_close(&mut self) -> Result<()> {
         if let Some(index) = self.remote_index {
-            let core = self.core.lock().await;
+            let core = self.core.read().await;
             let len = core.len();
 
             if len < index {
@@ -102,3 +233,38 @@ where
         Ok(())
     }
 }
+
+fn parse_signature<T>(core: &Core<T>, data: &Data) -> Result<Signature> {
+    let scheme = SignatureScheme::from_u8(u8::try_from(data.signature_scheme.unwrap_or(0))?);
+    ensure!(
+        scheme == core.scheme(),
+        "Remote signature scheme {:?} does not match {:?}.",
+        scheme,
+        core.scheme()
+    );
+    let signature = Signature::from_bytes(&data.data_signature, &data.tree_signature)?;
+    Ok(match data.key_rotation {
+        Some(true) => signature.into_key_rotation(),
+        _ => signature,
+    })
+}
+
+/// Request the [SignedHead] of the remote at the latest checkpoint, once.
+fn request_signed_head<T>(requested: &mut bool, core: &Core<T>) -> Option<Request> {
+    let index = core.checkpoint_len();
+    if *requested || index == 0 {
+        return None;
+    }
+    *requested = true;
+    Some(Request {
+        index,
+        signed_head: Some(true),
+    })
+}
+
+fn ensure_not_forked<T>(core: &Core<T>) -> Result<()> {
+    match core.fork_proof() {
+        Some(proof) => Err(ForkError::new(proof.clone()).into()),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{ensure, Result};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{Core, CoreIterator, IndexAccess, Proof, Signature, Snapshot};
+
+/// [CoreSnapshot] is a cloneable read-only handle
+/// of a shared [Core] pinned to a [Snapshot].
+///
+/// Reads take the read lock of the [Core] only for a single block,
+/// so appends can continue in between.
+/// Blocks past the [Snapshot] are never returned.
+pub struct CoreSnapshot<T> {
+    core: Arc<RwLock<Core<T>>>,
+    snapshot: Snapshot,
+}
+impl<T> Clone for CoreSnapshot<T> {
+    fn clone(&self) -> Self {
+        Self {
+            core: Arc::clone(&self.core),
+            snapshot: self.snapshot.clone(),
+        }
+    }
+}
+impl<T> CoreSnapshot<T>
+where
+    T: IndexAccess + Send + 'static,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+{
+    /// Create a new [CoreSnapshot] at the current length of `core`.
+    pub async fn new(core: Arc<RwLock<Core<T>>>) -> Self {
+        let snapshot = core.read().await.snapshot();
+        Self { core, snapshot }
+    }
+
+    /// Get the pinned [Snapshot].
+    #[must_use]
+    #[inline]
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+    /// Get the number of entries in the [Snapshot].
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.snapshot.len()
+    }
+    /// Check if the [Snapshot] is empty.
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.snapshot.is_empty()
+    }
+
+    /// Retrieve data for a block at index, if it is in the [Snapshot].
+    ///
+    /// The [Snapshot] does not keep the data,
+    /// blocks cleared since fail with a [ClearedError](crate::ClearedError).
+    pub async fn get(&self, index: u32) -> Result<Option<(Vec<u8>, Signature)>> {
+        if index >= self.snapshot.len() {
+            return Ok(None);
+        }
+        let core = self.core.read().await;
+        self.ensure_pinned(&core, index).await?;
+        core.get(index).await
+    }
+
+    /// Create a [Proof] for the block at index,
+    /// against a checkpoint in the [Snapshot].
+    pub async fn proof(&self, index: u32) -> Result<Proof> {
+        ensure!(
+            index < self.snapshot.checkpoint_len(),
+            "Block {} is not covered by a checkpoint of the snapshot.",
+            index
+        );
+        let core = self.core.read().await;
+        self.ensure_pinned(&core, index).await?;
+        core.proof(index).await
+    }
+
+    /// Create a [CoreIterator] from `index` to the end of the [Snapshot].
+    #[must_use]
+    #[inline]
+    pub fn iter(&self, index: u32) -> CoreIterator<T> {
+        CoreIterator::with_range(Arc::clone(&self.core), index..self.snapshot.len())
+    }
+
+    /// Blocks past the latest checkpoint of a replica can be rolled back
+    /// and replaced, the tree at the length of the [Snapshot] tells.
+    async fn ensure_pinned(&self, core: &Core<T>, index: u32) -> Result<()> {
+        if index < self.snapshot.checkpoint_len() {
+            // blocks covered by a checkpoint are never rolled back
+            return Ok(());
+        }
+        ensure!(
+            core.len() >= self.snapshot.len(),
+            "Core was rolled back below the snapshot."
+        );
+        ensure!(
+            core.roots(self.snapshot.len()).await? == self.snapshot.roots(),
+            "Core was rolled back and replaced blocks of the snapshot."
+        );
+        Ok(())
+    }
+}
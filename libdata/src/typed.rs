@@ -0,0 +1,207 @@
+//! Typed records over [Core] blocks.
+//!
+//! [TypedCore] encodes and decodes every block with a [Codec].
+//! The [Codec::schema] is recorded in the [Core] header,
+//! signed by the writer and replicated with the blocks,
+//! opening a [Core] with a different schema fails.
+//!
+//! Built-in codecs are [Json], [Postcard] and [Protobuf].
+
+use anyhow::Result;
+use futures_lite::stream::Stream;
+use prost::Message;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::RwLock;
+
+use crate::{Core, CoreIterator, IndexAccess};
+
+/// [Codec] encodes records of type [Codec::Item] into blocks.
+pub trait Codec: Send + Sync {
+    /// Type of the records.
+    type Item;
+
+    /// Get the schema id recorded in the [Core] header.
+    ///
+    /// Should change whenever the encoding of [Codec::Item] does.
+    fn schema(&self) -> String;
+    /// Encode a record into a block.
+    fn encode(&self, item: &Self::Item) -> Result<Vec<u8>>;
+    /// Decode a record from a block.
+    fn decode(&self, data: &[u8]) -> Result<Self::Item>;
+}
+
+/// [Codec] encoding `serde` records as JSON.
+pub struct Json<V> {
+    name: String,
+    item: PhantomData<fn() -> V>,
+}
+impl<V> Json<V> {
+    /// Create a new [Json] codec for records named `name`.
+    #[must_use]
+    #[inline]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            item: PhantomData,
+        }
+    }
+}
+impl<V: Serialize + DeserializeOwned> Codec for Json<V> {
+    type Item = V;
+
+    fn schema(&self) -> String {
+        format!("json:{}", self.name)
+    }
+    fn encode(&self, item: &V) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(item)?)
+    }
+    fn decode(&self, data: &[u8]) -> Result<V> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// [Codec] encoding `serde` records with `postcard`.
+pub struct Postcard<V> {
+    name: String,
+    item: PhantomData<fn() -> V>,
+}
+impl<V> Postcard<V> {
+    /// Create a new [Postcard] codec for records named `name`.
+    #[must_use]
+    #[inline]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            item: PhantomData,
+        }
+    }
+}
+impl<V: Serialize + DeserializeOwned> Codec for Postcard<V> {
+    type Item = V;
+
+    fn schema(&self) -> String {
+        format!("postcard:{}", self.name)
+    }
+    fn encode(&self, item: &V) -> Result<Vec<u8>> {
+        Ok(postcard::to_allocvec(item)?)
+    }
+    fn decode(&self, data: &[u8]) -> Result<V> {
+        Ok(postcard::from_bytes(data)?)
+    }
+}
+
+/// [Codec] encoding `prost` messages as protobuf.
+pub struct Protobuf<V> {
+    name: String,
+    item: PhantomData<fn() -> V>,
+}
+impl<V> Protobuf<V> {
+    /// Create a new [Protobuf] codec for messages named `name`.
+    #[must_use]
+    #[inline]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            item: PhantomData,
+        }
+    }
+}
+impl<V: Message + Default> Codec for Protobuf<V> {
+    type Item = V;
+
+    fn schema(&self) -> String {
+        format!("protobuf:{}", self.name)
+    }
+    fn encode(&self, item: &V) -> Result<Vec<u8>> {
+        Ok(item.encode_to_vec())
+    }
+    fn decode(&self, data: &[u8]) -> Result<V> {
+        Ok(V::decode(data)?)
+    }
+}
+
+/// [TypedCore] appends and reads [Codec::Item]s instead of raw blocks.
+pub struct TypedCore<T, C> {
+    core: Arc<RwLock<Core<T>>>,
+    codec: Arc<C>,
+}
+impl<T, C> TypedCore<T, C>
+where
+    T: IndexAccess + Send + 'static,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+    C: Codec,
+{
+    /// Create a new [TypedCore], recording the [Codec::schema]
+    /// in the [Core] header or failing if it has a different one.
+    ///
+    /// Blocks of a [Core] without a schema are decoded first.
+    pub async fn new(core: Arc<RwLock<Core<T>>>, codec: C) -> Result<Self> {
+        core.write()
+            .await
+            .set_schema_with(&codec.schema(), |data| codec.decode(data).map(|_| ()))
+            .await?;
+        Ok(Self {
+            core,
+            codec: Arc::new(codec),
+        })
+    }
+
+    /// Access the underlying [Core].
+    #[must_use]
+    #[inline]
+    pub fn core(&self) -> &Arc<RwLock<Core<T>>> {
+        &self.core
+    }
+
+    /// Append a record.
+    pub async fn append(&self, item: &C::Item) -> Result<()> {
+        let data = self.codec.encode(item)?;
+        self.core.write().await.append(&data, None).await
+    }
+
+    /// Get the record at `index`, `None` for a key rotation record.
+    pub async fn get(&self, index: u32) -> Result<Option<C::Item>> {
+        let block = self.core.read().await.get(index).await?;
+        match block {
+            Some((_, signature)) if signature.is_key_rotation() => Ok(None),
+            Some((data, _)) => Ok(Some(self.codec.decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Create a [TypedIterator] starting at `index`.
+    #[must_use]
+    #[inline]
+    pub fn iter(&self, index: u32) -> TypedIterator<T, C> {
+        TypedIterator {
+            iter: CoreIterator::new(Arc::clone(&self.core), index),
+            codec: Arc::clone(&self.codec),
+        }
+    }
+}
+
+/// Async [Stream] iterator over the records of a [TypedCore].
+pub struct TypedIterator<T, C> {
+    iter: CoreIterator<T>,
+    codec: Arc<C>,
+}
+impl<T, C> Stream for TypedIterator<T, C>
+where
+    T: IndexAccess + Send + 'static,
+    <T as IndexAccess>::Error: Into<anyhow::Error>,
+    C: Codec,
+{
+    type Item = Result<(u32, C::Item)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.iter)
+            .poll_next(cx)
+            .map(|block| block.map(|(index, data)| Ok((index, this.codec.decode(&data)?))))
+    }
+}
@@ -1,8 +1,9 @@
 use anyhow::Result;
 use tokio::test;
 
+use datacore::SledStorage;
 use index_access_memory::IndexAccessMemory;
-use libdata::{key, KeyPair, Core, Cores};
+use libdata::{key, KeyPair, Core, Cores, Stats};
 
 async fn new_core() -> Result<Core<IndexAccessMemory>> {
     let keypair = KeyPair::generate();
@@ -65,3 +66,62 @@ async fn cores_insert_2() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+async fn cores_stats() -> Result<()> {
+    let mut a = new_core().await?;
+    a.append(b"abc", None).await?;
+    a.append(b"d", None).await?;
+    let mut b = new_core().await?;
+    b.append(b"efghij", None).await?;
+    let stats = a.stats() + b.stats();
+
+    let mut cores = Cores::default();
+    assert_eq!(cores.stats().await, Stats::default());
+    cores.insert(a);
+    cores.insert(b);
+
+    let total = cores.stats().await;
+    assert_eq!(total, stats);
+    assert_eq!(total.blocks(), 3);
+    assert_eq!(total.bytes(), 10);
+    assert_eq!(total.average_block_size(), 3);
+    Ok(())
+}
+
+#[test]
+async fn cores_sled() -> Result<()> {
+    let db = sled::Config::new().temporary(true).open()?;
+    let storage = SledStorage::from_db(db);
+
+    let mut keys = vec![];
+    for i in 0..3u8 {
+        let keypair = KeyPair::generate();
+        let mut core = Core::new(
+            storage.storage(&keypair.pk)?,
+            keypair.pk,
+            Some(keypair.sk),
+        )
+        .await?;
+        for _ in 0..=i {
+            core.append(&[i], None).await?;
+        }
+        keys.push(keypair.pk);
+    }
+
+    // open all cores from the shared handle
+    let mut cores = Cores::default();
+    for public in storage.public_keys() {
+        let core = Core::new(storage.storage(&public)?, public, None).await?;
+        cores.insert(core);
+    }
+    assert_eq!(cores.len(), 3);
+    for (i, public) in keys.iter().enumerate() {
+        let core = cores.get_by_public(public).unwrap();
+        let core = core.read().await;
+        assert_eq!(core.len() as usize, i + 1);
+        assert_eq!(core.get(0).await?.unwrap().0, vec![i as u8]);
+    }
+    assert_eq!(cores.stats().await.blocks(), 6);
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::test;
+
+use index_access_memory::IndexAccessMemory;
+use libdata::drive::{Drive, BLOCK_SIZE};
+use libdata::{Core, KeyPair};
+
+async fn new_core() -> Result<Arc<RwLock<Core<IndexAccessMemory>>>> {
+    let keypair = KeyPair::generate();
+    let core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await?;
+    Ok(Arc::new(RwLock::new(core)))
+}
+
+async fn new_drive() -> Result<Drive<IndexAccessMemory>> {
+    Ok(Drive::new(new_core().await?, new_core().await?))
+}
+
+async fn read(drive: &Drive<IndexAccessMemory>, path: &str) -> Result<Option<Vec<u8>>> {
+    match drive.read_file(path).await? {
+        Some(mut file) => Ok(Some(file.read_to_end().await?)),
+        None => Ok(None),
+    }
+}
+
+#[test]
+async fn drive_write_read_stat() -> Result<()> {
+    let drive = new_drive().await?;
+    drive.write_file("/a.txt", b"hello", 0o644).await?;
+    drive.write_file("/empty", b"", 0o600).await?;
+
+    assert_eq!(read(&drive, "/a.txt").await?, Some(b"hello".to_vec()));
+    assert!(read(&drive, "a.txt").await.is_err());
+    assert_eq!(read(&drive, "//a.txt/").await?, Some(b"hello".to_vec()));
+    assert_eq!(read(&drive, "/empty").await?, Some(vec![]));
+    assert_eq!(read(&drive, "/missing").await?, None);
+
+    let stat = drive.stat("/a.txt").await?.unwrap();
+    assert_eq!(stat.size(), 5);
+    assert_eq!(stat.mode(), 0o644);
+    assert_eq!(stat.blocks(), &(0..1));
+
+    drive.write_file("/a.txt", b"bye", 0o644).await?;
+    assert_eq!(read(&drive, "/a.txt").await?, Some(b"bye".to_vec()));
+    assert!(drive.write_file("/", b"root", 0o644).await.is_err());
+    Ok(())
+}
+
+#[test]
+async fn drive_read_seek() -> Result<()> {
+    let drive = new_drive().await?;
+    let data = (0..BLOCK_SIZE * 2 + 100)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<u8>>();
+    drive.write_file("/before", b"0123", 0o644).await?;
+    drive.write_file("/big", &data, 0o644).await?;
+    drive.write_file("/after", b"4567", 0o644).await?;
+
+    let mut file = drive.read_file("/big").await?.unwrap();
+    assert_eq!(file.stat().blocks().len(), 3);
+    assert_eq!(file.read_to_end().await?, data);
+    assert!(file.read(10).await?.is_empty());
+
+    // reads stop at block boundaries
+    let position = BLOCK_SIZE as u64 - 5;
+    file.seek(position)?;
+    assert_eq!(file.read(10).await?, &data[BLOCK_SIZE - 5..BLOCK_SIZE]);
+    assert_eq!(file.read(10).await?, &data[BLOCK_SIZE..BLOCK_SIZE + 10]);
+    assert_eq!(file.position(), position + 15);
+
+    file.seek(data.len() as u64 - 3)?;
+    assert_eq!(file.read(10).await?, &data[data.len() - 3..]);
+    assert!(file.seek(data.len() as u64 + 1).is_err());
+    Ok(())
+}
+
+#[test]
+async fn drive_list_delete() -> Result<()> {
+    let drive = new_drive().await?;
+    for path in ["/a.txt", "/a/x", "/a/y/z", "/a-b", "/b"] {
+        drive.write_file(path, path.as_bytes(), 0o644).await?;
+    }
+
+    assert_eq!(drive.list("/").await?, ["a", "a-b", "a.txt", "b"]);
+    assert_eq!(drive.list("/a").await?, ["x", "y"]);
+    assert_eq!(drive.list("/a/y/").await?, ["z"]);
+    assert!(drive.list("/c").await?.is_empty());
+
+    assert!(drive.delete("/a/x").await?);
+    assert!(!drive.delete("/a/x").await?);
+    assert_eq!(drive.list("/a").await?, ["y"]);
+    assert_eq!(drive.stat("/a/x").await?, None);
+    Ok(())
+}
+
+#[test]
+async fn drive_checkout() -> Result<()> {
+    let drive = new_drive().await?;
+    drive.write_file("/a", b"1", 0o644).await?;
+    drive.write_file("/b", b"2", 0o644).await?;
+    let version = drive.version().await;
+    drive.write_file("/a", b"3", 0o644).await?;
+    drive.delete("/b").await?;
+
+    let old = drive.checkout(version);
+    assert_eq!(read(&old, "/a").await?, Some(b"1".to_vec()));
+    assert_eq!(read(&old, "/b").await?, Some(b"2".to_vec()));
+    assert_eq!(old.list("/").await?, ["a", "b"]);
+    assert!(old.write_file("/c", b"4", 0o644).await.is_err());
+    assert_eq!(drive.content().read().await.len(), 3);
+
+    assert_eq!(read(&drive, "/a").await?, Some(b"3".to_vec()));
+    assert_eq!(drive.list("/").await?, ["a"]);
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use futures_lite::stream::StreamExt;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use tokio::test;
 
 use index_access_memory::IndexAccessMemory;
@@ -23,7 +23,7 @@ async fn iter_simple() -> Result<()> {
         core.append(&[d], None).await.unwrap();
     }
 
-    let mut iter = CoreIterator::new(Arc::new(Mutex::new(core)), 0);
+    let mut iter = CoreIterator::new(Arc::new(RwLock::new(core)), 0);
     assert_eq!(iter.next().await.unwrap(), (0, vec![1]));
     assert_eq!(iter.next().await.unwrap(), (1, vec![2]));
     assert_eq!(iter.next().await.unwrap(), (2, vec![3]));
@@ -47,7 +47,7 @@ async fn iter_offset() -> Result<()> {
         core.append(&[d], None).await.unwrap();
     }
 
-    let mut iter = CoreIterator::new(Arc::new(Mutex::new(core)), 1);
+    let mut iter = CoreIterator::new(Arc::new(RwLock::new(core)), 1);
     assert_eq!(iter.next().await.unwrap(), (1, vec![2]));
     assert_eq!(iter.next().await.unwrap(), (2, vec![3]));
     assert_eq!(iter.next().await, None);
@@ -70,7 +70,59 @@ async fn iter_out_of_bounds() -> Result<()> {
         core.append(&[d], None).await.unwrap();
     }
 
-    let mut iter = CoreIterator::new(Arc::new(Mutex::new(core)), 100);
+    let mut iter = CoreIterator::new(Arc::new(RwLock::new(core)), 100);
     assert_eq!(iter.next().await, None);
     Ok(())
 }
+
+#[test]
+async fn iter_range() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+
+    for d in 0..5 {
+        core.append(&[d], None).await.unwrap();
+    }
+
+    let mut iter = CoreIterator::with_range(Arc::new(RwLock::new(core)), 1..3);
+    assert_eq!(iter.next().await.unwrap(), (1, vec![1]));
+    assert_eq!(iter.next().await.unwrap(), (2, vec![2]));
+    assert_eq!(iter.next().await, None);
+    Ok(())
+}
+
+#[test]
+async fn iter_concurrent_readers() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await
+    .unwrap();
+
+    for d in 0..5 {
+        core.append(&[d], None).await.unwrap();
+    }
+
+    let core = Arc::new(RwLock::new(core));
+    let reader = core.read().await;
+    let mut iter = CoreIterator::new(Arc::clone(&core), 0);
+    let mut other = CoreIterator::new(Arc::clone(&core), 2);
+    assert_eq!(iter.next().await.unwrap(), (0, vec![0]));
+    assert_eq!(other.next().await.unwrap(), (2, vec![2]));
+    assert_eq!(reader.get(4).await?.unwrap().0, vec![4]);
+    assert_eq!(iter.next().await.unwrap(), (1, vec![1]));
+    drop(reader);
+
+    core.write().await.append(&[5], None).await?;
+    assert_eq!(other.count().await, 3);
+    Ok(())
+}
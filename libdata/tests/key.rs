@@ -1,6 +1,8 @@
 use insta;
 use quickcheck::{quickcheck, TestResult};
 
+use libdata::key::{self, ReadKey, Url};
+use libdata::keypair::{ChainKey, DerivationPath};
 use libdata::{keypair, KeyPair};
 
 #[test]
@@ -11,7 +13,12 @@ fn key_can_generate() {
 #[test]
 fn key_can_derive() {
     let keypair = KeyPair::generate();
-    let _derived = keypair::derive(&keypair.sk, "hello");
+    let (public, secret) = keypair::derive(keypair.sk.expose(), "hello");
+    assert_eq!(secret.public_key(), public);
+    assert_eq!(
+        format!("{:?}", secret),
+        format!("ZeroizingKey {{ public_key: {:?}, .. }}", public)
+    );
 }
 
 quickcheck! {
@@ -21,27 +28,169 @@ quickcheck! {
         }
 
         let main = KeyPair::generate();
-        let a = keypair::derive(&main.sk, &a);
-        let b = keypair::derive(&main.sk, &b);
+        let a = keypair::derive(main.sk.expose(), &a);
+        let b = keypair::derive(main.sk.expose(), &b);
 
-        TestResult::from_bool(a.as_slice() != b.as_slice())
+        TestResult::from_bool(a != b)
     }
 
     fn key_different_key_same_name(name: String) -> bool {
         let a = KeyPair::generate();
         let b = KeyPair::generate();
-        let a = keypair::derive(&a.sk, &name);
-        let b = keypair::derive(&b.sk, &name);
+        let a = keypair::derive(a.sk.expose(), &name);
+        let b = keypair::derive(b.sk.expose(), &name);
 
-        a.as_slice() != b.as_slice()
+        a != b
     }
 
     fn key_same_key_same_name(name: String) -> bool {
         let main = KeyPair::generate();
-        let a = keypair::derive(&main.sk, &name);
-        let b = keypair::derive(&main.sk, &name);
+        let a = keypair::derive(main.sk.expose(), &name);
+        let b = keypair::derive(main.sk.expose(), &name);
+
+        a == b
+    }
+
+    fn key_path_different_segments(a: String, b: String) -> TestResult {
+        let root = DerivationPath::root();
+        let (a, b) = match (root.child(&a), root.child(&b)) {
+            (Ok(a), Ok(b)) if a != b => (a, b),
+            _ => return TestResult::discard(),
+        };
+
+        let main = KeyPair::generate();
+        let a = keypair::derive_path(main.sk.expose(), &a);
+        let b = keypair::derive_path(main.sk.expose(), &b);
+
+        TestResult::from_bool(a != b)
+    }
+}
+
+#[test]
+fn key_path_parse() {
+    let path: DerivationPath = "m/app/channel/3".parse().unwrap();
+    assert_eq!(path.segments(), ["app", "channel", "3"]);
+    assert_eq!(path.to_string(), "m/app/channel/3");
+    assert_eq!(path.parent().unwrap().to_string(), "m/app/channel");
+    assert_eq!(DerivationPath::root().child("app").unwrap().depth(), 1);
+    assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::root());
+    assert_eq!(DerivationPath::root().parent(), None);
+
+    for invalid in ["", "app/channel", "m/", "m//app", "n/app", "/app"] {
+        assert!(invalid.parse::<DerivationPath>().is_err(), "{}", invalid);
+    }
+    assert!(DerivationPath::root().child("a/b").is_err());
+}
+
+#[test]
+fn key_path_hierarchy() {
+    let main = KeyPair::generate();
+    let path: DerivationPath = "m/app/channel/3".parse().unwrap();
+    let keypair = keypair::derive_path(main.sk.expose(), &path);
+
+    // a subtree is derived from its chain key alone
+    let app = ChainKey::root(main.sk.expose()).derive(&"m/app".parse().unwrap());
+    let app = ChainKey::from_bytes(*app.as_bytes());
+    let channel = app.child("channel").unwrap().child("3").unwrap();
+    assert_eq!(channel.keypair(), keypair);
+    assert!(app.child("").is_err());
+    assert_eq!(format!("{:?}", app), "ChainKey(..)");
+
+    // segments are not concatenated
+    let other = keypair::derive_path(main.sk.expose(), &"m/appchannel/3".parse().unwrap());
+    assert_ne!(other, keypair);
+    let other = keypair::derive_path(main.sk.expose(), &"m/app/channel".parse().unwrap());
+    assert_ne!(other, keypair);
+    // the root path is not the root key
+    let root = keypair::derive_path(main.sk.expose(), &DerivationPath::root());
+    assert_ne!(root.1.expose(), main.sk.expose());
+}
+
+#[test]
+fn key_encode_decode() {
+    let public = KeyPair::generate().pk;
+    let encoded = key::encode_public(&public);
+    assert!(encoded.starts_with("ldpk1"));
+    assert_eq!(key::decode_public(&encoded).unwrap(), public);
+    assert_eq!(key::decode_public(&encoded.to_uppercase()).unwrap(), public);
+
+    let discovery = key::discovery(public.as_slice().try_into().unwrap());
+    let encoded_discovery = key::encode_discovery(&discovery);
+    assert!(encoded_discovery.starts_with("lddk1"));
+    assert_eq!(
+        key::decode_discovery(&encoded_discovery).unwrap(),
+        discovery
+    );
+    // keys of another kind are refused
+    assert!(key::decode_public(&encoded_discovery).is_err());
+
+    let read_key = ReadKey::generate();
+    assert_eq!(read_key.to_string().parse::<ReadKey>().unwrap(), read_key);
+    assert_eq!(format!("{:?}", read_key), "ReadKey(..)");
+}
+
+#[test]
+fn key_decode_typo() {
+    let encoded = key::encode_public(&KeyPair::generate().pk);
+    for position in [5, 20, encoded.len() - 1] {
+        let mut typo = encoded.clone().into_bytes();
+        typo[position] = if typo[position] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        let err = key::decode_public(&typo).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid checksum"), "{}", err);
+    }
+    let mut invalid = encoded.clone();
+    invalid.replace_range(10..11, "b");
+    let err = key::decode_public(&invalid).unwrap_err();
+    assert!(
+        err.to_string().starts_with("Invalid character 'b'"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn key_url() {
+    let public = KeyPair::generate().pk;
+    let url = Url::new(public);
+    assert_eq!(
+        url.to_string(),
+        format!("libdata://{}", key::encode_public(&public))
+    );
+    assert_eq!(url.to_string().parse::<Url>().unwrap(), url);
+    assert_eq!(url.length(), None);
+    assert!(url.read_key().is_none());
+    assert_eq!(
+        url.discovery_key(),
+        key::discovery(public.as_slice().try_into().unwrap())
+    );
+
+    let read_key = ReadKey::generate();
+    let url = url.with_length(42).with_read_key(read_key.clone());
+    let string = url.to_string();
+    assert_eq!(
+        string,
+        format!(
+            "libdata://{}?length=42&read={}",
+            key::encode_public(&public),
+            read_key
+        )
+    );
+    assert_eq!(string.parse::<Url>().unwrap(), url);
 
-        a.as_slice() == b.as_slice()
+    // unknown parameters are ignored
+    let extended = format!("{}&format=2", string);
+    assert_eq!(extended.parse::<Url>().unwrap(), url);
+
+    let encoded = key::encode_public(&public);
+    for invalid in [
+        format!("https://{}", encoded),
+        format!("libdata://{}?length=x", encoded),
+        format!("libdata://{}?length=1&length=2", encoded),
+        format!("libdata://{}?read={}", encoded, encoded),
+        format!("libdata://{}", &encoded[1..]),
+    ] {
+        assert!(invalid.parse::<Url>().is_err(), "{}", invalid);
     }
 }
 
@@ -53,31 +202,48 @@ const SEED_BYTES: [u8; 32] = [
 #[test]
 fn key_secret_key_bytes_have_not_changed() {
     let main = KeyPair::from_seed(keypair::Seed::from(SEED_BYTES)).sk;
-    insta::assert_debug_snapshot!(main.as_slice());
+    insta::assert_debug_snapshot!(main.expose().as_slice());
 }
 
 #[test]
 fn key_snapshot_1() {
     let main = KeyPair::from_seed(keypair::Seed::from(SEED_BYTES)).sk;
-    let keypair = keypair::derive(&main, "hello");
-    insta::assert_debug_snapshot!(keypair.as_slice());
+    let keypair = keypair::derive(main.expose(), "hello");
+    insta::assert_debug_snapshot!(keypair.1.expose().as_slice());
 }
 
 #[test]
 fn key_snapshot_2() {
     let main = KeyPair::from_seed(keypair::Seed::from(SEED_BYTES)).sk;
-    let keypair = keypair::derive(&main, "hello2");
-    insta::assert_debug_snapshot!(keypair.as_slice());
+    let keypair = keypair::derive(main.expose(), "hello2");
+    insta::assert_debug_snapshot!(keypair.1.expose().as_slice());
 }
 
 #[test]
 fn key_snapshot_3() {
     let main = KeyPair::from_seed(keypair::Seed::from(SEED_BYTES)).sk;
     let keypair = keypair::derive(
-        &main,
+        main.expose(),
         "a very long string as a key name should not break the key derive, \
         it should just work without any issues, this is just testing it, \
         to be sure",
     );
-    insta::assert_debug_snapshot!(keypair.as_slice());
+    insta::assert_debug_snapshot!(keypair.1.expose().as_slice());
+}
+
+#[test]
+fn key_snapshot_path() {
+    let main = KeyPair::from_seed(keypair::Seed::from(SEED_BYTES)).sk;
+    let path = "m/app/channel/3".parse().unwrap();
+    let keypair = keypair::derive_path(main.expose(), &path);
+    insta::assert_debug_snapshot!(keypair.1.expose().as_slice());
+}
+
+#[test]
+fn key_snapshot_url() {
+    let public = KeyPair::from_seed(keypair::Seed::from(SEED_BYTES)).pk;
+    let url = Url::new(public)
+        .with_length(42)
+        .with_read_key(ReadKey::new(SEED_BYTES));
+    insta::assert_snapshot!(url.to_string());
 }
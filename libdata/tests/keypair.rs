@@ -1,12 +1,90 @@
 use quickcheck::quickcheck;
 
-use libdata::keypair;
+use libdata::keypair::{self, Language, WordCount};
 
 quickcheck! {
     fn keypair_bip39_generate_recover() -> bool {
         let (original, phrase) = keypair::generate_bip39();
         let recovered = keypair::recover_bip39(&phrase).unwrap();
 
-        recovered.as_slice() == original.as_slice()
+        recovered == original
     }
 }
+
+#[test]
+fn keypair_bip39_word_counts() {
+    for words in [
+        WordCount::Words12,
+        WordCount::Words15,
+        WordCount::Words18,
+        WordCount::Words21,
+        WordCount::Words24,
+    ] {
+        let (original, phrase) = keypair::generate_bip39_with(words, Language::English, "");
+        assert_eq!(phrase.split_whitespace().count(), words.words());
+        let recovered = keypair::recover_bip39_with(&phrase, Language::English, "").unwrap();
+        assert_eq!(recovered, original);
+    }
+}
+
+#[test]
+fn keypair_bip39_passphrase() {
+    let (original, phrase) =
+        keypair::generate_bip39_with(WordCount::Words12, Language::English, "secret");
+    let recovered = keypair::recover_bip39_with(&phrase, Language::English, "secret").unwrap();
+    assert_eq!(recovered, original);
+    let other = keypair::recover_bip39_with(&phrase, Language::English, "").unwrap();
+    assert_ne!(other, original);
+
+    // an empty passphrase recovers the default keypair
+    let (original, phrase) = keypair::generate_bip39();
+    let recovered = keypair::recover_bip39_with(&phrase, Language::English, "").unwrap();
+    assert_eq!(recovered, original);
+}
+
+#[test]
+fn keypair_bip39_language() {
+    let (original, phrase) =
+        keypair::generate_bip39_with(WordCount::Words18, Language::Japanese, "");
+    let recovered = keypair::recover_bip39_with(&phrase, Language::Japanese, "").unwrap();
+    assert_eq!(recovered, original);
+
+    let err = keypair::recover_bip39(&phrase).unwrap_err();
+    assert!(err.to_string().starts_with("Word 1 "), "{}", err);
+    assert!(err.to_string().ends_with("is not in the English wordlist."), "{}", err);
+}
+
+#[test]
+fn keypair_bip39_errors() {
+    let (_, phrase) = keypair::generate_bip39();
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+
+    let err = keypair::recover_bip39(&words[..13].join(" ")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Mnemonic has 13 words, expected 12, 15, 18, 21 or 24."
+    );
+
+    let mut misspelled = words.clone();
+    let word = format!("{}x", words[2]);
+    misspelled[2] = &word;
+    let err = keypair::recover_bip39(&misspelled.join(" ")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Word 3 {:?} is not in the English wordlist.", word)
+    );
+
+    // swapped words break the checksum, unless the checksum matches by chance
+    let checksum = (0..8)
+        .filter(|&i| words[i] != words[i + 1])
+        .find_map(|i| {
+            let mut swapped = words.clone();
+            swapped.swap(i, i + 1);
+            keypair::recover_bip39(&swapped.join(" ")).err()
+        })
+        .unwrap();
+    assert_eq!(
+        checksum.to_string(),
+        "Mnemonic checksum does not match, a word may be mistyped or out of order."
+    );
+}
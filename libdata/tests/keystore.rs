@@ -0,0 +1,144 @@
+use anyhow::Result;
+
+use libdata::keypair::{self, DerivationPath};
+use libdata::keystore::{KdfParams, Keystore};
+use libdata::KeyPair;
+
+// cheap parameters, the defaults are slow on purpose
+fn params() -> KdfParams {
+    KdfParams::new(64, 1, 1)
+}
+
+#[test]
+fn keystore_encrypt_decrypt() -> Result<()> {
+    let root = KeyPair::generate();
+    let other = KeyPair::generate();
+    let mut keystore = Keystore::new();
+    keystore.insert("root", root.sk.clone())?;
+    keystore.insert("other", other.sk.clone())?;
+    let path: DerivationPath = "m/app/channel/3".parse()?;
+    keystore.insert_derived("channel", "root", path.clone())?;
+
+    let data = keystore.encrypt_with("password", params())?;
+    let keystore = Keystore::decrypt(&data, "password")?;
+    assert_eq!(
+        keystore.names().collect::<Vec<_>>(),
+        ["channel", "other", "root"]
+    );
+    assert_eq!(keystore.keypair("root").unwrap().1, root.sk);
+    assert_eq!(keystore.keypair("other").unwrap().1, other.sk);
+    assert!(keystore.keypair("missing").is_none());
+
+    // derived keys are derived from their root
+    let channel = keypair::derive_path(root.sk.expose(), &path);
+    assert_eq!(keystore.keypair("channel").unwrap(), channel);
+    assert_eq!(keystore.derivation("channel"), Some(("root", &path)));
+    assert_eq!(keystore.derivation("root"), None);
+    let named = keypair::derive(root.sk.expose(), "topic");
+    assert_eq!(keystore.derive("root", "topic")?, named);
+    assert!(keystore.derive("channel", "topic").is_err());
+    Ok(())
+}
+
+#[test]
+fn keystore_wrong_password() -> Result<()> {
+    let mut keystore = Keystore::new();
+    keystore.insert("root", KeyPair::generate().sk)?;
+    let data = keystore.encrypt_with("password", params())?;
+
+    let err = Keystore::decrypt(&data, "passw0rd").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Could not decrypt keystore, wrong password or corrupted."
+    );
+
+    // the header is authenticated too
+    for index in [5, 40, data.len() - 1] {
+        let mut corrupted = data.clone();
+        corrupted[index] ^= 1;
+        assert!(Keystore::decrypt(&corrupted, "password").is_err());
+    }
+    assert!(Keystore::decrypt(&data[..20], "password").is_err());
+
+    let mut version = data.clone();
+    version[4] = 2;
+    let err = Keystore::decrypt(&version, "password").err().unwrap();
+    assert_eq!(err.to_string(), "Keystore version 2 is not supported.");
+    Ok(())
+}
+
+#[test]
+fn keystore_kdf_limits() -> Result<()> {
+    let mut keystore = Keystore::new();
+    keystore.insert("root", KeyPair::generate().sk)?;
+    let data = keystore.encrypt_with("password", params())?;
+
+    // memory, iterations and parallelism are bounded before deriving the key
+    let errors = [
+        "Keystore asks for 4294967295 KiB of memory, at most 4194304 KiB are allowed.",
+        "Keystore asks for 4294967295 iterations, at most 64 are allowed.",
+        "Keystore asks for a parallelism of 4294967295, at most 64 is allowed.",
+    ];
+    for (field, error) in errors.iter().enumerate() {
+        let mut oversized = data.clone();
+        let start = 5 + 4 * field;
+        oversized[start..start + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = Keystore::decrypt(&oversized, "password").err().unwrap();
+        assert_eq!(err.to_string(), *error);
+    }
+    assert!(keystore
+        .encrypt_with("password", KdfParams::new(8, u32::MAX, 1))
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn keystore_roots() -> Result<()> {
+    let mut keystore = Keystore::new();
+    assert!(keystore.is_empty());
+    assert!(keystore
+        .insert_derived("a", "root", DerivationPath::root())
+        .is_err());
+    keystore.insert("root", KeyPair::generate().sk)?;
+    keystore.insert_derived("a", "root", "m/a".parse()?)?;
+    assert!(keystore.insert_derived("b", "a", "m/b".parse()?).is_err());
+    assert!(keystore.insert("", KeyPair::generate().sk).is_err());
+
+    // secret keys are not replaced by derived keys
+    let root = keystore.keypair("root").unwrap().0;
+    assert!(keystore
+        .insert_derived("root", "root", "m/a".parse()?)
+        .is_err());
+    keystore.insert("other", KeyPair::generate().sk)?;
+    assert!(keystore
+        .insert_derived("other", "root", "m/b".parse()?)
+        .is_err());
+    assert!(keystore.derivation("other").is_none());
+    assert_eq!(keystore.keypair("root").unwrap().0, root);
+    keystore.insert_derived("a", "root", "m/c".parse()?)?;
+    assert!(keystore.remove("other")?);
+
+    // roots stay while keys derive from them
+    assert!(keystore.remove("root").is_err());
+    assert!(keystore.insert("root", KeyPair::generate().sk).is_err());
+    assert!(keystore.remove("a")?);
+    assert!(!keystore.remove("a")?);
+    assert!(keystore.remove("root")?);
+    assert_eq!(keystore.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn keystore_save_load() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("keys");
+    let root = KeyPair::generate();
+    let mut keystore = Keystore::new();
+    keystore.insert("root", root.sk.clone())?;
+    keystore.save(&path, "password")?;
+
+    let keystore = Keystore::load(&path, "password")?;
+    assert_eq!(keystore.keypair("root").unwrap().1, root.sk);
+    assert!(Keystore::load(&path, "").is_err());
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::test;
+
+use index_access_memory::IndexAccessMemory;
+use libdata::kv::KeyValue;
+use libdata::{Core, KeyPair};
+
+async fn new_kv() -> Result<KeyValue<IndexAccessMemory>> {
+    let keypair = KeyPair::generate();
+    let core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await?;
+    Ok(KeyValue::new(Arc::new(RwLock::new(core))))
+}
+
+fn pairs(entries: &[(&[u8], &[u8])]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.to_vec(), value.to_vec()))
+        .collect()
+}
+
+#[test]
+async fn kv_put_get_del() -> Result<()> {
+    let kv = new_kv().await?;
+    assert_eq!(kv.get(b"a").await?, None);
+    assert!(!kv.del(b"a").await?);
+
+    kv.put(b"a", b"1").await?;
+    kv.put(b"b", b"2").await?;
+    assert_eq!(kv.get(b"a").await?, Some(b"1".to_vec()));
+    assert_eq!(kv.get(b"b").await?, Some(b"2".to_vec()));
+    assert_eq!(kv.get(b"c").await?, None);
+
+    kv.put(b"a", b"3").await?;
+    assert_eq!(kv.get(b"a").await?, Some(b"3".to_vec()));
+
+    assert!(kv.del(b"a").await?);
+    assert!(!kv.del(b"a").await?);
+    assert_eq!(kv.get(b"a").await?, None);
+    assert_eq!(kv.core().read().await.len(), 4);
+
+    kv.put(b"a", b"4").await?;
+    assert_eq!(kv.get(b"a").await?, Some(b"4".to_vec()));
+    Ok(())
+}
+
+#[test]
+async fn kv_key_rotation() -> Result<()> {
+    let kv = new_kv().await?;
+    kv.put(b"a", b"1").await?;
+    kv.core().write().await.rotate(KeyPair::generate().sk).await?;
+    assert_eq!(kv.get(b"a").await?, Some(b"1".to_vec()));
+
+    kv.put(b"b", b"2").await?;
+    assert_eq!(kv.get(b"a").await?, Some(b"1".to_vec()));
+    assert_eq!(kv.get(b"b").await?, Some(b"2".to_vec()));
+    assert_eq!(kv.core().read().await.len(), 3);
+    Ok(())
+}
+
+#[test]
+async fn kv_range_prefix() -> Result<()> {
+    let kv = new_kv().await?;
+    for key in [&b"b/2"[..], b"a/1", b"b/1", b"c", b"b/3", b"a/2", b"b\xff"] {
+        kv.put(key, key).await?;
+    }
+    kv.del(b"b/2").await?;
+
+    assert_eq!(
+        kv.prefix(b"b/").await?,
+        pairs(&[(b"b/1", b"b/1"), (b"b/3", b"b/3")])
+    );
+    assert_eq!(kv.prefix(b"b\xff").await?, pairs(&[(b"b\xff", b"b\xff")]));
+    assert_eq!(
+        kv.range(b"a/2".to_vec()..b"b/3".to_vec()).await?,
+        pairs(&[(b"a/2", b"a/2"), (b"b/1", b"b/1")])
+    );
+    assert_eq!(
+        kv.range(b"b/3".to_vec()..).await?,
+        pairs(&[(b"b/3", b"b/3"), (b"b\xff", b"b\xff"), (b"c", b"c")])
+    );
+    assert_eq!(kv.range(..).await?.len(), 6);
+    assert_eq!(kv.prefix(b"").await?.len(), 6);
+    Ok(())
+}
+
+#[test]
+async fn kv_many() -> Result<()> {
+    let kv = new_kv().await?;
+    let mut expected = BTreeMap::new();
+    for i in 0..300u32 {
+        let key = (i.wrapping_mul(7919) % 211).to_be_bytes().to_vec();
+        let value = i.to_le_bytes().to_vec();
+        kv.put(&key, &value).await?;
+        expected.insert(key, value);
+        if i % 5 == 0 {
+            let key = ((i * 31) % 211).to_be_bytes().to_vec();
+            assert_eq!(kv.del(&key).await?, expected.remove(&key).is_some());
+        }
+    }
+
+    for (key, value) in &expected {
+        assert_eq!(kv.get(key).await?.as_ref(), Some(value));
+    }
+    assert_eq!(
+        kv.range(..).await?,
+        expected.clone().into_iter().collect::<Vec<_>>()
+    );
+    let start = 50u32.to_be_bytes().to_vec();
+    let end = 150u32.to_be_bytes().to_vec();
+    assert_eq!(
+        kv.range(start.clone()..=end.clone()).await?,
+        expected
+            .range(start..=end)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+async fn kv_reopen() -> Result<()> {
+    let kv = new_kv().await?;
+    for i in 0..20u8 {
+        kv.put(&[i], &[i]).await?;
+    }
+    let kv = KeyValue::new(Arc::clone(kv.core()));
+    assert_eq!(kv.get(&[7]).await?, Some(vec![7]));
+    assert_eq!(kv.range(..).await?.len(), 20);
+    Ok(())
+}
+
+#[test]
+async fn kv_checkout() -> Result<()> {
+    let kv = new_kv().await?;
+    kv.put(b"a", b"1").await?;
+    kv.put(b"b", b"2").await?;
+    let version = kv.version().await;
+    kv.put(b"a", b"3").await?;
+    kv.del(b"b").await?;
+
+    let old = kv.checkout(version);
+    assert_eq!(old.version().await, 2);
+    assert_eq!(old.get(b"a").await?, Some(b"1".to_vec()));
+    assert_eq!(old.range(..).await?.len(), 2);
+    assert!(old.put(b"c", b"4").await.is_err());
+    assert!(old.del(b"a").await.is_err());
+    assert_eq!(kv.checkout(0).get(b"a").await?, None);
+    assert!(kv.checkout(5).get(b"a").await.is_err());
+
+    assert_eq!(kv.get(b"a").await?, Some(b"3".to_vec()));
+    assert_eq!(kv.get(b"b").await?, None);
+    Ok(())
+}
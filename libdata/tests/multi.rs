@@ -0,0 +1,220 @@
+use anyhow::Result;
+use futures_lite::stream::StreamExt;
+use tokio::test;
+
+use index_access_memory::IndexAccessMemory;
+use libdata::multi::{decode, encode, Clock, MultiWriter};
+use libdata::{key, ClearedError, Core, Cores, KeyPair};
+
+async fn new_core(keypair: &KeyPair) -> Result<Core<IndexAccessMemory>> {
+    Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk.clone()),
+    )
+    .await
+}
+
+async fn new_writer(keypair: &KeyPair) -> Result<MultiWriter<IndexAccessMemory>> {
+    let mut cores = Cores::default();
+    cores.insert(new_core(keypair).await?);
+    MultiWriter::new(cores, keypair.pk)
+}
+
+/// Copy all blocks of `public` from one [MultiWriter] to the other.
+async fn sync(
+    from: &MultiWriter<IndexAccessMemory>,
+    to: &MultiWriter<IndexAccessMemory>,
+    public: &key::Public,
+) -> Result<()> {
+    let from = from.cores().get_by_public(public).unwrap();
+    let from = from.read().await;
+    let to = to.cores().get_by_public(public).unwrap();
+    let mut to = to.write().await;
+    for index in to.len()..from.len() {
+        let (data, signature) = from.get(index).await?.unwrap();
+        to.append(&data, Some(signature)).await?;
+    }
+    Ok(())
+}
+
+async fn collect(writer: &MultiWriter<IndexAccessMemory>) -> Result<Vec<Vec<u8>>> {
+    Ok(writer
+        .iter()
+        .await?
+        .map(|entry| entry.map(|entry| entry.data().to_vec()))
+        .try_collect()
+        .await?)
+}
+
+#[test]
+async fn multi_encode_decode() -> Result<()> {
+    let mut clock = Clock::new();
+    clock.insert([1; 32], 3);
+    clock.insert([2; 32], 1);
+    let block = encode(&clock, b"data")?;
+    assert_eq!(decode(&block)?, (clock, &b"data"[..]));
+    assert_eq!(
+        decode(&encode(&Clock::new(), b"")?)?,
+        (Clock::new(), &b""[..])
+    );
+    assert!(decode(&block[..10]).is_err());
+    assert!(decode(&[0]).is_err());
+    Ok(())
+}
+
+#[test]
+async fn multi_single_writer() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let writer = new_writer(&keypair).await?;
+    writer.append(b"a").await?;
+    writer.append(b"b").await?;
+    assert_eq!(collect(&writer).await?, vec![b"a".to_vec(), b"b".to_vec()]);
+
+    let mut cores = Cores::default();
+    cores.insert(new_core(&keypair).await?);
+    assert!(MultiWriter::new(cores, KeyPair::generate().pk).is_err());
+    Ok(())
+}
+
+#[test]
+async fn multi_causal_order() -> Result<()> {
+    let a_keypair = KeyPair::generate();
+    let b_keypair = KeyPair::generate();
+    let mut a = new_writer(&a_keypair).await?;
+    let mut b = new_writer(&b_keypair).await?;
+    a.add_writer(Core::new(IndexAccessMemory::default(), b_keypair.pk, None).await?);
+    b.add_writer(Core::new(IndexAccessMemory::default(), a_keypair.pk, None).await?);
+
+    a.append(b"a0").await?;
+    sync(&a, &b, &a_keypair.pk).await?;
+    b.append(b"b0").await?;
+    sync(&b, &a, &b_keypair.pk).await?;
+    a.append(b"a1").await?;
+    sync(&a, &b, &a_keypair.pk).await?;
+
+    let expected = vec![b"a0".to_vec(), b"b0".to_vec(), b"a1".to_vec()];
+    assert_eq!(collect(&a).await?, expected);
+    assert_eq!(collect(&b).await?, expected);
+    Ok(())
+}
+
+#[test]
+async fn multi_key_rotation() -> Result<()> {
+    let a_keypair = KeyPair::generate();
+    let b_keypair = KeyPair::generate();
+    let mut a = new_writer(&a_keypair).await?;
+    let mut b = new_writer(&b_keypair).await?;
+    a.add_writer(Core::new(IndexAccessMemory::default(), b_keypair.pk, None).await?);
+    b.add_writer(Core::new(IndexAccessMemory::default(), a_keypair.pk, None).await?);
+
+    a.append(b"a0").await?;
+    let a_core = a.cores().get_by_public(&a_keypair.pk).unwrap();
+    a_core.write().await.rotate(KeyPair::generate().sk).await?;
+    sync(&a, &b, &a_keypair.pk).await?;
+    b.append(b"b0").await?;
+    sync(&b, &a, &b_keypair.pk).await?;
+    a.append(b"a1").await?;
+    sync(&a, &b, &a_keypair.pk).await?;
+
+    // the rotation is left out of the merged log
+    let expected = vec![b"a0".to_vec(), b"b0".to_vec(), b"a1".to_vec()];
+    assert_eq!(collect(&a).await?, expected);
+    assert_eq!(collect(&b).await?, expected);
+    assert!(!a.linearize().await?.contains(&(a_keypair.pk, 1)));
+    Ok(())
+}
+
+#[test]
+async fn multi_undecodable_block() -> Result<()> {
+    let a_keypair = KeyPair::generate();
+    let b_keypair = KeyPair::generate();
+    let mut a = new_writer(&a_keypair).await?;
+    let mut b = new_writer(&b_keypair).await?;
+    a.add_writer(Core::new(IndexAccessMemory::default(), b_keypair.pk, None).await?);
+    b.add_writer(Core::new(IndexAccessMemory::default(), a_keypair.pk, None).await?);
+
+    // b writes a block without a clock, its later blocks are left out too
+    a.append(b"a0").await?;
+    b.append(b"b0").await?;
+    let b_core = b.cores().get_by_public(&b_keypair.pk).unwrap();
+    b_core.write().await.append(&[1], None).await?;
+    b.append(b"b2").await?;
+    sync(&b, &a, &b_keypair.pk).await?;
+    a.append(b"a1").await?;
+
+    // a1 has seen the bad block and waits for it
+    let order = a.linearize().await?;
+    assert_eq!(order.len(), 2);
+    assert!(order.contains(&(a_keypair.pk, 0)));
+    assert!(order.contains(&(b_keypair.pk, 0)));
+    assert_eq!(collect(&a).await?.len(), 2);
+    Ok(())
+}
+
+#[test]
+async fn multi_concurrent_deterministic() -> Result<()> {
+    let a_keypair = KeyPair::generate();
+    let b_keypair = KeyPair::generate();
+    let mut a = new_writer(&a_keypair).await?;
+    let mut b = new_writer(&b_keypair).await?;
+    a.add_writer(Core::new(IndexAccessMemory::default(), b_keypair.pk, None).await?);
+    b.add_writer(Core::new(IndexAccessMemory::default(), a_keypair.pk, None).await?);
+
+    // concurrent appends, neither writer has seen the other
+    a.append(b"a0").await?;
+    b.append(b"b0").await?;
+
+    // b has not received the blocks of a yet
+    b.append(b"b1").await?;
+    sync(&b, &a, &b_keypair.pk).await?;
+    a.append(b"a1").await?;
+    assert_eq!(b.linearize().await?.len(), 2);
+
+    sync(&a, &b, &a_keypair.pk).await?;
+    let order = a.linearize().await?;
+    assert_eq!(order.len(), 4);
+    assert_eq!(order, b.linearize().await?);
+    assert_eq!(collect(&a).await?, collect(&b).await?);
+
+    // a1 has seen both blocks of b
+    let a1 = order
+        .iter()
+        .position(|entry| *entry == (a_keypair.pk, 1))
+        .unwrap();
+    let b1 = order
+        .iter()
+        .position(|entry| *entry == (b_keypair.pk, 1))
+        .unwrap();
+    assert!(b1 < a1);
+    Ok(())
+}
+
+#[test]
+async fn multi_cleared_block() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let writer = new_writer(&keypair).await?;
+    writer.append(b"a").await?;
+    writer.append(b"b").await?;
+    writer.append(b"c").await?;
+
+    // the cleared block is left out with its successors
+    let core = writer.cores().get_by_public(&keypair.pk).unwrap();
+    let (block, _) = core.read().await.get(1).await?.unwrap();
+    core.write().await.clear(1..2).await?;
+    assert_eq!(writer.linearize().await?, vec![(keypair.pk, 0)]);
+    assert_eq!(collect(&writer).await?, vec![b"a".to_vec()]);
+    core.write().await.restore(1, &block).await?;
+    assert_eq!(writer.linearize().await?.len(), 3);
+
+    // the order is kept, reading the cleared block fails
+    core.write().await.clear(1..2).await?;
+    assert_eq!(writer.linearize().await?.len(), 3);
+    let mut iter = writer.iter().await?;
+    assert_eq!(iter.next().await.unwrap()?.data(), b"a");
+    let err = iter.next().await.unwrap().unwrap_err();
+    assert!(err.is::<ClearedError>());
+    assert_eq!(iter.next().await.unwrap()?.data(), b"c");
+    assert!(iter.next().await.is_none());
+    Ok(())
+}
@@ -4,12 +4,14 @@ use futures_lite::future::zip;
 use sluice::pipe::{pipe, PipeReader, PipeWriter};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use tokio::{task, test, time};
 
+use datacore::{Fault, Faults, IndexAccessFault, InjectedError, Injection, Operation, Trigger};
 use index_access_memory::IndexAccessMemory;
+use libdata::kv::KeyValue;
 use libdata::replication::{CoreReplica, Duplex, Handle, Link, Options};
-use libdata::{key, KeyPair, Core};
+use libdata::{key, ClearedError, Core, ForkError, KeyPair};
 
 async fn new_core() -> Result<Core<IndexAccessMemory>> {
     let keypair = KeyPair::generate();
@@ -24,6 +26,14 @@ async fn new_replica(key: key::Public) -> Result<Core<IndexAccessMemory>> {
     Core::new(IndexAccessMemory::default(), key, None).await
 }
 
+async fn new_fault_replica(
+    key: key::Public,
+) -> Result<(Core<IndexAccessFault<IndexAccessMemory>>, Faults)> {
+    let storage = IndexAccessFault::new(IndexAccessMemory::default());
+    let faults = storage.faults();
+    Ok((Core::new(storage, key, None).await?, faults))
+}
+
 type Transfer = Duplex<Compat<PipeReader>, Compat<PipeWriter>>;
 type Replication = (Link<Transfer>, Handle);
 
@@ -78,8 +88,8 @@ async fn replication_core_replica() -> Result<()> {
     let data = b"hello world";
     a.append(data, None).await?;
 
-    let a_replica = Box::new(CoreReplica::new(Arc::new(Mutex::new(a))));
-    let b = Arc::new(Mutex::new(b));
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
     let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
 
     let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
@@ -98,7 +108,7 @@ async fn replication_core_replica() -> Result<()> {
     ra?;
     rb?;
 
-    let mut b = b.lock().await;
+    let b = b.read().await;
     assert_eq!(b.get(0).await?.unwrap().0, data);
     Ok(())
 }
@@ -111,8 +121,8 @@ async fn replication_core_replica_async_open() -> Result<()> {
     let data = b"hello world";
     a.append(data, None).await?;
 
-    let a_replica = Box::new(CoreReplica::new(Arc::new(Mutex::new(a))));
-    let b = Arc::new(Mutex::new(b));
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
     let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
 
     let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
@@ -141,7 +151,7 @@ async fn replication_core_replica_async_open() -> Result<()> {
     rc?;
     rd?;
 
-    let mut b = b.lock().await;
+    let b = b.read().await;
     assert_eq!(b.get(0).await?.unwrap().0, data);
     Ok(())
 }
@@ -157,8 +167,8 @@ async fn replication_core_replica_multiple_blocks() -> Result<()> {
         a.append(&[d], None).await?;
     }
 
-    let a_replica = Box::new(CoreReplica::new(Arc::new(Mutex::new(a))));
-    let b = Arc::new(Mutex::new(b));
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
     let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
 
     let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
@@ -177,7 +187,7 @@ async fn replication_core_replica_multiple_blocks() -> Result<()> {
     ra??;
     rb??;
 
-    let mut b = b.lock().await;
+    let b = b.read().await;
     for (i, &d) in data.into_iter().enumerate() {
         assert_eq!(b.get(i as u32).await?.unwrap().0[0], d);
     }
@@ -192,9 +202,9 @@ async fn replication_core_replica_multiple_blocks_live() -> Result<()> {
 
     let data = b"hello world";
 
-    let a = Arc::new(Mutex::new(a));
+    let a = Arc::new(RwLock::new(a));
     let a_replica = Box::new(CoreReplica::new(Arc::clone(&a)));
-    let b = Arc::new(Mutex::new(b));
+    let b = Arc::new(RwLock::new(b));
     let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
 
     let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
@@ -212,7 +222,7 @@ async fn replication_core_replica_multiple_blocks_live() -> Result<()> {
             task::spawn(async move {
                 a_handle.open(&public, a_replica).unwrap();
                 for &d in data.into_iter() {
-                    let mut a = a.lock().await;
+                    let mut a = a.write().await;
                     a.append(&[d], None).await.unwrap();
                     a_handle.reopen(&public).unwrap();
                     time::sleep(Duration::from_millis(10)).await;
@@ -229,7 +239,7 @@ async fn replication_core_replica_multiple_blocks_live() -> Result<()> {
     rc?;
     rd?;
 
-    let mut b = b.lock().await;
+    let b = b.read().await;
     for (i, &d) in data.into_iter().enumerate() {
         assert_eq!(b.get(i as u32).await?.unwrap().0[0], d);
     }
@@ -246,11 +256,11 @@ async fn replication_core_replica_of_replica() -> Result<()> {
     let data = b"hello world";
     a.append(data, None).await?;
 
-    let a_replica = Box::new(CoreReplica::new(Arc::new(Mutex::new(a))));
-    let b = Arc::new(Mutex::new(b));
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
     let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
     let b2_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
-    let c = Arc::new(Mutex::new(c));
+    let c = Arc::new(RwLock::new(c));
     let c_replica = Box::new(CoreReplica::new(Arc::clone(&c)));
 
     let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
@@ -285,7 +295,435 @@ async fn replication_core_replica_of_replica() -> Result<()> {
     ra?;
     rb?;
 
-    let mut c = c.lock().await;
+    let c = c.read().await;
     assert_eq!(c.get(0).await?.unwrap().0, data);
     Ok(())
 }
+
+#[test]
+async fn replication_core_replica_fork() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let public = keypair.pk.clone();
+    let mut a = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk.clone(),
+        Some(keypair.sk.clone()),
+    )
+    .await?;
+    let mut fork = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk.clone(),
+        Some(keypair.sk.clone()),
+    )
+    .await?;
+    for &d in b"abc" {
+        a.append(&[d], None).await?;
+    }
+    for &d in b"abXYZ" {
+        fork.append(&[d], None).await?;
+    }
+
+    // b replicates a, then connects to a fork of a
+    let mut b = new_replica(public.clone()).await?;
+    for i in 0..a.len() {
+        let (data, signature) = a.get(i).await?.unwrap();
+        b.append(&data, Some(signature)).await?;
+    }
+    let fork_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(fork))));
+    let b = Arc::new(RwLock::new(b));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+    let ((fork_replication, mut fork_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (_, rb) = zip(
+        task::spawn(async move {
+            fork_handle.open(&public, fork_replica).unwrap();
+            fork_replication.run().await
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await
+        }),
+    )
+    .await;
+    let err = rb?.unwrap_err();
+    let proof = err.downcast_ref::<ForkError>().unwrap().proof();
+    assert_eq!(proof.length(), 3);
+
+    let b = b.read().await;
+    assert_eq!(b.fork_proof(), Some(proof));
+    assert_eq!(b.len(), 3);
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_fork_same_length() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let public = keypair.pk;
+    let mut a = Core::new(
+        IndexAccessMemory::default(),
+        public,
+        Some(keypair.sk.clone()),
+    )
+    .await?;
+    let mut fork = Core::new(IndexAccessMemory::default(), public, Some(keypair.sk)).await?;
+    for &d in b"abc" {
+        a.append(&[d], None).await?;
+    }
+    for &d in b"abX" {
+        fork.append(&[d], None).await?;
+    }
+
+    // b has the same length as the fork, no block is exchanged
+    let mut b = new_replica(public).await?;
+    for i in 0..a.len() {
+        let (data, signature) = a.get(i).await?.unwrap();
+        b.append(&data, Some(signature)).await?;
+    }
+    let fork = Arc::new(RwLock::new(fork));
+    let fork_replica = Box::new(CoreReplica::new(Arc::clone(&fork)));
+    let b = Arc::new(RwLock::new(b));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+    let ((fork_replication, mut fork_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (rfork, rb) = zip(
+        task::spawn(async move {
+            fork_handle.open(&public, fork_replica).unwrap();
+            fork_replication.run().await
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await
+        }),
+    )
+    .await;
+
+    // both sides compare the heads, the first to find the fork closes the link
+    let err = match (rfork?, rb?) {
+        (Err(err), _) | (_, Err(err)) => err,
+        _ => panic!("Fork was not detected."),
+    };
+    let proof = err.downcast_ref::<ForkError>().unwrap().proof();
+    assert_eq!(proof.length(), 3);
+    let (fork, b) = (fork.read().await, b.read().await);
+    assert!(fork.fork_proof().or(b.fork_proof()).is_some());
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_rotation() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let rotated = KeyPair::generate();
+    let public = keypair.pk;
+    let mut a = Core::new(IndexAccessMemory::default(), public, Some(keypair.sk)).await?;
+    a.append(b"a", None).await?;
+    a.rotate(rotated.sk).await?;
+    a.append(b"b", None).await?;
+
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(new_replica(public).await?));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+    let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (ra, rb) = zip(
+        task::spawn(async move {
+            a_handle.open(&public, a_replica).unwrap();
+            a_replication.run().await.unwrap();
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await.unwrap();
+        }),
+    )
+    .await;
+    ra?;
+    rb?;
+
+    // the replica follows the rotation from the public key of the `Core`
+    let b = b.read().await;
+    assert_eq!(b.len(), 3);
+    assert_eq!(b.writer_key(), &rotated.pk);
+    assert_eq!(b.get(2).await?.unwrap().0, b"b");
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_refetch() -> Result<()> {
+    let mut a = new_core().await?;
+    let public = a.public_key().clone();
+    let mut b = new_replica(public.clone()).await?;
+
+    let data = b"hello world";
+    for &d in data.into_iter() {
+        a.append(&[d], None).await?;
+    }
+    for i in 0..a.len() {
+        let (data, signature) = a.get(i).await?.unwrap();
+        b.append(&data, Some(signature)).await?;
+    }
+    b.clear(2..8).await?;
+    b.refetch(3..5).await?;
+    assert_eq!(b.next_refetch(), Some(3));
+
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+    let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (ra, rb) = zip(
+        task::spawn(async move {
+            a_handle.open(&public, a_replica).unwrap();
+            a_replication.run().await
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await
+        }),
+    )
+    .await;
+    ra??;
+    rb??;
+
+    let b = b.read().await;
+    assert_eq!(b.next_refetch(), None);
+    for i in [0, 1, 3, 4, 8, 9, 10] {
+        assert_eq!(b.get(i).await?.unwrap().0[0], data[i as usize]);
+    }
+    for i in [2, 5, 6, 7] {
+        assert!(b.get(i).await.unwrap_err().is::<ClearedError>());
+    }
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_refetch_cleared() -> Result<()> {
+    let mut a = new_core().await?;
+    let public = a.public_key().clone();
+    let mut b = new_replica(public.clone()).await?;
+
+    let data = b"hello world";
+    for &d in data.into_iter() {
+        a.append(&[d], None).await?;
+    }
+    for i in 0..6 {
+        let (data, signature) = a.get(i).await?.unwrap();
+        b.append(&data, Some(signature)).await?;
+    }
+    a.clear(2..3).await?;
+    b.clear(2..3).await?;
+    b.clear(5..6).await?;
+    b.refetch(0..6).await?;
+    assert_eq!(b.next_refetch(), Some(2));
+
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+    let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (ra, rb) = zip(
+        task::spawn(async move {
+            a_handle.open(&public, a_replica).unwrap();
+            a_replication.run().await
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await
+        }),
+    )
+    .await;
+    ra??;
+    rb??;
+
+    // the block cleared on both sides is skipped and stays marked
+    let b = b.read().await;
+    assert_eq!(b.len(), 11);
+    assert_eq!(b.next_refetch(), Some(2));
+    for i in [0, 1, 3, 4, 5, 6, 7, 8, 9, 10] {
+        assert_eq!(b.get(i).await?.unwrap().0[0], data[i as usize]);
+    }
+    assert!(b.get(2).await.unwrap_err().is::<ClearedError>());
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_schema() -> Result<()> {
+    let mut a = new_core().await?;
+    let public = a.public_key().clone();
+    a.set_schema("json:message").await?;
+    a.append(b"{}", None).await?;
+    let a = Arc::new(RwLock::new(a));
+
+    for (expected, ok) in [(None, true), (Some("json:other"), false)] {
+        let mut b = new_replica(public.clone()).await?;
+        if let Some(schema) = expected {
+            b.set_schema(schema).await?;
+        }
+        let a_replica = Box::new(CoreReplica::new(Arc::clone(&a)));
+        let b = Arc::new(RwLock::new(b));
+        let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+        let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+            create_replication_pair_memory().await;
+        let (_, rb) = zip(
+            task::spawn(async move {
+                a_handle.open(&public, a_replica).unwrap();
+                a_replication.run().await
+            }),
+            task::spawn(async move {
+                b_handle.open(&public, b_replica).unwrap();
+                b_replication.run().await
+            }),
+        )
+        .await;
+        assert_eq!(rb?.is_ok(), ok);
+
+        // the replica adopts the signed schema
+        let b = b.read().await;
+        if ok {
+            assert_eq!(b.schema(), Some("json:message"));
+            assert!(b.schema_signature().is_some());
+            assert_eq!(b.len(), 1);
+        } else {
+            assert_eq!(b.schema(), Some("json:other"));
+            assert_eq!(b.len(), 0);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_write_error() -> Result<()> {
+    let mut a = new_core().await?;
+    let public = a.public_key().clone();
+    let (b, faults) = new_fault_replica(public.clone()).await?;
+
+    let data = b"hello world";
+    for &d in data.into_iter() {
+        a.append(&[d], None).await?;
+    }
+    // writes after the header and 3 blocks with their trees fail
+    faults.inject(Injection::new(
+        Operation::Write,
+        Trigger::After(1 + 2 * 3),
+        Fault::Error,
+    ));
+
+    let a = Arc::new(RwLock::new(a));
+    let b = Arc::new(RwLock::new(b));
+    for synced in [false, true] {
+        let a_replica = Box::new(CoreReplica::new(Arc::clone(&a)));
+        let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+        let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+            create_replication_pair_memory().await;
+        let (_, rb) = zip(
+            task::spawn(async move {
+                a_handle.open(&public, a_replica).unwrap();
+                a_replication.run().await
+            }),
+            task::spawn(async move {
+                b_handle.open(&public, b_replica).unwrap();
+                b_replication.run().await
+            }),
+        )
+        .await;
+
+        let b = b.read().await;
+        if synced {
+            rb??;
+            assert_eq!(b.len(), 11);
+        } else {
+            let err = rb?.unwrap_err();
+            assert!(err.is::<InjectedError>());
+            assert_eq!(b.len(), 3);
+            // replication continues once the storage recovers
+            faults.clear();
+        }
+        for i in 0..b.len() {
+            assert_eq!(b.get(i).await?.unwrap().0[0], data[i as usize]);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+async fn replication_core_replica_read_error() -> Result<()> {
+    let storage = IndexAccessFault::new(IndexAccessMemory::default());
+    let faults = storage.faults();
+    let keypair = KeyPair::generate();
+    let mut a = Core::new(storage, keypair.pk, Some(keypair.sk)).await?;
+    let public = a.public_key().clone();
+    let b = new_replica(public.clone()).await?;
+
+    let data = b"hello world";
+    for &d in data.into_iter() {
+        a.append(&[d], None).await?;
+    }
+    // block 4 can not be read
+    faults.inject(Injection::new(Operation::Read, Trigger::Index(5), Fault::Error));
+
+    let a_replica = Box::new(CoreReplica::new(Arc::new(RwLock::new(a))));
+    let b = Arc::new(RwLock::new(b));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(&b)));
+
+    let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (ra, _) = zip(
+        task::spawn(async move {
+            a_handle.open(&public, a_replica).unwrap();
+            a_replication.run().await
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await
+        }),
+    )
+    .await;
+    let err = ra?.unwrap_err();
+    assert_eq!(
+        err.downcast_ref(),
+        Some(&InjectedError::new(Operation::Read, 5))
+    );
+
+    let b = b.read().await;
+    assert_eq!(b.len(), 4);
+    Ok(())
+}
+
+#[test]
+async fn replication_key_value() -> Result<()> {
+    let a = new_core().await?;
+    let public = a.public_key().clone();
+    let b = new_replica(public.clone()).await?;
+
+    let a = KeyValue::new(Arc::new(RwLock::new(a)));
+    for i in 0..20u8 {
+        a.put(&[i], &[i, i]).await?;
+    }
+    let a_replica = Box::new(CoreReplica::new(Arc::clone(a.core())));
+    let b = KeyValue::new(Arc::new(RwLock::new(b)));
+    let b_replica = Box::new(CoreReplica::new(Arc::clone(b.core())));
+
+    let ((a_replication, mut a_handle), (b_replication, mut b_handle)) =
+        create_replication_pair_memory().await;
+    let (ra, rb) = zip(
+        task::spawn(async move {
+            a_handle.open(&public, a_replica).unwrap();
+            a_replication.run().await.unwrap();
+        }),
+        task::spawn(async move {
+            b_handle.open(&public, b_replica).unwrap();
+            b_replication.run().await.unwrap();
+        }),
+    )
+    .await;
+    ra?;
+    rb?;
+
+    assert_eq!(b.get(&[7]).await?, Some(vec![7, 7]));
+    assert_eq!(b.range(..).await?, a.range(..).await?);
+    Ok(())
+}
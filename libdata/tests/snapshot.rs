@@ -0,0 +1,130 @@
+use anyhow::Result;
+use futures_lite::stream::StreamExt;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::test;
+
+use index_access_memory::IndexAccessMemory;
+use libdata::{verify_proof, ClearedError, Core, CoreSnapshot, KeyPair, Signature};
+
+#[test]
+async fn snapshot_pinned() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await?;
+    for i in 0..3u8 {
+        core.append(&[i], None).await?;
+    }
+    let roots = core.roots(3).await?;
+    let core = Arc::new(RwLock::new(core));
+
+    let snapshot = CoreSnapshot::new(Arc::clone(&core)).await;
+    let clone = snapshot.clone();
+    for i in 3..6u8 {
+        core.write().await.append(&[i], None).await?;
+    }
+
+    assert_eq!(snapshot.len(), 3);
+    assert_eq!(snapshot.snapshot().roots(), roots.as_slice());
+    assert_eq!(snapshot.get(2).await?.unwrap().0, vec![2]);
+    assert!(snapshot.get(3).await?.is_none());
+    assert!(clone.get(4).await?.is_none());
+    assert_eq!(core.read().await.get(4).await?.unwrap().0, vec![4]);
+
+    let data = snapshot
+        .iter(0)
+        .map(|(_, data)| data)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(data, vec![vec![0], vec![1], vec![2]]);
+
+    let proof = snapshot.proof(1).await?;
+    verify_proof(&keypair.pk, &keypair.pk, &[], &[1], &proof)?;
+    assert!(proof.length() <= snapshot.len());
+    assert!(snapshot.proof(3).await.is_err());
+    Ok(())
+}
+
+#[test]
+async fn snapshot_concurrent_reads() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await?;
+    let core = Arc::new(RwLock::new(core));
+    for i in 0..10u8 {
+        core.write().await.append(&[i], None).await?;
+    }
+    let snapshot = CoreSnapshot::new(Arc::clone(&core)).await;
+
+    let writer = {
+        let core = Arc::clone(&core);
+        tokio::spawn(async move {
+            for i in 10..50u8 {
+                core.write().await.append(&[i], None).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+    for _ in 0..5 {
+        let count = snapshot.iter(0).count().await;
+        assert_eq!(count, 10);
+    }
+    writer.await?;
+    assert_eq!(snapshot.iter(0).count().await, 10);
+    assert_eq!(core.read().await.len(), 50);
+    Ok(())
+}
+
+#[test]
+async fn snapshot_rolled_back() -> Result<()> {
+    let keypair = KeyPair::generate();
+    let mut core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await?;
+    core.enable_checkpoints(2).await?;
+    for i in 0..4u8 {
+        core.append(&[i], None).await?;
+    }
+    let mut replica = Core::new(IndexAccessMemory::default(), keypair.pk, None).await?;
+    replica.enable_checkpoints(0).await?;
+    for i in 0..2 {
+        let (data, signature) = core.get(i).await?.unwrap();
+        replica.append(&data, Some(signature)).await?;
+    }
+    // block 0 replayed at index 2, only caught by the next checkpoint
+    let (data, signature) = core.get(0).await?.unwrap();
+    let signature = Signature::without_tree(*signature.data());
+    replica.append(&data, Some(signature)).await?;
+    let replica = Arc::new(RwLock::new(replica));
+    let snapshot = CoreSnapshot::new(Arc::clone(&replica)).await;
+    assert_eq!(snapshot.len(), 3);
+
+    let (data, signature) = core.get(3).await?.unwrap();
+    assert!(replica.write().await.append(&data, Some(signature)).await.is_err());
+    for i in 2..4 {
+        let (data, signature) = core.get(i).await?.unwrap();
+        replica.write().await.append(&data, Some(signature)).await?;
+    }
+    assert_eq!(replica.read().await.len(), 4);
+
+    // the replaced block 2 is not read through the snapshot
+    assert_eq!(snapshot.get(1).await?.unwrap().0, vec![1]);
+    assert!(snapshot.get(2).await.is_err());
+
+    // cleared blocks are reported
+    replica.write().await.clear(1..2).await?;
+    let err = snapshot.get(1).await.unwrap_err();
+    assert!(err.is::<ClearedError>());
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use futures_lite::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::test;
+
+use index_access_memory::IndexAccessMemory;
+use libdata::typed::{Codec, Json, Postcard, Protobuf, TypedCore};
+use libdata::{Core, KeyPair};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Message {
+    id: u32,
+    text: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProtoMessage {
+    #[prost(uint32, tag = "1")]
+    id: u32,
+    #[prost(string, tag = "2")]
+    text: String,
+}
+
+async fn new_core() -> Result<Arc<RwLock<Core<IndexAccessMemory>>>> {
+    let keypair = KeyPair::generate();
+    let core = Core::new(
+        IndexAccessMemory::default(),
+        keypair.pk,
+        Some(keypair.sk),
+    )
+    .await?;
+    Ok(Arc::new(RwLock::new(core)))
+}
+
+async fn roundtrip<C>(codec: C, items: Vec<C::Item>) -> Result<()>
+where
+    C: Codec,
+    C::Item: PartialEq + std::fmt::Debug,
+{
+    let schema = codec.schema();
+    let core = TypedCore::new(new_core().await?, codec).await?;
+    for item in &items {
+        core.append(item).await?;
+    }
+    assert_eq!(core.core().read().await.schema(), Some(schema.as_str()));
+    assert_eq!(core.get(1).await?.as_ref(), items.get(1));
+    assert!(core.get(items.len() as u32).await?.is_none());
+
+    let read = core
+        .iter(0)
+        .map(|item| item.map(|(_, item)| item))
+        .collect::<Vec<Result<C::Item>>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<C::Item>>>()?;
+    assert_eq!(read, items);
+    Ok(())
+}
+
+fn messages() -> Vec<Message> {
+    (0..3)
+        .map(|id| Message {
+            id,
+            text: format!("message {}", id),
+        })
+        .collect()
+}
+
+#[test]
+async fn typed_json() -> Result<()> {
+    roundtrip(Json::new("message"), messages()).await
+}
+
+#[test]
+async fn typed_postcard() -> Result<()> {
+    roundtrip(Postcard::new("message"), messages()).await
+}
+
+#[test]
+async fn typed_protobuf() -> Result<()> {
+    let messages = messages()
+        .into_iter()
+        .map(|message| ProtoMessage {
+            id: message.id,
+            text: message.text,
+        })
+        .collect();
+    roundtrip(Protobuf::<ProtoMessage>::new("message"), messages).await
+}
+
+#[test]
+async fn typed_schema_mismatch() -> Result<()> {
+    let core = new_core().await?;
+    let typed = TypedCore::new(Arc::clone(&core), Json::<Message>::new("message")).await?;
+    typed.append(&messages()[0]).await?;
+    drop(typed);
+
+    assert!(
+        TypedCore::new(Arc::clone(&core), Postcard::<Message>::new("message"))
+            .await
+            .is_err()
+    );
+    assert!(
+        TypedCore::new(Arc::clone(&core), Json::<Message>::new("other"))
+            .await
+            .is_err()
+    );
+    let typed = TypedCore::new(core, Json::<Message>::new("message")).await?;
+    assert_eq!(typed.get(0).await?, Some(messages()[0].clone()));
+    Ok(())
+}
+
+#[test]
+async fn typed_iter_decode_error() -> Result<()> {
+    let core = new_core().await?;
+    let typed = TypedCore::new(Arc::clone(&core), Json::<Message>::new("message")).await?;
+    typed.append(&messages()[0]).await?;
+    core.write().await.append(b"not json", None).await?;
+
+    assert!(typed.get(1).await.is_err());
+    let read = typed.iter(0).collect::<Vec<_>>().await;
+    assert_eq!(read.len(), 2);
+    assert!(read[0].is_ok());
+    assert!(read[1].is_err());
+    Ok(())
+}
+
+#[test]
+async fn typed_existing_blocks() -> Result<()> {
+    let core = new_core().await?;
+    let data = serde_json::to_vec(&messages()[0])?;
+    core.write().await.append(&data, None).await?;
+    assert!(
+        TypedCore::new(Arc::clone(&core), Postcard::<Message>::new("message"))
+            .await
+            .is_err()
+    );
+    assert_eq!(core.read().await.schema(), None);
+
+    let typed = TypedCore::new(Arc::clone(&core), Json::<Message>::new("message")).await?;
+    assert_eq!(typed.get(0).await?, Some(messages()[0].clone()));
+    assert!(core.read().await.schema_signature().is_some());
+    Ok(())
+}
+
+#[test]
+async fn typed_key_rotation() -> Result<()> {
+    let core = new_core().await?;
+    let typed = TypedCore::new(Arc::clone(&core), Json::<Message>::new("message")).await?;
+    typed.append(&messages()[0]).await?;
+    core.write().await.rotate(KeyPair::generate().sk).await?;
+    typed.append(&messages()[1]).await?;
+
+    // the rotation holds no record
+    assert_eq!(typed.get(1).await?, None);
+    assert_eq!(typed.get(2).await?, Some(messages()[1].clone()));
+    let read = typed.iter(0).try_collect::<_, _, Vec<_>>().await?;
+    assert_eq!(
+        read,
+        vec![(0, messages()[0].clone()), (2, messages()[1].clone())]
+    );
+    Ok(())
+}
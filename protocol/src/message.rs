@@ -309,12 +309,18 @@ mod tests {
             }),
             Message::Request(Request {
                 index: 0,
+                signed_head: Some(true),
             }),
             Message::Data(Data {
                 index: 1,
                 data: vec![0u8; 10],
                 data_signature: vec![1u8; 32],
                 tree_signature: vec![2u8; 32],
+                signature_scheme: Some(1),
+                schema: Some("json:message".to_owned()),
+                schema_signature: Some(vec![3u8; 64]),
+                key_rotation: Some(true),
+                signed_head: Some(vec![4u8; 16]),
             })
         };
     }